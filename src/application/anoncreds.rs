@@ -0,0 +1,876 @@
+/*
+  Copyright (c) 2018-present evan GmbH.
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Conversion of schemas, credential definitions, revocation registry definitions, credentials,
+//! proof requests and proof presentations from and to the JSON structures used by Hyperledger
+//! Indy/AnonCreds, e.g. to answer proof requests of Aries agents or verify their presentations.
+//!
+//! Both use Ursa CL signatures with a `master_secret` as non credential attribute and encode values
+//! as described in Aries RFC 0036, so key material, signatures and proofs are taken over as they are.
+//! The following limitations apply:
+//!
+//! - AnonCreds revocation registry definitions reference a tails file by `tailsLocation` and
+//!   `tailsHash`, while `RevocationRegistryDefinition`s embed a tails generator. Tails files have to
+//!   be created and published separately, AnonCreds revocation registry definitions cannot be
+//!   converted into `RevocationRegistryDefinition`s.
+//! - Predicates and self attested attributes are not supported.
+//! - AnonCreds allows answering multiple referents with different credentials of the same schema,
+//!   proof requests of this crate request exactly one credential per schema.
+//! - Revocation registry states are referenced by `timestamp` in AnonCreds and by `version` here,
+//!   so exported proofs do not contain timestamps.
+//! - Imported presentations have to present credentials in the order of the proof request and
+//!   reveal attributes as attribute groups (`names`), as requested by `proof_request_to_anoncreds`.
+//!   Presented credentials have no ID or issuance date in AnonCreds, so generated IDs and empty
+//!   issuance dates are used.
+
+use crate::{
+    application::datatypes::{
+        AggregatedProof,
+        AnonCredsAttributeInfo,
+        AnonCredsCredential,
+        AnonCredsCredentialDefinition,
+        AnonCredsCredentialDefinitionValue,
+        AnonCredsIdentifier,
+        AnonCredsProof,
+        AnonCredsProofData,
+        AnonCredsProofRequest,
+        AnonCredsRequestedProof,
+        AnonCredsRestriction,
+        AnonCredsRevealedAttribute,
+        AnonCredsRevealedAttributeGroup,
+        AnonCredsRevocationRegistry,
+        AnonCredsRevocationRegistryDefinition,
+        AnonCredsRevocationRegistryDefinitionValue,
+        AnonCredsRevocationRegistryPublicKeys,
+        AnonCredsSchema,
+        Credential,
+        CredentialDefinition,
+        CredentialRestriction,
+        CredentialSchema,
+        CredentialSchemaReference,
+        CredentialSignature,
+        CredentialSubProof,
+        CredentialSubject,
+        ProofCredential,
+        ProofPresentation,
+        ProofRequest,
+        RevocationRegistryDefinition,
+        SchemaProperty,
+        SubProofRequest,
+        CL_TYPE,
+        PROOF_VERSION,
+    },
+    utils::utils::{generate_uuid, get_now_as_iso_string},
+};
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    error::Error,
+};
+use ursa::cl::{CredentialKeyCorrectnessProof, Nonce, SubProof};
+
+const ANONCREDS_VERSION: &str = "1.0";
+const W3C_CONTEXT: &str = "https://www.w3.org/2018/credentials/v1";
+
+/// Converts a credential schema into an AnonCreds schema. Credential schemas are not versioned,
+/// so the version of the AnonCreds schema has to be given.
+///
+/// # Arguments
+/// * `schema` - Schema to convert
+/// * `version` - Version of the AnonCreds schema, e.g. "1.0"
+///
+/// # Returns
+/// * `AnonCredsSchema` - The converted schema, with the schema's properties as attribute names
+pub fn schema_to_anoncreds(schema: &CredentialSchema, version: &str) -> AnonCredsSchema {
+    let mut attr_names: Vec<String> = schema.properties.keys().cloned().collect();
+    attr_names.sort();
+
+    AnonCredsSchema {
+        ver: ANONCREDS_VERSION.to_owned(),
+        id: schema.id.to_owned(),
+        name: schema.name.to_owned(),
+        version: version.to_owned(),
+        attr_names,
+        seq_no: None,
+    }
+}
+
+/// Converts an AnonCreds schema into a credential schema. All attributes are required strings.
+///
+/// # Arguments
+/// * `schema` - AnonCreds schema to convert
+/// * `author` - DID of the schema's author
+///
+/// # Returns
+/// * `CredentialSchema` - The converted schema
+pub fn schema_from_anoncreds(schema: AnonCredsSchema, author: &str) -> CredentialSchema {
+    let mut properties: HashMap<String, SchemaProperty> = HashMap::new();
+    for attr_name in &schema.attr_names {
+        properties.insert(
+            attr_name.to_owned(),
+            SchemaProperty {
+                r#type: "string".to_owned(),
+                format: None,
+                items: None,
+            },
+        );
+    }
+
+    CredentialSchema {
+        id: schema.id,
+        r#type: "EvanVCSchema".to_owned(),
+        name: schema.name,
+        author: author.to_owned(),
+        created_at: get_now_as_iso_string(),
+        description: String::new(),
+        properties,
+        required: schema.attr_names,
+        additional_properties: false,
+        proof: None,
+    }
+}
+
+/// Converts a credential definition into an AnonCreds credential definition.
+///
+/// # Arguments
+/// * `definition` - Credential definition to convert
+///
+/// # Returns
+/// * `AnonCredsCredentialDefinition` - The converted definition, referencing the schema by its DID
+pub fn credential_definition_to_anoncreds(
+    definition: &CredentialDefinition,
+) -> Result<AnonCredsCredentialDefinition, Box<dyn Error>> {
+    let mut public_key = serde_json::to_value(&definition.public_key)?;
+    let revocation = match public_key["r_key"].take() {
+        Value::Null => None,
+        revocation => Some(revocation),
+    };
+
+    Ok(AnonCredsCredentialDefinition {
+        ver: ANONCREDS_VERSION.to_owned(),
+        id: definition.id.to_owned(),
+        schema_id: definition.schema.to_owned(),
+        r#type: CL_TYPE.to_owned(),
+        tag: "default".to_owned(),
+        value: AnonCredsCredentialDefinitionValue {
+            primary: public_key["p_key"].take(),
+            revocation,
+        },
+    })
+}
+
+/// Converts an AnonCreds credential definition into a credential definition.
+///
+/// # Arguments
+/// * `definition` - AnonCreds credential definition to convert
+/// * `issuer` - DID of the definition's issuer
+/// * `key_correctness_proof` - Correctness proof of the definition's public key, AnonCreds issuers
+///   send it with their credential offers
+///
+/// # Returns
+/// * `CredentialDefinition` - The converted definition
+pub fn credential_definition_from_anoncreds(
+    definition: AnonCredsCredentialDefinition,
+    issuer: &str,
+    key_correctness_proof: CredentialKeyCorrectnessProof,
+) -> Result<CredentialDefinition, Box<dyn Error>> {
+    if definition.r#type != CL_TYPE {
+        return Err(Box::from(format!(
+            "credential definition {} has unsupported type {}",
+            &definition.id, &definition.r#type,
+        )));
+    }
+    let public_key = serde_json::from_value(json!({
+        "p_key": definition.value.primary,
+        "r_key": definition.value.revocation,
+    }))
+    .map_err(|e| format!("invalid public key in credential definition; {}", &e))?;
+
+    Ok(CredentialDefinition {
+        id: definition.id,
+        r#type: "EvanZKPCredentialDefinition".to_owned(),
+        issuer: issuer.to_owned(),
+        schema: definition.schema_id,
+        created_at: get_now_as_iso_string(),
+        public_key,
+        public_key_correctness_proof: key_correctness_proof,
+        proof: None,
+    })
+}
+
+/// Converts a revocation registry definition into an AnonCreds revocation registry definition and
+/// its current AnonCreds revocation registry. As the tails generator of `RevocationRegistryDefinition`s
+/// cannot be referenced by AnonCreds, a tails file has to be created and published separately.
+///
+/// # Arguments
+/// * `definition` - Revocation registry definition to convert
+/// * `tails_location` - Location of the published tails file
+/// * `tails_hash` - Hash of the published tails file
+///
+/// # Returns
+/// * `AnonCredsRevocationRegistryDefinition` - The converted definition
+/// * `AnonCredsRevocationRegistry` - The current accumulator of the definition
+pub fn revocation_registry_definition_to_anoncreds(
+    definition: &RevocationRegistryDefinition,
+    tails_location: &str,
+    tails_hash: &str,
+) -> Result<
+    (
+        AnonCredsRevocationRegistryDefinition,
+        AnonCredsRevocationRegistry,
+    ),
+    Box<dyn Error>,
+> {
+    let anoncreds_definition = AnonCredsRevocationRegistryDefinition {
+        ver: ANONCREDS_VERSION.to_owned(),
+        id: definition.id.to_owned(),
+        revoc_def_type: "CL_ACCUM".to_owned(),
+        tag: "default".to_owned(),
+        cred_def_id: definition.credential_definition.to_owned(),
+        value: AnonCredsRevocationRegistryDefinitionValue {
+            // revocation registries are always created with issuance by default
+            issuance_type: "ISSUANCE_BY_DEFAULT".to_owned(),
+            max_cred_num: definition.maximum_credential_count,
+            public_keys: AnonCredsRevocationRegistryPublicKeys {
+                accum_key: definition.revocation_public_key.clone(),
+            },
+            tails_hash: tails_hash.to_owned(),
+            tails_location: tails_location.to_owned(),
+        },
+    };
+    let registry = AnonCredsRevocationRegistry {
+        ver: ANONCREDS_VERSION.to_owned(),
+        value: serde_json::from_str(&serde_json::to_string(&definition.registry)?)?,
+    };
+
+    Ok((anoncreds_definition, registry))
+}
+
+/// Converts a credential into an AnonCreds credential.
+///
+/// # Arguments
+/// * `credential` - Credential to convert
+///
+/// # Returns
+/// * `AnonCredsCredential` - The converted credential
+pub fn credential_to_anoncreds(credential: Credential) -> AnonCredsCredential {
+    AnonCredsCredential {
+        schema_id: credential.credential_schema.id,
+        cred_def_id: credential.proof.credential_definition,
+        rev_reg_id: Some(credential.proof.revocation_registry_definition),
+        values: credential.credential_subject.data,
+        signature: credential.proof.signature,
+        signature_correctness_proof: credential.proof.signature_correctness_proof,
+        rev_reg: None,
+        witness: None,
+    }
+}
+
+/// Converts an AnonCreds credential into a credential. AnonCreds credentials do not contain
+/// metadata like ID, issuer or subject, so these have to be given. Only credentials with revocation
+/// support can be converted.
+///
+/// # Arguments
+/// * `credential` - AnonCreds credential to convert
+/// * `id` - ID of the credential
+/// * `issuer` - DID of the issuer
+/// * `subject` - DID of the subject
+/// * `issuance_date` - Date of issuance
+/// * `issuance_nonce` - Nonce of the credential request the credential has been issued for
+///
+/// # Returns
+/// * `Credential` - The converted credential
+pub fn credential_from_anoncreds(
+    credential: AnonCredsCredential,
+    id: &str,
+    issuer: &str,
+    subject: &str,
+    issuance_date: &str,
+    issuance_nonce: Nonce,
+) -> Result<Credential, Box<dyn Error>> {
+    let revocation_registry_definition = credential
+        .rev_reg_id
+        .ok_or("credentials without revocation registry are not supported")?;
+    let revocation_id = serde_json::to_value(&credential.signature)?["r_credential"]["i"]
+        .as_u64()
+        .ok_or("credential signature does not contain a revocation id")?;
+    let revocation_id = u32::try_from(revocation_id)
+        .map_err(|_| format!("revocation id {} is out of range", revocation_id))?;
+
+    Ok(Credential {
+        context: vec![W3C_CONTEXT.to_owned()],
+        id: id.to_owned(),
+        r#type: vec!["VerifiableCredential".to_owned()],
+        issuer: issuer.to_owned(),
+        issuance_date: issuance_date.to_owned(),
+        expiration_date: None,
+        credential_subject: CredentialSubject {
+            id: subject.to_owned(),
+            data: credential.values,
+        },
+        credential_schema: CredentialSchemaReference {
+            id: credential.schema_id,
+            r#type: "EvanZKPSchema".to_owned(),
+        },
+        proof: CredentialSignature {
+            r#type: "CLSignature2019".to_owned(),
+            credential_definition: credential.cred_def_id,
+            signature: credential.signature,
+            signature_correctness_proof: credential.signature_correctness_proof,
+            issuance_nonce,
+            revocation_id,
+            revocation_registry_definition,
+        },
+    })
+}
+
+/// Converts a proof request into an AnonCreds proof request, requesting the revealed attributes of
+/// each sub proof request as an attribute group.
+///
+/// # Arguments
+/// * `proof_request` - Proof request to convert
+///
+/// # Returns
+/// * `AnonCredsProofRequest` - The converted proof request
+pub fn proof_request_to_anoncreds(
+    proof_request: &ProofRequest,
+) -> Result<AnonCredsProofRequest, Box<dyn Error>> {
+    let mut requested_attributes: BTreeMap<String, AnonCredsAttributeInfo> = BTreeMap::new();
+    for (index, sub_proof_request) in proof_request.sub_proof_requests.iter().enumerate() {
+        let schema_id = Some(sub_proof_request.schema.to_owned());
+        let restrictions = if sub_proof_request.restrictions.is_empty() {
+            vec![AnonCredsRestriction {
+                schema_id,
+                issuer_did: None,
+                cred_def_id: None,
+            }]
+        } else {
+            sub_proof_request
+                .restrictions
+                .iter()
+                .map(|restriction| AnonCredsRestriction {
+                    schema_id: schema_id.clone(),
+                    issuer_did: restriction.issuer.clone(),
+                    cred_def_id: restriction.credential_definition.clone(),
+                })
+                .collect()
+        };
+        requested_attributes.insert(
+            format!("sub_proof_request_{}", index),
+            AnonCredsAttributeInfo {
+                name: None,
+                names: Some(sub_proof_request.revealed_attributes.clone()),
+                restrictions,
+                non_revoked: None,
+            },
+        );
+    }
+
+    Ok(AnonCredsProofRequest {
+        name: proof_request.r#type.to_owned(),
+        version: ANONCREDS_VERSION.to_owned(),
+        nonce: serde_json::from_str(&serde_json::to_string(&proof_request.nonce)?)?,
+        requested_attributes,
+        requested_predicates: BTreeMap::new(),
+        non_revoked: proof_request.non_revoked.clone(),
+    })
+}
+
+/// Converts an AnonCreds proof request into a proof request. Attributes requested from the same
+/// schema are requested in one sub proof request.
+///
+/// # Arguments
+/// * `proof_request` - AnonCreds proof request to convert
+/// * `verifier_did` - DID of the verifier
+/// * `prover_did` - DID of the prover
+///
+/// # Returns
+/// * `ProofRequest` - The converted proof request
+pub fn proof_request_from_anoncreds(
+    proof_request: AnonCredsProofRequest,
+    verifier_did: &str,
+    prover_did: &str,
+) -> Result<ProofRequest, Box<dyn Error>> {
+    if !proof_request.requested_predicates.is_empty() {
+        return Err(Box::from("predicates are not supported"));
+    }
+
+    let mut sub_proof_requests: Vec<SubProofRequest> = Vec::new();
+    for (referent, info) in &proof_request.requested_attributes {
+        if info.non_revoked.is_some() {
+            return Err(Box::from(format!(
+                "referent {} has its own non-revocation interval, which is not supported",
+                referent,
+            )));
+        }
+        let schema = get_referent_schema(referent, info)?;
+        let attributes = get_referent_attributes(referent, info)?;
+        let restrictions: Vec<CredentialRestriction> = if info
+            .restrictions
+            .iter()
+            .any(|r| r.issuer_did.is_none() && r.cred_def_id.is_none())
+        {
+            // a restriction on the schema only allows all credentials of the schema
+            Vec::new()
+        } else {
+            info.restrictions
+                .iter()
+                .map(|r| CredentialRestriction {
+                    issuer: r.issuer_did.clone(),
+                    credential_definition: r.cred_def_id.clone(),
+                })
+                .collect()
+        };
+
+        match sub_proof_requests.iter_mut().find(|s| s.schema == schema) {
+            Some(sub_proof_request) => {
+                if serde_json::to_value(&sub_proof_request.restrictions)?
+                    != serde_json::to_value(&restrictions)?
+                {
+                    return Err(Box::from(format!(
+                        "referent {} restricts schema {} differently than other referents",
+                        referent, &schema,
+                    )));
+                }
+                for attribute in attributes {
+                    if !sub_proof_request.revealed_attributes.contains(&attribute) {
+                        sub_proof_request.revealed_attributes.push(attribute);
+                    }
+                }
+            }
+            None => sub_proof_requests.push(SubProofRequest {
+                schema,
+                revealed_attributes: attributes,
+                restrictions,
+            }),
+        }
+    }
+
+    Ok(ProofRequest {
+        verifier: verifier_did.to_owned(),
+        prover: prover_did.to_owned(),
+        created_at: get_now_as_iso_string(),
+        expires_at: None,
+        nonce: proof_request.nonce,
+        r#type: CL_TYPE.to_owned(),
+        sub_proof_requests,
+        non_revoked: proof_request.non_revoked,
+    })
+}
+
+/// Converts a proof presentation into an AnonCreds proof, answering the referents of the
+/// AnonCreds proof request the presentation has been created for.
+///
+/// # Arguments
+/// * `presentation` - Presentation to convert
+/// * `proof_request` - AnonCreds proof request answered by the presentation
+///
+/// # Returns
+/// * `AnonCredsProof` - The converted proof
+pub fn presentation_to_anoncreds(
+    presentation: ProofPresentation,
+    proof_request: &AnonCredsProofRequest,
+) -> Result<AnonCredsProof, Box<dyn Error>> {
+    if !proof_request.requested_predicates.is_empty() {
+        return Err(Box::from("predicates are not supported"));
+    }
+
+    let mut requested_proof = AnonCredsRequestedProof::default();
+    for (referent, info) in &proof_request.requested_attributes {
+        let schema = get_referent_schema(referent, info)?;
+        let index = presentation
+            .verifiable_credential
+            .iter()
+            .position(|credential| credential.credential_schema.id == schema)
+            .ok_or_else(|| format!("no credential presented for referent {}", referent))?;
+        let data = &presentation.verifiable_credential[index]
+            .credential_subject
+            .data;
+        let get_value = |attribute: &str| {
+            data.get(attribute).cloned().ok_or_else(|| {
+                format!(
+                    "attribute {} of referent {} has not been revealed",
+                    attribute, referent,
+                )
+            })
+        };
+
+        if let Some(name) = &info.name {
+            let value = get_value(name)?;
+            requested_proof.revealed_attrs.insert(
+                referent.to_owned(),
+                AnonCredsRevealedAttribute {
+                    sub_proof_index: index as u32,
+                    raw: value.raw,
+                    encoded: value.encoded,
+                },
+            );
+        } else {
+            let mut values = HashMap::new();
+            for name in get_referent_attributes(referent, info)? {
+                let value = get_value(&name)?;
+                values.insert(name, value);
+            }
+            requested_proof.revealed_attr_groups.insert(
+                referent.to_owned(),
+                AnonCredsRevealedAttributeGroup {
+                    sub_proof_index: index as u32,
+                    values,
+                },
+            );
+        }
+    }
+
+    let mut proofs: Vec<SubProof> = Vec::new();
+    let mut identifiers: Vec<AnonCredsIdentifier> = Vec::new();
+    for credential in presentation.verifiable_credential {
+        identifiers.push(AnonCredsIdentifier {
+            schema_id: credential.credential_schema.id,
+            cred_def_id: credential.proof.credential_definition,
+            rev_reg_id: Some(credential.proof.revocation_registry_definition),
+            timestamp: None,
+        });
+        proofs.push(credential.proof.proof);
+    }
+
+    Ok(AnonCredsProof {
+        proof: AnonCredsProofData {
+            proofs,
+            aggregated_proof: presentation.proof.aggregated_proof,
+        },
+        requested_proof,
+        identifiers,
+    })
+}
+
+/// Converts an AnonCreds proof into a proof presentation, that can be verified against the
+/// proof request it answers.
+///
+/// # Arguments
+/// * `proof` - AnonCreds proof to convert
+/// * `proof_request` - Proof request answered by the proof
+/// * `credential_definitions` - Definitions of the presented credentials, indexed by the according
+///   schema's ID, to take the credentials' issuers from
+///
+/// # Returns
+/// * `ProofPresentation` - The converted presentation
+pub fn presentation_from_anoncreds(
+    proof: AnonCredsProof,
+    proof_request: &ProofRequest,
+    credential_definitions: &HashMap<String, CredentialDefinition>,
+) -> Result<ProofPresentation, Box<dyn Error>> {
+    let requested_proof = proof.requested_proof;
+    if !requested_proof.predicates.is_empty() || !requested_proof.self_attested_attrs.is_empty() {
+        return Err(Box::from(
+            "predicates and self attested attributes are not supported",
+        ));
+    }
+    if !requested_proof.revealed_attrs.is_empty() {
+        return Err(Box::from(
+            "single revealed attributes are not supported, reveal attributes in groups",
+        ));
+    }
+    if proof.proof.proofs.len() != proof.identifiers.len()
+        || proof.identifiers.len() != proof_request.sub_proof_requests.len()
+    {
+        return Err(Box::from(format!(
+            "proof contains {} sub proofs and {} identifiers, but {} have been requested",
+            proof.proof.proofs.len(),
+            proof.identifiers.len(),
+            proof_request.sub_proof_requests.len(),
+        )));
+    }
+
+    let mut verifiable_credential: Vec<ProofCredential> = Vec::new();
+    for (index, (sub_proof, identifier)) in proof
+        .proof
+        .proofs
+        .into_iter()
+        .zip(proof.identifiers.into_iter())
+        .enumerate()
+    {
+        let schema = &proof_request.sub_proof_requests[index].schema;
+        if identifier.schema_id != *schema {
+            return Err(Box::from(format!(
+                "sub proof {} is for schema {}, but schema {} has been requested at this position",
+                index, &identifier.schema_id, schema,
+            )));
+        }
+        let definition = credential_definitions
+            .get(schema)
+            .ok_or_else(|| format!("no credential definition given for schema {}", schema))?;
+        let revocation_registry_definition = identifier
+            .rev_reg_id
+            .ok_or("credentials without revocation registry are not supported")?;
+        let mut data = HashMap::new();
+        for group in requested_proof.revealed_attr_groups.values() {
+            if group.sub_proof_index as usize == index {
+                data.extend(group.values.clone());
+            }
+        }
+
+        verifiable_credential.push(ProofCredential {
+            context: vec![W3C_CONTEXT.to_owned()],
+            id: generate_uuid(),
+            r#type: vec!["VerifiableCredential".to_owned()],
+            issuer: definition.issuer.to_owned(),
+            issuance_date: String::new(),
+            credential_subject: CredentialSubject {
+                id: proof_request.prover.to_owned(),
+                data,
+            },
+            credential_schema: CredentialSchemaReference {
+                id: identifier.schema_id,
+                r#type: "EvanZKPSchema".to_owned(),
+            },
+            proof: CredentialSubProof {
+                credential_definition: identifier.cred_def_id,
+                revocation_registry_definition,
+                revocation_registry_version: None,
+                proof_version: PROOF_VERSION,
+                proof: sub_proof,
+            },
+        });
+    }
+
+    Ok(ProofPresentation {
+        context: vec![W3C_CONTEXT.to_owned()],
+        id: generate_uuid(),
+        r#type: vec!["VerifiablePresentation".to_owned()],
+        verifiable_credential,
+        proof: AggregatedProof {
+            nonce: serde_json::from_str(&serde_json::to_string(&proof_request.nonce)?)?,
+            proof_version: PROOF_VERSION,
+            aggregated_proof: proof.proof.aggregated_proof,
+        },
+        holder_binding: None,
+        assertion_proof: None,
+    })
+}
+
+/// Gets the schema a referent of an AnonCreds proof request is restricted to.
+fn get_referent_schema(
+    referent: &str,
+    info: &AnonCredsAttributeInfo,
+) -> Result<String, Box<dyn Error>> {
+    let schema_ids: Vec<&Option<String>> = info.restrictions.iter().map(|r| &r.schema_id).collect();
+    match schema_ids.first() {
+        Some(Some(schema_id)) if schema_ids.iter().all(|s| s.as_ref() == Some(schema_id)) => {
+            Ok(schema_id.to_owned())
+        }
+        _ => Err(Box::from(format!(
+            "referent {} has to be restricted to exactly one schema",
+            referent,
+        ))),
+    }
+}
+
+/// Gets the attributes requested by a referent of an AnonCreds proof request.
+fn get_referent_attributes(
+    referent: &str,
+    info: &AnonCredsAttributeInfo,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    match (&info.name, &info.names) {
+        (Some(name), None) => Ok(vec![name.to_owned()]),
+        (None, Some(names)) => Ok(names.clone()),
+        _ => Err(Box::from(format!(
+            "referent {} has to request either name or names",
+            referent,
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate utilities;
+
+    use super::*;
+    use ursa::cl::new_nonce;
+    use utilities::test_data::vc_zkp::{
+        EXAMPLE_AGGREGATED_PROOF,
+        EXAMPLE_CREDENTIAL_SCHEMA,
+        EXAMPLE_SUB_PROOF,
+    };
+
+    fn get_proof_request() -> Result<ProofRequest, Box<dyn Error>> {
+        let nonce = new_nonce().map_err(|e| format!("{}", &e))?;
+        Ok(serde_json::from_value(json!({
+            "verifier": "did:evan:verifier",
+            "prover": "did:evan:prover",
+            "createdAt": "2020-01-01T00:00:00.000Z",
+            "nonce": nonce,
+            "type": CL_TYPE,
+            "subProofRequests": [
+                { "schema": "did:evan:schema1", "revealedAttributes": ["name", "age"] },
+                {
+                    "schema": "did:evan:schema2",
+                    "revealedAttributes": ["city"],
+                    "restrictions": [{ "issuer": "did:evan:issuer" }],
+                },
+            ],
+        }))?)
+    }
+
+    fn get_presentation(proof_request: &ProofRequest) -> Result<ProofPresentation, Box<dyn Error>> {
+        let credential = |schema: &str, data: Value| {
+            json!({
+                "@context": [W3C_CONTEXT],
+                "id": generate_uuid(),
+                "type": ["VerifiableCredential"],
+                "issuer": "did:evan:issuer",
+                "issuanceDate": "2020-01-01T00:00:00.000Z",
+                "credentialSubject": { "id": "did:evan:prover", "data": data },
+                "credentialSchema": { "id": schema, "type": "EvanZKPSchema" },
+                "proof": {
+                    "credentialDefinition": format!("{}#definition", schema),
+                    "revocationRegistryDefinition": format!("{}#registry", schema),
+                    // sub proofs in the JSON string form of version 1
+                    "proof": EXAMPLE_SUB_PROOF,
+                },
+            })
+        };
+        Ok(serde_json::from_value(json!({
+            "@context": [W3C_CONTEXT],
+            "id": "presentation",
+            "type": ["VerifiablePresentation"],
+            "verifiableCredential": [
+                credential("did:evan:schema1", json!({
+                    "name": { "raw": "Alice", "encoded": "1" },
+                    "age": { "raw": "42", "encoded": "42" },
+                })),
+                credential("did:evan:schema2", json!({
+                    "city": { "raw": "Berlin", "encoded": "2" },
+                })),
+            ],
+            "proof": { "nonce": proof_request.nonce, "aggregatedProof": EXAMPLE_AGGREGATED_PROOF },
+        }))?)
+    }
+
+    #[test]
+    fn can_convert_schemas_from_and_to_anoncreds() -> Result<(), Box<dyn Error>> {
+        let schema: CredentialSchema = serde_json::from_str(&EXAMPLE_CREDENTIAL_SCHEMA)?;
+        let anoncreds = schema_to_anoncreds(&schema, "2.1");
+        assert_eq!(anoncreds.ver, ANONCREDS_VERSION);
+        assert_eq!(anoncreds.version, "2.1");
+        let mut attr_names: Vec<&String> = schema.properties.keys().collect();
+        attr_names.sort();
+        assert_eq!(anoncreds.attr_names.iter().collect::<Vec<_>>(), attr_names);
+
+        let imported = schema_from_anoncreds(anoncreds, &schema.author);
+        assert_eq!(imported.id, schema.id);
+        assert_eq!(imported.name, schema.name);
+        assert_eq!(imported.properties.len(), schema.properties.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_convert_proof_requests_from_and_to_anoncreds() -> Result<(), Box<dyn Error>> {
+        let proof_request = get_proof_request()?;
+        let anoncreds = proof_request_to_anoncreds(&proof_request)?;
+        let value = serde_json::to_value(&anoncreds)?;
+        assert_eq!(value["nonce"], serde_json::to_value(&proof_request.nonce)?);
+        assert_eq!(
+            value["requested_attributes"]["sub_proof_request_1"],
+            json!({
+                "names": ["city"],
+                "restrictions": [{
+                    "schema_id": "did:evan:schema2",
+                    "issuer_did": "did:evan:issuer",
+                }],
+            }),
+        );
+
+        let imported =
+            proof_request_from_anoncreds(anoncreds, "did:evan:verifier", "did:evan:prover")?;
+        assert_eq!(
+            serde_json::to_value(&imported.sub_proof_requests)?,
+            serde_json::to_value(&proof_request.sub_proof_requests)?,
+        );
+        assert_eq!(
+            serde_json::to_value(&imported.nonce)?,
+            serde_json::to_value(&proof_request.nonce)?,
+        );
+
+        // referents requesting single attributes of the same schema are merged
+        let mut anoncreds = proof_request_to_anoncreds(&proof_request)?;
+        anoncreds.requested_attributes.insert(
+            "name".to_owned(),
+            AnonCredsAttributeInfo {
+                name: Some("address".to_owned()),
+                names: None,
+                restrictions: anoncreds.requested_attributes["sub_proof_request_0"]
+                    .restrictions
+                    .clone(),
+                non_revoked: None,
+            },
+        );
+        let imported =
+            proof_request_from_anoncreds(anoncreds, "did:evan:verifier", "did:evan:prover")?;
+        assert_eq!(imported.sub_proof_requests.len(), 2);
+        assert_eq!(
+            imported.sub_proof_requests[0].revealed_attributes,
+            vec!["address", "name", "age"],
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_convert_presentations_to_anoncreds() -> Result<(), Box<dyn Error>> {
+        let proof_request = get_proof_request()?;
+        let anoncreds_request = proof_request_to_anoncreds(&proof_request)?;
+        let presentation = get_presentation(&proof_request)?;
+
+        let proof = presentation_to_anoncreds(presentation, &anoncreds_request)?;
+        let value = serde_json::to_value(&proof)?;
+        assert_eq!(
+            value["proof"]["proofs"][0],
+            serde_json::from_str::<Value>(EXAMPLE_SUB_PROOF)?,
+        );
+        assert_eq!(
+            value["proof"]["aggregated_proof"],
+            serde_json::from_str::<Value>(EXAMPLE_AGGREGATED_PROOF)?,
+        );
+        assert_eq!(
+            value["requested_proof"]["revealed_attr_groups"]["sub_proof_request_1"],
+            json!({
+                "sub_proof_index": 1,
+                "values": { "city": { "raw": "Berlin", "encoded": "2" } },
+            }),
+        );
+        assert_eq!(
+            value["identifiers"][1]["rev_reg_id"],
+            "did:evan:schema2#registry"
+        );
+
+        // presentations are imported in the order of the proof request
+        let mut reordered = get_proof_request()?;
+        reordered.sub_proof_requests.reverse();
+        match presentation_from_anoncreds(proof, &reordered, &HashMap::new()) {
+            Ok(_) => Err(Box::from("import should have failed")),
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "sub proof 0 is for schema did:evan:schema1, but schema did:evan:schema2 has \
+                     been requested at this position",
+                );
+                Ok(())
+            }
+        }
+    }
+}
@@ -14,14 +14,16 @@
   limitations under the License.
 */
 
-use crate::crypto::crypto_datatypes::AssertionProof;
-use serde::{Deserialize, Serialize};
+use crate::crypto::crypto_datatypes::{AssertionProof, EncryptedData};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
 use std::{
-    collections::{HashMap, HashSet},
-    convert::TryInto,
+    collections::{BTreeMap, HashMap, HashSet},
+    convert::{TryFrom, TryInto},
 };
 use ursa::cl::{
     issuer::Issuer as UrsaIssuer,
+    AggregatedProof as CryptoAggregatedProof,
     BlindedCredentialSecrets,
     BlindedCredentialSecretsCorrectnessProof,
     CredentialKeyCorrectnessProof,
@@ -34,6 +36,7 @@ use ursa::cl::{
     RevocationRegistryDelta,
     RevocationTailsGenerator,
     SignatureCorrectnessProof,
+    SubProof,
     Witness,
 };
 pub use ursa::cl::{
@@ -131,6 +134,12 @@ pub struct CredentialOffer {
     pub schema: String,
     pub credential_definition: String,
     pub nonce: Nonce,
+    #[serde(default)]
+    pub created_at: String,
+    /// Date after which the offer cannot be used for issuance anymore, offers created before
+    /// this field has been added do not have it and cannot be used for issuance
+    #[serde(default)]
+    pub expires_at: String,
 }
 
 /// Message following a `CredentialOffer`, sent by a potential credential prover.
@@ -179,6 +188,8 @@ pub struct CredentialSubject {
 /// A verifiable credential issued by an issuer upon receiving a `CredentialRequest`.
 /// Specifies the signed values, the DID of the prover/subject, the `CredentialSchema`, and the `CredentialSignature`
 /// including revocation info.
+/// The optional `expiration_date` is not part of the signed values and only used by provers to
+/// skip expired credentials.
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Credential {
@@ -188,6 +199,8 @@ pub struct Credential {
     pub r#type: Vec<String>,
     pub issuer: String,
     pub issuance_date: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiration_date: Option<String>,
     pub credential_subject: CredentialSubject,
     pub credential_schema: CredentialSchemaReference,
     pub proof: CredentialSignature,
@@ -197,6 +210,8 @@ pub struct Credential {
 /// The `registry` and `registry_delta` properties need to be updated after every revocation
 /// (and, depending on the type of the revocation registry, after every issuance).
 /// Contains a `DeltaHistory` to let provers update their credential's `Witness` before proving non-revocation.
+/// Older history entries can be compacted into a `DeltaCheckpoint`.
+/// Every update of the registry increments its `version`, starting at 0 for a new definition.
 /// Needs to be stored publicly available and temper-proof.
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -206,7 +221,11 @@ pub struct RevocationRegistryDefinition {
     pub updated_at: String,
     pub registry: RevocationRegistry,
     pub registry_delta: RevocationRegistryDelta,
+    #[serde(default)]
+    pub version: u64,
     pub delta_history: Vec<DeltaHistory>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint: Option<DeltaCheckpoint>,
     pub tails: RevocationTailsGenerator,
     pub revocation_public_key: RevocationKeyPublic,
     pub maximum_credential_count: u32,
@@ -218,18 +237,36 @@ pub struct RevocationRegistryDefinition {
 #[serde(rename_all = "camelCase")]
 pub struct DeltaHistory {
     pub created: u64,
+    #[serde(default)]
+    pub version: u64,
+    pub delta: RevocationRegistryDelta,
+}
+
+/// Merged state of all `DeltaHistory` entries that have been compacted, created at the time and with
+/// the version of the newest compacted entry. Lets provers with a `RevocationState` older than the
+/// remaining `DeltaHistory` catch up with the revocation registry.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DeltaCheckpoint {
+    pub created: u64,
+    #[serde(default)]
+    pub version: u64,
     pub delta: RevocationRegistryDelta,
 }
 
 /// Holds the current `Witness` for a credential. Witnesses need to be updated before creating proofs.
 /// To do this, the prover needs to retrieve the `DeltaHistory` of the relevant `RevocationRegistryDefinition`
-/// and update the witness with all deltas that are newer than the `updated` property of the `RevocationState`.
+/// and update the witness with all deltas with a `version` greater than the `version` of the `RevocationState`.
+/// States without a `version` have been created by older versions and are updated based on their `updated`
+/// timestamp once, after which they track the registry version as well.
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct RevocationState {
     pub credential_id: String,
     pub revocation_id: u32,
     pub updated: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
     pub delta: RevocationRegistryDelta,
     pub witness: Witness,
 }
@@ -250,6 +287,56 @@ pub struct CredentialProposal {
 pub struct SubProofRequest {
     pub schema: String,
     pub revealed_attributes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub restrictions: Vec<CredentialRestriction>,
+}
+
+impl SubProofRequest {
+    /// Checks if credentials with given issuer and credential definition satisfy the restrictions
+    /// of this sub proof request. Requests without restrictions are satisfied by any credential.
+    pub fn is_satisfied_by(&self, issuer: &str, credential_definition: &str) -> bool {
+        self.restrictions.is_empty()
+            || self.restrictions.iter().any(|restriction| {
+                restriction.issuer.as_ref().map_or(true, |i| i == issuer)
+                    && restriction
+                        .credential_definition
+                        .as_ref()
+                        .map_or(true, |d| d == credential_definition)
+            })
+    }
+}
+
+/// Restricts the credentials that can be used for a `SubProofRequest`. A credential has to match
+/// all given properties of at least one of the sub proof request's restrictions.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialRestriction {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_definition: Option<String>,
+}
+
+/// Time range (unix timestamps in seconds) in which the credentials of a proof have to be
+/// non-revoked. Satisfied if the revocation registry state used for a proof has been current at
+/// any point between `from` and `to`. If `from` is omitted, the state has to be current at `to`.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NonRevokedInterval {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<u64>,
+    pub to: u64,
+}
+
+/// Point in time to check non-revocation of a proof against, given as either a unix timestamp
+/// in seconds or a revocation registry version.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RevocationPointInTime {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
 }
 
 /// Message sent by a verifier to prompt a prover to prove one or many assertions.
@@ -259,24 +346,118 @@ pub struct ProofRequest {
     pub verifier: String,
     pub prover: String,
     pub created_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
     pub nonce: Nonce,
     pub r#type: String,
     pub sub_proof_requests: Vec<SubProofRequest>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub non_revoked: Option<NonRevokedInterval>,
 }
 
+/// Format version Ursa proofs of `CredentialSubProof` and `AggregatedProof` are serialized in.
+/// Version 1 embedded proofs as JSON strings, version 2 serializes them as nested objects.
+pub const PROOF_VERSION: u32 = 2;
+
 #[derive(Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", try_from = "SerializedCredentialSubProof")]
 pub struct CredentialSubProof {
     pub credential_definition: String,
     pub revocation_registry_definition: String,
-    pub proof: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revocation_registry_version: Option<u64>,
+    /// Format version of `proof`, see `PROOF_VERSION`
+    pub proof_version: u32,
+    pub proof: SubProof,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
+struct SerializedCredentialSubProof {
+    credential_definition: String,
+    revocation_registry_definition: String,
+    #[serde(default)]
+    revocation_registry_version: Option<u64>,
+    #[serde(default)]
+    proof_version: Option<u32>,
+    proof: Value,
+}
+
+impl TryFrom<SerializedCredentialSubProof> for CredentialSubProof {
+    type Error = String;
+
+    fn try_from(sub_proof: SerializedCredentialSubProof) -> Result<Self, Self::Error> {
+        Ok(CredentialSubProof {
+            credential_definition: sub_proof.credential_definition,
+            revocation_registry_definition: sub_proof.revocation_registry_definition,
+            revocation_registry_version: sub_proof.revocation_registry_version,
+            proof_version: PROOF_VERSION,
+            proof: parse_proof(sub_proof.proof, sub_proof.proof_version)?,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", try_from = "SerializedAggregatedProof")]
 pub struct AggregatedProof {
     pub nonce: Nonce,
-    pub aggregated_proof: String,
+    /// Format version of `aggregated_proof`, see `PROOF_VERSION`
+    pub proof_version: u32,
+    pub aggregated_proof: CryptoAggregatedProof,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SerializedAggregatedProof {
+    nonce: Nonce,
+    #[serde(default)]
+    proof_version: Option<u32>,
+    aggregated_proof: Value,
+}
+
+impl TryFrom<SerializedAggregatedProof> for AggregatedProof {
+    type Error = String;
+
+    fn try_from(proof: SerializedAggregatedProof) -> Result<Self, Self::Error> {
+        Ok(AggregatedProof {
+            nonce: proof.nonce,
+            proof_version: PROOF_VERSION,
+            aggregated_proof: parse_proof(proof.aggregated_proof, proof.proof_version)?,
+        })
+    }
+}
+
+/// Parses an Ursa proof serialized in the given format version, see `PROOF_VERSION`. The version
+/// is only guessed for proofs without one, which are parsed as version 1 if embedded as JSON
+/// strings and as version 2 otherwise.
+///
+/// # Arguments
+/// * `proof` - Serialized proof
+/// * `version` - Format version of `proof`, if given
+fn parse_proof<T>(proof: Value, version: Option<u32>) -> Result<T, String>
+where
+    T: DeserializeOwned,
+{
+    match (version, proof) {
+        (Some(1), Value::String(embedded)) | (None, Value::String(embedded)) => {
+            serde_json::from_str(&embedded)
+                .map_err(|e| format!("invalid proof embedded as JSON string; {}", &e))
+        }
+        (Some(1), _) => Err("proof of version 1 has to be embedded as JSON string".to_owned()),
+        (Some(PROOF_VERSION), proof) | (None, proof) => {
+            serde_json::from_value(proof).map_err(|e| format!("invalid proof; {}", &e))
+        }
+        (Some(version), _) => Err(format!("unsupported proof version {}", version)),
+    }
+}
+
+/// Deserializes Ursa proofs of `W3cSubProof` and `W3cAggregatedProof`, see `parse_proof`.
+fn deserialize_proof<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    parse_proof(Value::deserialize(deserializer)?, None).map_err(serde::de::Error::custom)
 }
 
 /// A single proof of a schema requested in a `ProofRequest` that reveals the requested attributes.
@@ -305,6 +486,12 @@ pub struct ProofPresentation {
     pub r#type: Vec<String>,
     pub verifiable_credential: Vec<ProofCredential>,
     pub proof: AggregatedProof,
+    /// Proof that the presenter controls the prover DID of the proof request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub holder_binding: Option<AssertionProof>,
+    /// Proof over the presentation, covering metadata not covered by the CL proof
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assertion_proof: Option<AssertionProof>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -316,6 +503,17 @@ pub struct ProofVerification {
     pub reason: Option<String>,
 }
 
+/// Revocation status of a credential, either "active" or "revoked", as of the given version of
+/// its revocation registry definition.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevocationStatus {
+    pub revocation_registry_definition: String,
+    pub revocation_id: u32,
+    pub version: u64,
+    pub status: String,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct EncodedCredentialValue {
@@ -323,6 +521,45 @@ pub struct EncodedCredentialValue {
     pub encoded: String,
 }
 
+/// A credential held by a prover together with its revocation state.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeldCredential {
+    pub credential: Credential,
+    pub revocation_state: RevocationState,
+}
+
+/// Credentials selected for a `ProofRequest`, ready to be passed to `Prover::present_proof`.
+/// `revocation_states` contains the updated states the `witnesses` were taken from, indexed by
+/// credential ID, and should be persisted by the prover.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialSelection {
+    pub credentials: HashMap<String, Credential>,
+    pub witnesses: HashMap<String, Witness>,
+    pub revocation_states: HashMap<String, RevocationState>,
+}
+
+/// Versioned, encrypted backup of a prover's master secret and optionally held credentials.
+/// `encrypted` is authenticated, so altered content is rejected on import.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MasterSecretBackup {
+    pub r#type: String,
+    pub version: u32,
+    pub created_at: String,
+    pub encrypted: EncryptedData,
+}
+
+/// Decrypted content of a `MasterSecretBackup`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MasterSecretBackupContent {
+    pub master_secret: MasterSecret,
+    #[serde(default)]
+    pub credentials: Vec<HeldCredential>,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct RevocationIdInformation {
@@ -330,3 +567,405 @@ pub struct RevocationIdInformation {
     pub next_unused_id: u32,
     pub used_ids: HashSet<u32>,
 }
+
+/// Claims of a `W3cCredential` or `W3cProofCredential`, given as raw values next to the subject's ID.
+/// Exported claims are strings, imported claims with other values are converted to their JSON text.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct W3cCredentialSubject {
+    pub id: String,
+    #[serde(flatten)]
+    pub claims: HashMap<String, Value>,
+}
+
+/// Status of a `W3cCredential` or `W3cProofCredential`, pointing at the revocation registry definition
+/// the credential can be revoked in. Proof credentials hide the credential's `revocation_id` and
+/// reference the registry version the proof has been created with instead.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct W3cCredentialStatus {
+    pub id: String,
+    pub r#type: String,
+    pub revocation_registry_definition: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revocation_id: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revocation_registry_version: Option<u64>,
+}
+
+/// CL signature of a `W3cCredential`, i.e. a `CredentialSignature` without revocation information.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct W3cCredentialSignature {
+    pub r#type: String,
+    pub credential_definition: String,
+    pub signature: CryptoCredentialSignature,
+    pub signature_correctness_proof: SignatureCorrectnessProof,
+    pub issuance_nonce: Nonce,
+}
+
+/// `Credential` in the form of the W3C Verifiable Credentials Data Model 1.1.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct W3cCredential {
+    #[serde(rename(serialize = "@context", deserialize = "@context"))]
+    pub context: Vec<String>,
+    pub id: String,
+    pub r#type: Vec<String>,
+    pub issuer: String,
+    pub issuance_date: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiration_date: Option<String>,
+    pub credential_subject: W3cCredentialSubject,
+    pub credential_schema: CredentialSchemaReference,
+    pub credential_status: W3cCredentialStatus,
+    pub proof: W3cCredentialSignature,
+}
+
+/// CL sub proof of a `W3cProofCredential`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct W3cSubProof {
+    pub r#type: String,
+    pub credential_definition: String,
+    #[serde(deserialize_with = "deserialize_proof")]
+    pub proof: SubProof,
+}
+
+/// `ProofCredential` in the form of the W3C Verifiable Credentials Data Model 1.1.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct W3cProofCredential {
+    #[serde(rename(serialize = "@context", deserialize = "@context"))]
+    pub context: Vec<String>,
+    pub id: String,
+    pub r#type: Vec<String>,
+    pub issuer: String,
+    pub issuance_date: String,
+    pub credential_subject: W3cCredentialSubject,
+    pub credential_schema: CredentialSchemaReference,
+    pub credential_status: W3cCredentialStatus,
+    pub proof: W3cSubProof,
+}
+
+/// Aggregated CL proof of a `W3cPresentation`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct W3cAggregatedProof {
+    pub r#type: String,
+    pub nonce: Nonce,
+    #[serde(deserialize_with = "deserialize_proof")]
+    pub aggregated_proof: CryptoAggregatedProof,
+}
+
+/// `ProofPresentation` in the form of the W3C Verifiable Credentials Data Model 1.1.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct W3cPresentation {
+    #[serde(rename(serialize = "@context", deserialize = "@context"))]
+    pub context: Vec<String>,
+    pub id: String,
+    pub r#type: Vec<String>,
+    pub verifiable_credential: Vec<W3cProofCredential>,
+    pub proof: W3cAggregatedProof,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub holder_binding: Option<AssertionProof>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assertion_proof: Option<AssertionProof>,
+}
+
+/// Credential schema in the AnonCreds format.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnonCredsSchema {
+    pub ver: String,
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub attr_names: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seq_no: Option<u64>,
+}
+
+/// Credential definition in the AnonCreds format.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnonCredsCredentialDefinition {
+    pub ver: String,
+    pub id: String,
+    pub schema_id: String,
+    pub r#type: String,
+    pub tag: String,
+    pub value: AnonCredsCredentialDefinitionValue,
+}
+
+/// Primary and revocation part of a `CredentialPublicKey`, as used in AnonCreds credential definitions.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnonCredsCredentialDefinitionValue {
+    pub primary: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revocation: Option<Value>,
+}
+
+/// Revocation registry definition in the AnonCreds format.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnonCredsRevocationRegistryDefinition {
+    pub ver: String,
+    pub id: String,
+    pub revoc_def_type: String,
+    pub tag: String,
+    pub cred_def_id: String,
+    pub value: AnonCredsRevocationRegistryDefinitionValue,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnonCredsRevocationRegistryDefinitionValue {
+    pub issuance_type: String,
+    pub max_cred_num: u32,
+    pub public_keys: AnonCredsRevocationRegistryPublicKeys,
+    pub tails_hash: String,
+    pub tails_location: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnonCredsRevocationRegistryPublicKeys {
+    pub accum_key: RevocationKeyPublic,
+}
+
+/// Current accumulator of a revocation registry in the AnonCreds format.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnonCredsRevocationRegistry {
+    pub ver: String,
+    pub value: RevocationRegistry,
+}
+
+/// Credential in the AnonCreds format.
+#[derive(Serialize, Deserialize)]
+pub struct AnonCredsCredential {
+    pub schema_id: String,
+    pub cred_def_id: String,
+    pub rev_reg_id: Option<String>,
+    pub values: HashMap<String, EncodedCredentialValue>,
+    pub signature: CryptoCredentialSignature,
+    pub signature_correctness_proof: SignatureCorrectnessProof,
+    #[serde(default)]
+    pub rev_reg: Option<RevocationRegistry>,
+    #[serde(default)]
+    pub witness: Option<Witness>,
+}
+
+/// Proof request in the AnonCreds format. Predicates are not supported by this crate.
+#[derive(Serialize, Deserialize)]
+pub struct AnonCredsProofRequest {
+    pub name: String,
+    pub version: String,
+    pub nonce: Nonce,
+    pub requested_attributes: BTreeMap<String, AnonCredsAttributeInfo>,
+    #[serde(default)]
+    pub requested_predicates: BTreeMap<String, Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub non_revoked: Option<NonRevokedInterval>,
+}
+
+/// Attribute or group of attributes (`names`) requested in an `AnonCredsProofRequest`.
+#[derive(Serialize, Deserialize)]
+pub struct AnonCredsAttributeInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub names: Option<Vec<String>>,
+    #[serde(default)]
+    pub restrictions: Vec<AnonCredsRestriction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub non_revoked: Option<NonRevokedInterval>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AnonCredsRestriction {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer_did: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cred_def_id: Option<String>,
+}
+
+/// Proof in the AnonCreds format, answering an `AnonCredsProofRequest`.
+#[derive(Serialize, Deserialize)]
+pub struct AnonCredsProof {
+    pub proof: AnonCredsProofData,
+    pub requested_proof: AnonCredsRequestedProof,
+    pub identifiers: Vec<AnonCredsIdentifier>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AnonCredsProofData {
+    pub proofs: Vec<SubProof>,
+    pub aggregated_proof: CryptoAggregatedProof,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct AnonCredsRequestedProof {
+    #[serde(default)]
+    pub revealed_attrs: BTreeMap<String, AnonCredsRevealedAttribute>,
+    #[serde(default)]
+    pub revealed_attr_groups: BTreeMap<String, AnonCredsRevealedAttributeGroup>,
+    #[serde(default)]
+    pub self_attested_attrs: BTreeMap<String, String>,
+    #[serde(default)]
+    pub unrevealed_attrs: BTreeMap<String, Value>,
+    #[serde(default)]
+    pub predicates: BTreeMap<String, Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AnonCredsRevealedAttribute {
+    pub sub_proof_index: u32,
+    pub raw: String,
+    pub encoded: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AnonCredsRevealedAttributeGroup {
+    pub sub_proof_index: u32,
+    pub values: HashMap<String, EncodedCredentialValue>,
+}
+
+/// Identifies schema, credential definition and revocation registry of a sub proof of an `AnonCredsProof`.
+#[derive(Serialize, Deserialize)]
+pub struct AnonCredsIdentifier {
+    pub schema_id: String,
+    pub cred_def_id: String,
+    pub rev_reg_id: Option<String>,
+    pub timestamp: Option<u64>,
+}
+
+/// DIDComm message of the Aries issue-credential and present-proof protocols. Message specific
+/// properties, like attachments, are kept in `content`.
+#[derive(Serialize, Deserialize)]
+pub struct DidCommMessage {
+    #[serde(rename(serialize = "@type", deserialize = "@type"))]
+    pub r#type: String,
+    #[serde(rename(serialize = "@id", deserialize = "@id"))]
+    pub id: String,
+    #[serde(
+        rename(serialize = "~thread", deserialize = "~thread"),
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub thread: Option<DidCommThread>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub formats: Vec<DidCommAttachmentFormat>,
+    #[serde(flatten)]
+    pub content: Map<String, Value>,
+}
+
+/// Thread decorator of a `DidCommMessage`, `thid` is the ID of the thread's first message.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DidCommThread {
+    pub thid: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pthid: Option<String>,
+}
+
+/// Format of an attachment of a `DidCommMessage`, used by version 2.0 of the protocols.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DidCommAttachmentFormat {
+    pub attach_id: String,
+    pub format: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DidCommAttachment {
+    #[serde(rename(serialize = "@id", deserialize = "@id"))]
+    pub id: String,
+    #[serde(rename(serialize = "mime-type", deserialize = "mime-type"))]
+    pub mime_type: String,
+    pub data: DidCommAttachmentData,
+}
+
+/// Content of a `DidCommAttachment`, either base64url encoded or inlined as JSON.
+#[derive(Serialize, Deserialize)]
+pub struct DidCommAttachmentData {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base64: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json: Option<Value>,
+}
+
+/// State of a credential issuance exchange, as seen by either issuer or holder.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum IssuanceState {
+    ProposalSent,
+    ProposalReceived,
+    OfferSent,
+    OfferReceived,
+    RequestSent,
+    RequestReceived,
+    CredentialIssued,
+    Done,
+    Abandoned,
+}
+
+/// State of a proof presentation exchange, as seen by either verifier or holder.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum PresentationState {
+    RequestSent,
+    RequestReceived,
+    PresentationSent,
+    Done,
+    Abandoned,
+}
+
+/// Record of a credential issuance exchange, identified by the ID of its DIDComm thread.
+/// Contains the blinding factors of the holder's credential request, so holder records have to be
+/// stored privately.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssuanceExchange {
+    pub thread_id: String,
+    pub protocol: String,
+    pub state: IssuanceState,
+    pub issuer: String,
+    pub subject: String,
+    pub schema: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offer: Option<CredentialOffer>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<CredentialRequest>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blinding_factors: Option<CredentialSecretsBlindingFactors>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub problem: Option<String>,
+    pub updated_at: String,
+}
+
+/// Record of a proof presentation exchange, identified by the ID of its DIDComm thread.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresentationExchange {
+    pub thread_id: String,
+    pub protocol: String,
+    pub state: PresentationState,
+    pub verifier: String,
+    pub prover: String,
+    pub proof_request: ProofRequest,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presentation_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification: Option<ProofVerification>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub problem: Option<String>,
+    pub updated_at: String,
+}
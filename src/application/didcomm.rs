@@ -0,0 +1,629 @@
+/*
+  Copyright (c) 2018-present evan GmbH.
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Builders and parsers for DIDComm messages of the Aries issue-credential
+//! ([1.0](https://github.com/hyperledger/aries-rfcs/tree/main/features/0036-issue-credential),
+//! [2.0](https://github.com/hyperledger/aries-rfcs/tree/main/features/0453-issue-credential-v2))
+//! and present-proof
+//! ([1.0](https://github.com/hyperledger/aries-rfcs/tree/main/features/0037-present-proof),
+//! [2.0](https://github.com/hyperledger/aries-rfcs/tree/main/features/0454-present-proof-v2))
+//! protocols, wrapping the messages of this crate as attachments.
+//!
+//! Messages answering another message reference the ID of the thread's first message in their
+//! `~thread` decorator, see `get_thread_id`. Version 2.0 messages declare the format of their
+//! attachments as `CL_ATTACHMENT_FORMAT`.
+//! Credential proposals of version 1.0 carry the proposed schema and issuer as `schema_id` and
+//! `issuer_did` instead of an attachment and take the proposing subject from the sender.
+
+use crate::{
+    application::datatypes::{
+        Credential,
+        CredentialOffer,
+        CredentialProposal,
+        CredentialRequest,
+        DidCommAttachment,
+        DidCommAttachmentData,
+        DidCommAttachmentFormat,
+        DidCommMessage,
+        DidCommThread,
+        ProofPresentation,
+        ProofRequest,
+    },
+    utils::utils::generate_uuid,
+};
+use data_encoding::{BASE64URL, BASE64URL_NOPAD};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Map};
+use std::error::Error;
+
+pub const ISSUE_CREDENTIAL_V1: &str = "https://didcomm.org/issue-credential/1.0";
+pub const ISSUE_CREDENTIAL_V2: &str = "https://didcomm.org/issue-credential/2.0";
+pub const PRESENT_PROOF_V1: &str = "https://didcomm.org/present-proof/1.0";
+pub const PRESENT_PROOF_V2: &str = "https://didcomm.org/present-proof/2.0";
+/// Protocol of problem reports sent in version 1.0 of the protocols
+pub const REPORT_PROBLEM_V1: &str = "https://didcomm.org/report-problem/1.0";
+/// Format of attachments containing messages of this crate in version 2.0 of the protocols
+pub const CL_ATTACHMENT_FORMAT: &str = "evan/zkp-cl@v1.0";
+
+/// Prefix of message types used by older Aries agents instead of `https://didcomm.org/`
+const LEGACY_MESSAGE_TYPE_PREFIX: &str = "did:sov:BzCbsNYhMrjHiqZDTUASHg;spec/";
+
+const ISSUE_CREDENTIAL: &str = "issue-credential";
+const PRESENT_PROOF: &str = "present-proof";
+
+/// Creates a message proposing a credential, starting a new thread.
+///
+/// # Arguments
+/// * `protocol` - Either `ISSUE_CREDENTIAL_V1` or `ISSUE_CREDENTIAL_V2`
+/// * `proposal` - Proposal to send
+///
+/// # Returns
+/// * `DidCommMessage` - The propose-credential message
+pub fn credential_proposal_message(
+    protocol: &str,
+    proposal: &CredentialProposal,
+) -> Result<DidCommMessage, Box<dyn Error>> {
+    if check_protocol(protocol, ISSUE_CREDENTIAL)? {
+        let mut message = new_message(protocol, "propose-credential", None);
+        message
+            .content
+            .insert("schema_id".to_owned(), json!(&proposal.schema));
+        message
+            .content
+            .insert("issuer_did".to_owned(), json!(&proposal.issuer));
+        Ok(message)
+    } else {
+        build_message(
+            protocol,
+            "propose-credential",
+            "filters~attach",
+            proposal,
+            None,
+        )
+    }
+}
+
+/// Creates a message offering a credential.
+///
+/// # Arguments
+/// * `protocol` - Either `ISSUE_CREDENTIAL_V1` or `ISSUE_CREDENTIAL_V2`
+/// * `offer` - Offer to send
+/// * `thread_id` - Thread of the answered proposal, starts a new thread if omitted
+///
+/// # Returns
+/// * `DidCommMessage` - The offer-credential message
+pub fn credential_offer_message(
+    protocol: &str,
+    offer: &CredentialOffer,
+    thread_id: Option<&str>,
+) -> Result<DidCommMessage, Box<dyn Error>> {
+    check_protocol(protocol, ISSUE_CREDENTIAL)?;
+    build_message(
+        protocol,
+        "offer-credential",
+        "offers~attach",
+        offer,
+        thread_id,
+    )
+}
+
+/// Creates a message requesting a credential.
+///
+/// # Arguments
+/// * `protocol` - Either `ISSUE_CREDENTIAL_V1` or `ISSUE_CREDENTIAL_V2`
+/// * `request` - Request to send
+/// * `thread_id` - Thread of the answered offer
+///
+/// # Returns
+/// * `DidCommMessage` - The request-credential message
+pub fn credential_request_message(
+    protocol: &str,
+    request: &CredentialRequest,
+    thread_id: &str,
+) -> Result<DidCommMessage, Box<dyn Error>> {
+    check_protocol(protocol, ISSUE_CREDENTIAL)?;
+    build_message(
+        protocol,
+        "request-credential",
+        "requests~attach",
+        request,
+        Some(thread_id),
+    )
+}
+
+/// Creates a message issuing a credential.
+///
+/// # Arguments
+/// * `protocol` - Either `ISSUE_CREDENTIAL_V1` or `ISSUE_CREDENTIAL_V2`
+/// * `credential` - Issued credential
+/// * `thread_id` - Thread of the answered request
+///
+/// # Returns
+/// * `DidCommMessage` - The issue-credential message
+pub fn credential_message(
+    protocol: &str,
+    credential: &Credential,
+    thread_id: &str,
+) -> Result<DidCommMessage, Box<dyn Error>> {
+    check_protocol(protocol, ISSUE_CREDENTIAL)?;
+    build_message(
+        protocol,
+        "issue-credential",
+        "credentials~attach",
+        credential,
+        Some(thread_id),
+    )
+}
+
+/// Creates a message requesting a proof presentation.
+///
+/// # Arguments
+/// * `protocol` - Either `PRESENT_PROOF_V1` or `PRESENT_PROOF_V2`
+/// * `proof_request` - Proof request to send
+/// * `thread_id` - Thread of an answered proposal, starts a new thread if omitted
+///
+/// # Returns
+/// * `DidCommMessage` - The request-presentation message
+pub fn proof_request_message(
+    protocol: &str,
+    proof_request: &ProofRequest,
+    thread_id: Option<&str>,
+) -> Result<DidCommMessage, Box<dyn Error>> {
+    check_protocol(protocol, PRESENT_PROOF)?;
+    build_message(
+        protocol,
+        "request-presentation",
+        "request_presentations~attach",
+        proof_request,
+        thread_id,
+    )
+}
+
+/// Creates a message presenting a proof.
+///
+/// # Arguments
+/// * `protocol` - Either `PRESENT_PROOF_V1` or `PRESENT_PROOF_V2`
+/// * `presentation` - Presentation to send
+/// * `thread_id` - Thread of the answered proof request
+///
+/// # Returns
+/// * `DidCommMessage` - The presentation message
+pub fn presentation_message(
+    protocol: &str,
+    presentation: &ProofPresentation,
+    thread_id: &str,
+) -> Result<DidCommMessage, Box<dyn Error>> {
+    check_protocol(protocol, PRESENT_PROOF)?;
+    build_message(
+        protocol,
+        "presentation",
+        "presentations~attach",
+        presentation,
+        Some(thread_id),
+    )
+}
+
+/// Creates a message acknowledging the last message of a thread, e.g. a received credential or a
+/// verified presentation.
+///
+/// # Arguments
+/// * `protocol` - Protocol of the thread
+/// * `thread_id` - Thread to acknowledge
+///
+/// # Returns
+/// * `DidCommMessage` - The ack message
+pub fn ack_message(protocol: &str, thread_id: &str) -> Result<DidCommMessage, Box<dyn Error>> {
+    get_protocol_version(protocol)?;
+    let mut message = new_message(protocol, "ack", Some(thread_id));
+    message.content.insert("status".to_owned(), json!("OK"));
+    Ok(message)
+}
+
+/// Creates a message reporting a problem, that ends a thread, e.g. a rejected presentation.
+///
+/// # Arguments
+/// * `protocol` - Protocol of the thread
+/// * `thread_id` - Thread to report the problem for
+/// * `code` - Code of the problem, e.g. "rejected"
+/// * `description` - Human readable description of the problem
+///
+/// # Returns
+/// * `DidCommMessage` - The problem-report message
+pub fn problem_report_message(
+    protocol: &str,
+    thread_id: &str,
+    code: &str,
+    description: &str,
+) -> Result<DidCommMessage, Box<dyn Error>> {
+    let protocol = match get_protocol_version(protocol)?.1 {
+        "1.0" => REPORT_PROBLEM_V1,
+        _ => protocol,
+    };
+    let mut message = new_message(protocol, "problem-report", Some(thread_id));
+    message.content.insert(
+        "description".to_owned(),
+        json!({ "en": description, "code": code }),
+    );
+    Ok(message)
+}
+
+/// Parses the credential proposal of a propose-credential message.
+///
+/// # Arguments
+/// * `message` - Received message
+/// * `subject_did` - DID of the message's sender, that proposes a credential for itself
+///
+/// # Returns
+/// * `CredentialProposal` - The proposal
+pub fn parse_credential_proposal(
+    message: &DidCommMessage,
+    subject_did: &str,
+) -> Result<CredentialProposal, Box<dyn Error>> {
+    let protocol = check_message_type(message, ISSUE_CREDENTIAL, "propose-credential")?;
+    if protocol == ISSUE_CREDENTIAL_V1 {
+        let get_field = |name: &str| {
+            message
+                .content
+                .get(name)
+                .and_then(|value| value.as_str())
+                .map(|value| value.to_owned())
+                .ok_or_else(|| format!("propose-credential message is missing {}", name))
+        };
+        return Ok(CredentialProposal {
+            issuer: get_field("issuer_did")?,
+            subject: subject_did.to_owned(),
+            r#type: "EvanZKPCredentialProposal".to_owned(),
+            schema: get_field("schema_id")?,
+        });
+    }
+
+    let proposal: CredentialProposal = parse_attachment(message, "filters~attach")?;
+    if proposal.subject != subject_did {
+        return Err(Box::from(format!(
+            "credential proposal has been created for subject {}, but has been sent by {}",
+            &proposal.subject, subject_did,
+        )));
+    }
+    Ok(proposal)
+}
+
+/// Parses the credential offer of an offer-credential message.
+///
+/// # Arguments
+/// * `message` - Received message
+///
+/// # Returns
+/// * `CredentialOffer` - The offer
+pub fn parse_credential_offer(message: &DidCommMessage) -> Result<CredentialOffer, Box<dyn Error>> {
+    check_message_type(message, ISSUE_CREDENTIAL, "offer-credential")?;
+    parse_attachment(message, "offers~attach")
+}
+
+/// Parses the credential request of a request-credential message.
+///
+/// # Arguments
+/// * `message` - Received message
+///
+/// # Returns
+/// * `CredentialRequest` - The request
+pub fn parse_credential_request(
+    message: &DidCommMessage,
+) -> Result<CredentialRequest, Box<dyn Error>> {
+    check_message_type(message, ISSUE_CREDENTIAL, "request-credential")?;
+    parse_attachment(message, "requests~attach")
+}
+
+/// Parses the credential of an issue-credential message.
+///
+/// # Arguments
+/// * `message` - Received message
+///
+/// # Returns
+/// * `Credential` - The issued credential
+pub fn parse_credential(message: &DidCommMessage) -> Result<Credential, Box<dyn Error>> {
+    check_message_type(message, ISSUE_CREDENTIAL, "issue-credential")?;
+    parse_attachment(message, "credentials~attach")
+}
+
+/// Parses the proof request of a request-presentation message.
+///
+/// # Arguments
+/// * `message` - Received message
+///
+/// # Returns
+/// * `ProofRequest` - The proof request
+pub fn parse_proof_request(message: &DidCommMessage) -> Result<ProofRequest, Box<dyn Error>> {
+    check_message_type(message, PRESENT_PROOF, "request-presentation")?;
+    parse_attachment(message, "request_presentations~attach")
+}
+
+/// Parses the proof presentation of a presentation message.
+///
+/// # Arguments
+/// * `message` - Received message
+///
+/// # Returns
+/// * `ProofPresentation` - The presentation
+pub fn parse_presentation(message: &DidCommMessage) -> Result<ProofPresentation, Box<dyn Error>> {
+    check_message_type(message, PRESENT_PROOF, "presentation")?;
+    parse_attachment(message, "presentations~attach")
+}
+
+/// Gets the ID of the thread a message belongs to, which is the ID of the thread's first message.
+///
+/// # Arguments
+/// * `message` - Message to get the thread ID of
+pub fn get_thread_id(message: &DidCommMessage) -> &str {
+    match &message.thread {
+        Some(thread) => &thread.thid,
+        None => &message.id,
+    }
+}
+
+/// Splits the type of a message into protocol and message name, e.g.
+/// `https://didcomm.org/issue-credential/1.0` and `offer-credential`. Message types with the
+/// prefix used by older Aries agents are normalized.
+///
+/// # Arguments
+/// * `message` - Message to get the type of
+///
+/// # Returns
+/// * `(String, String)` - Protocol and name of the message
+pub fn get_message_type(message: &DidCommMessage) -> Result<(String, String), Box<dyn Error>> {
+    let message_type = match message.r#type.strip_prefix(LEGACY_MESSAGE_TYPE_PREFIX) {
+        Some(suffix) => format!("https://didcomm.org/{}", suffix),
+        None => message.r#type.to_owned(),
+    };
+    match message_type.rfind('/') {
+        Some(index) => Ok((
+            message_type[..index].to_owned(),
+            message_type[index + 1..].to_owned(),
+        )),
+        None => Err(Box::from(format!(
+            "invalid message type {}",
+            &message.r#type
+        ))),
+    }
+}
+
+fn new_message(protocol: &str, name: &str, thread_id: Option<&str>) -> DidCommMessage {
+    DidCommMessage {
+        r#type: format!("{}/{}", protocol, name),
+        id: generate_uuid(),
+        thread: thread_id.map(|thid| DidCommThread {
+            thid: thid.to_owned(),
+            pthid: None,
+        }),
+        comment: None,
+        formats: Vec::new(),
+        content: Map::new(),
+    }
+}
+
+fn build_message<T: Serialize>(
+    protocol: &str,
+    name: &str,
+    attachment_key: &str,
+    attachment: &T,
+    thread_id: Option<&str>,
+) -> Result<DidCommMessage, Box<dyn Error>> {
+    let mut message = new_message(protocol, name, thread_id);
+    let attachment = DidCommAttachment {
+        id: generate_uuid(),
+        mime_type: "application/json".to_owned(),
+        data: DidCommAttachmentData {
+            base64: Some(BASE64URL.encode(serde_json::to_string(attachment)?.as_bytes())),
+            json: None,
+        },
+    };
+    if get_protocol_version(protocol)?.1 == "2.0" {
+        message.formats.push(DidCommAttachmentFormat {
+            attach_id: attachment.id.to_owned(),
+            format: CL_ATTACHMENT_FORMAT.to_owned(),
+        });
+    }
+    message.content.insert(
+        attachment_key.to_owned(),
+        serde_json::to_value(vec![attachment])?,
+    );
+
+    Ok(message)
+}
+
+fn parse_attachment<T: DeserializeOwned>(
+    message: &DidCommMessage,
+    attachment_key: &str,
+) -> Result<T, Box<dyn Error>> {
+    let attachments: Vec<DidCommAttachment> = serde_json::from_value(
+        message
+            .content
+            .get(attachment_key)
+            .cloned()
+            .ok_or_else(|| format!("message {} is missing {}", &message.id, attachment_key))?,
+    )?;
+    let attachment = match attachments.as_slice() {
+        [attachment] => attachment,
+        _ => {
+            return Err(Box::from(format!(
+                "message {} has to contain exactly one attachment in {}",
+                &message.id, attachment_key,
+            )))
+        }
+    };
+    if !message.formats.is_empty()
+        && !message.formats.iter().any(|format| {
+            format.attach_id == attachment.id && format.format == CL_ATTACHMENT_FORMAT
+        })
+    {
+        return Err(Box::from(format!(
+            "attachment {} of message {} does not have format {}",
+            &attachment.id, &message.id, CL_ATTACHMENT_FORMAT,
+        )));
+    }
+
+    match (&attachment.data.base64, &attachment.data.json) {
+        (Some(base64), _) => {
+            // accept padded and unpadded base64url as well as standard base64
+            let normalized = base64
+                .trim_end_matches('=')
+                .replace('+', "-")
+                .replace('/', "_");
+            let decoded = BASE64URL_NOPAD
+                .decode(normalized.as_bytes())
+                .map_err(|e| format!("could not decode attachment {}; {}", &attachment.id, &e))?;
+            Ok(serde_json::from_slice(&decoded)?)
+        }
+        (None, Some(json)) => Ok(serde_json::from_value(json.clone())?),
+        (None, None) => Err(Box::from(format!(
+            "attachment {} of message {} has no data",
+            &attachment.id, &message.id,
+        ))),
+    }
+}
+
+/// Checks that a protocol is a supported version of the given protocol family.
+///
+/// # Returns
+/// * `bool` - `true` for version 1.0, `false` for version 2.0
+fn check_protocol(protocol: &str, family: &str) -> Result<bool, Box<dyn Error>> {
+    let (protocol_family, version) = get_protocol_version(protocol)?;
+    if protocol_family != family {
+        return Err(Box::from(format!(
+            "expected protocol {}, got {}",
+            family, protocol
+        )));
+    }
+    Ok(version == "1.0")
+}
+
+/// Splits a supported protocol into family and version.
+fn get_protocol_version(protocol: &str) -> Result<(&str, &str), Box<dyn Error>> {
+    match protocol {
+        ISSUE_CREDENTIAL_V1 => Ok((ISSUE_CREDENTIAL, "1.0")),
+        ISSUE_CREDENTIAL_V2 => Ok((ISSUE_CREDENTIAL, "2.0")),
+        PRESENT_PROOF_V1 => Ok((PRESENT_PROOF, "1.0")),
+        PRESENT_PROOF_V2 => Ok((PRESENT_PROOF, "2.0")),
+        _ => Err(Box::from(format!("unsupported protocol {}", protocol))),
+    }
+}
+
+/// Checks that a message is of the given type in a supported version of the protocol family.
+///
+/// # Returns
+/// * `String` - Protocol of the message
+fn check_message_type(
+    message: &DidCommMessage,
+    family: &str,
+    name: &str,
+) -> Result<String, Box<dyn Error>> {
+    let (protocol, message_name) = get_message_type(message)?;
+    if message_name != name || check_protocol(&protocol, family).is_err() {
+        return Err(Box::from(format!(
+            "expected {} message of protocol {}, got {}",
+            name, family, &message.r#type,
+        )));
+    }
+    Ok(protocol)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_proof_request() -> Result<ProofRequest, Box<dyn Error>> {
+        Ok(serde_json::from_value(json!({
+            "verifier": "did:evan:verifier",
+            "prover": "did:evan:prover",
+            "createdAt": "2020-01-01T00:00:00.000Z",
+            "nonce": ursa::cl::new_nonce().map_err(|e| format!("{}", &e))?,
+            "type": "CL",
+            "subProofRequests": [{ "schema": "did:evan:schema", "revealedAttributes": ["name"] }],
+        }))?)
+    }
+
+    #[test]
+    fn can_wrap_and_parse_messages() -> Result<(), Box<dyn Error>> {
+        let proof_request = get_proof_request()?;
+        for protocol in &[PRESENT_PROOF_V1, PRESENT_PROOF_V2] {
+            let message = proof_request_message(protocol, &proof_request, None)?;
+            let value = serde_json::to_value(&message)?;
+            assert_eq!(value["@type"], format!("{}/request-presentation", protocol));
+            assert!(value.get("~thread").is_none());
+            assert_eq!(
+                value["formats"].as_array().map(|formats| formats.len()),
+                if *protocol == PRESENT_PROOF_V2 {
+                    Some(1)
+                } else {
+                    None
+                },
+            );
+
+            let parsed = parse_proof_request(&serde_json::from_value(value)?)?;
+            assert_eq!(
+                serde_json::to_value(&parsed)?,
+                serde_json::to_value(&proof_request)?,
+            );
+
+            let ack = ack_message(protocol, get_thread_id(&message))?;
+            assert_eq!(get_thread_id(&ack), message.id);
+        }
+
+        // messages of older agents and inlined JSON attachments are accepted
+        let message: DidCommMessage = serde_json::from_value(json!({
+            "@type": "did:sov:BzCbsNYhMrjHiqZDTUASHg;spec/present-proof/1.0/request-presentation",
+            "@id": "1234",
+            "request_presentations~attach": [{
+                "@id": "attachment",
+                "mime-type": "application/json",
+                "data": { "json": serde_json::to_value(&proof_request)? },
+            }],
+        }))?;
+        parse_proof_request(&message)?;
+        assert!(parse_presentation(&message).is_err());
+
+        let report = problem_report_message(PRESENT_PROOF_V1, "1234", "rejected", "invalid proof")?;
+        assert_eq!(
+            report.r#type,
+            format!("{}/problem-report", REPORT_PROBLEM_V1)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_wrap_and_parse_credential_proposals() -> Result<(), Box<dyn Error>> {
+        let proposal = CredentialProposal {
+            issuer: "did:evan:issuer".to_owned(),
+            subject: "did:evan:subject".to_owned(),
+            r#type: "EvanZKPCredentialProposal".to_owned(),
+            schema: "did:evan:schema".to_owned(),
+        };
+        for protocol in &[ISSUE_CREDENTIAL_V1, ISSUE_CREDENTIAL_V2] {
+            let message = credential_proposal_message(protocol, &proposal)?;
+            let parsed = parse_credential_proposal(&message, "did:evan:subject")?;
+            assert_eq!(
+                serde_json::to_value(&parsed)?,
+                serde_json::to_value(&proposal)?,
+            );
+        }
+
+        let message = credential_proposal_message(ISSUE_CREDENTIAL_V2, &proposal)?;
+        assert!(parse_credential_proposal(&message, "did:evan:other").is_err());
+        assert!(credential_proposal_message(PRESENT_PROOF_V2, &proposal).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,551 @@
+/*
+  Copyright (c) 2018-present evan GmbH.
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Compact binary encoding of proof presentations, proof requests and credentials for transports
+//! with limited space, e.g. QR codes and NFC.
+//!
+//! Documents are encoded as [CBOR](https://www.rfc-editor.org/rfc/rfc8949) and can be further
+//! encoded as text with [base45](https://www.rfc-editor.org/rfc/rfc9285), that is suited for the
+//! alphanumeric mode of QR codes, or base64url. Strings containing decimal numbers, e.g. the big
+//! numbers of Ursa proofs, are encoded as CBOR byte strings, that take less than half of their
+//! space. Presentations are parsed into `ProofPresentation` before encoding, which parses Ursa
+//! proofs embedded as JSON strings as well, see `PROOF_VERSION`, so these proofs are encoded as
+//! nested CBOR instead of escaped strings. Decoding restores the JSON form of the parsed document
+//! without losses.
+
+use crate::application::datatypes::{Credential, ProofPresentation, ProofRequest};
+use data_encoding::BASE64URL_NOPAD;
+use serde::{
+    de::{MapAccess, SeqAccess, Visitor},
+    Deserialize,
+    Deserializer,
+    Serialize,
+    Serializer,
+};
+use serde_json::{Map, Number, Value};
+use std::{error::Error, fmt};
+
+/// Characters used by base45, each character represents its index in this alphabet
+const BASE45_ALPHABET: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Encodes a proof presentation as CBOR.
+///
+/// # Arguments
+/// * `presentation` - Presentation to encode
+///
+/// # Returns
+/// * `Vec<u8>` - The encoded presentation
+pub fn presentation_to_cbor(presentation: &ProofPresentation) -> Result<Vec<u8>, Box<dyn Error>> {
+    to_cbor(presentation)
+}
+
+/// Decodes a proof presentation encoded with `presentation_to_cbor`.
+///
+/// # Arguments
+/// * `data` - Encoded presentation
+///
+/// # Returns
+/// * `ProofPresentation` - The decoded presentation
+pub fn presentation_from_cbor(data: &[u8]) -> Result<ProofPresentation, Box<dyn Error>> {
+    Ok(serde_json::from_value(from_cbor(data)?)?)
+}
+
+/// Encodes a proof request as CBOR.
+///
+/// # Arguments
+/// * `proof_request` - Proof request to encode
+///
+/// # Returns
+/// * `Vec<u8>` - The encoded proof request
+pub fn proof_request_to_cbor(proof_request: &ProofRequest) -> Result<Vec<u8>, Box<dyn Error>> {
+    to_cbor(proof_request)
+}
+
+/// Decodes a proof request encoded with `proof_request_to_cbor`.
+///
+/// # Arguments
+/// * `data` - Encoded proof request
+///
+/// # Returns
+/// * `ProofRequest` - The decoded proof request
+pub fn proof_request_from_cbor(data: &[u8]) -> Result<ProofRequest, Box<dyn Error>> {
+    Ok(serde_json::from_value(from_cbor(data)?)?)
+}
+
+/// Encodes a credential as CBOR.
+///
+/// # Arguments
+/// * `credential` - Credential to encode
+///
+/// # Returns
+/// * `Vec<u8>` - The encoded credential
+pub fn credential_to_cbor(credential: &Credential) -> Result<Vec<u8>, Box<dyn Error>> {
+    to_cbor(credential)
+}
+
+/// Decodes a credential encoded with `credential_to_cbor`.
+///
+/// # Arguments
+/// * `data` - Encoded credential
+///
+/// # Returns
+/// * `Credential` - The decoded credential
+pub fn credential_from_cbor(data: &[u8]) -> Result<Credential, Box<dyn Error>> {
+    Ok(serde_json::from_value(from_cbor(data)?)?)
+}
+
+/// Encodes a presentation, proof request or credential given in its JSON form as CBOR. The
+/// document is checked and encoded with `presentation_to_cbor`, `proof_request_to_cbor` or
+/// `credential_to_cbor`, depending on its properties.
+///
+/// # Arguments
+/// * `document` - JSON form of the document to encode
+///
+/// # Returns
+/// * `Vec<u8>` - The encoded document
+pub fn document_to_cbor(document: &Value) -> Result<Vec<u8>, Box<dyn Error>> {
+    if document.get("verifiableCredential").is_some() {
+        let presentation = serde_json::from_value(document.clone())
+            .map_err(|e| format!("invalid proof presentation; {}", &e))?;
+        presentation_to_cbor(&presentation)
+    } else if document.get("subProofRequests").is_some() {
+        let proof_request = serde_json::from_value(document.clone())
+            .map_err(|e| format!("invalid proof request; {}", &e))?;
+        proof_request_to_cbor(&proof_request)
+    } else if document.get("credentialSubject").is_some() {
+        let credential = serde_json::from_value(document.clone())
+            .map_err(|e| format!("invalid credential; {}", &e))?;
+        credential_to_cbor(&credential)
+    } else {
+        Err(Box::from(
+            "document is neither a proof presentation, a proof request nor a credential",
+        ))
+    }
+}
+
+/// Decodes a document encoded with one of the `*_to_cbor` functions into its JSON form.
+///
+/// # Arguments
+/// * `data` - Encoded document
+///
+/// # Returns
+/// * `Value` - JSON form of the decoded document
+pub fn document_from_cbor(data: &[u8]) -> Result<Value, Box<dyn Error>> {
+    from_cbor(data)
+}
+
+/// Encodes binary data as base45 text as specified in RFC 9285.
+///
+/// # Arguments
+/// * `data` - Data to encode
+///
+/// # Returns
+/// * `String` - The encoded text
+pub fn to_base45(data: &[u8]) -> String {
+    let mut encoded = Vec::with_capacity((data.len() + 1) / 2 * 3);
+    let chunks = data.chunks_exact(2);
+    let remainder = chunks.remainder();
+    for chunk in chunks {
+        push_base45(
+            &mut encoded,
+            (chunk[0] as usize) * 256 + chunk[1] as usize,
+            3,
+        );
+    }
+    if let [last] = remainder {
+        push_base45(&mut encoded, *last as usize, 2);
+    }
+
+    // alphabet only contains ASCII characters
+    String::from_utf8(encoded).unwrap_or_default()
+}
+
+/// Appends `length` base45 characters of `value`, least significant first.
+fn push_base45(encoded: &mut Vec<u8>, mut value: usize, length: usize) {
+    for _ in 0..length {
+        encoded.push(BASE45_ALPHABET[value % 45]);
+        value /= 45;
+    }
+}
+
+/// Decodes base45 text as specified in RFC 9285.
+///
+/// # Arguments
+/// * `text` - Text to decode
+///
+/// # Returns
+/// * `Vec<u8>` - The decoded data
+pub fn from_base45(text: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let values = text
+        .bytes()
+        .map(|character| {
+            BASE45_ALPHABET
+                .iter()
+                .position(|candidate| *candidate == character)
+                .ok_or_else(|| format!("invalid base45 character {}", character as char))
+        })
+        .collect::<Result<Vec<usize>, String>>()?;
+    let mut decoded = Vec::with_capacity(values.len() / 3 * 2 + 1);
+    for chunk in values.chunks(3) {
+        match chunk {
+            [c, d, e] => {
+                let value = c + d * 45 + e * 45 * 45;
+                if value > 0xffff {
+                    return Err(Box::from("invalid base45 text, chunk exceeds two bytes"));
+                }
+                decoded.push((value / 256) as u8);
+                decoded.push((value % 256) as u8);
+            }
+            [c, d] => {
+                let value = c + d * 45;
+                if value > 0xff {
+                    return Err(Box::from(
+                        "invalid base45 text, last chunk exceeds one byte",
+                    ));
+                }
+                decoded.push(value as u8);
+            }
+            _ => return Err(Box::from("invalid base45 text length")),
+        }
+    }
+
+    Ok(decoded)
+}
+
+/// Encodes binary data as unpadded base64url text.
+///
+/// # Arguments
+/// * `data` - Data to encode
+///
+/// # Returns
+/// * `String` - The encoded text
+pub fn to_base64url(data: &[u8]) -> String {
+    BASE64URL_NOPAD.encode(data)
+}
+
+/// Decodes base64url text, with or without padding.
+///
+/// # Arguments
+/// * `text` - Text to decode
+///
+/// # Returns
+/// * `Vec<u8>` - The decoded data
+pub fn from_base64url(text: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    BASE64URL_NOPAD
+        .decode(text.trim_end_matches('=').as_bytes())
+        .map_err(|e| Box::from(format!("could not decode base64url text; {}", &e)))
+}
+
+fn to_cbor<T: Serialize>(document: &T) -> Result<Vec<u8>, Box<dyn Error>> {
+    let value = serde_json::to_value(document)?;
+    serde_cbor::to_vec(&EncodedValue(&value))
+        .map_err(|e| Box::from(format!("could not encode document as CBOR; {}", &e)))
+}
+
+fn from_cbor(data: &[u8]) -> Result<Value, Box<dyn Error>> {
+    let decoded: DecodedValue = serde_cbor::from_slice(data)
+        .map_err(|e| format!("could not decode CBOR document; {}", &e))?;
+    Ok(decoded.0)
+}
+
+/// JSON value, that serializes strings of decimal numbers as byte strings.
+struct EncodedValue<'a>(&'a Value);
+
+impl Serialize for EncodedValue<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            Value::String(text) if is_decimal_number(text) => {
+                serializer.serialize_bytes(&decimal_to_bytes(text))
+            }
+            Value::Array(elements) => serializer.collect_seq(elements.iter().map(EncodedValue)),
+            Value::Object(properties) => serializer.collect_map(
+                properties
+                    .iter()
+                    .map(|(key, property)| (key, EncodedValue(property))),
+            ),
+            value => value.serialize(serializer),
+        }
+    }
+}
+
+/// JSON value, that deserializes byte strings as strings of decimal numbers.
+struct DecodedValue(Value);
+
+impl<'de> Deserialize<'de> for DecodedValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(DecodedValueVisitor)
+    }
+}
+
+struct DecodedValueVisitor;
+
+impl<'de> Visitor<'de> for DecodedValueVisitor {
+    type Value = DecodedValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a document encoded with to_cbor")
+    }
+
+    fn visit_unit<E>(self) -> Result<DecodedValue, E> {
+        Ok(DecodedValue(Value::Null))
+    }
+
+    fn visit_none<E>(self) -> Result<DecodedValue, E> {
+        Ok(DecodedValue(Value::Null))
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<DecodedValue, E> {
+        Ok(DecodedValue(Value::Bool(value)))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<DecodedValue, E> {
+        Ok(DecodedValue(Value::from(value)))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<DecodedValue, E> {
+        Ok(DecodedValue(Value::from(value)))
+    }
+
+    fn visit_f64<E>(self, value: f64) -> Result<DecodedValue, E> {
+        Ok(DecodedValue(
+            Number::from_f64(value).map_or(Value::Null, Value::Number),
+        ))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<DecodedValue, E> {
+        Ok(DecodedValue(Value::String(value.to_owned())))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<DecodedValue, E> {
+        Ok(DecodedValue(Value::String(value)))
+    }
+
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<DecodedValue, E> {
+        Ok(DecodedValue(Value::String(bytes_to_decimal(value))))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<DecodedValue, A::Error> {
+        let mut elements = Vec::new();
+        while let Some(DecodedValue(element)) = seq.next_element()? {
+            elements.push(element);
+        }
+        Ok(DecodedValue(Value::Array(elements)))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<DecodedValue, A::Error> {
+        let mut properties = Map::new();
+        while let Some((key, DecodedValue(property))) = map.next_entry::<String, DecodedValue>()? {
+            properties.insert(key, property);
+        }
+        Ok(DecodedValue(Value::Object(properties)))
+    }
+}
+
+/// Checks if a string is a decimal number, that can be restored from its bytes without losses,
+/// i.e. one without leading zeros.
+fn is_decimal_number(text: &str) -> bool {
+    !text.is_empty()
+        && text.bytes().all(|character| character.is_ascii_digit())
+        && (text == "0" || !text.starts_with('0'))
+}
+
+/// Converts a decimal number to its big-endian bytes without leading zeros.
+fn decimal_to_bytes(decimal: &str) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::with_capacity(decimal.len() / 2);
+    for digit in decimal.bytes() {
+        let mut carry = (digit - b'0') as u32;
+        for byte in bytes.iter_mut().rev() {
+            let value = (*byte as u32) * 10 + carry;
+            *byte = (value % 256) as u8;
+            carry = value / 256;
+        }
+        if carry > 0 {
+            bytes.insert(0, carry as u8);
+        }
+    }
+    bytes
+}
+
+/// Converts big-endian bytes to a decimal number.
+fn bytes_to_decimal(bytes: &[u8]) -> String {
+    // least significant digit first
+    let mut digits: Vec<u8> = Vec::with_capacity(bytes.len() * 3);
+    for byte in bytes {
+        let mut carry = *byte as u32;
+        for digit in digits.iter_mut() {
+            let value = (*digit as u32) * 256 + carry;
+            *digit = (value % 10) as u8;
+            carry = value / 10;
+        }
+        while carry > 0 {
+            digits.push((carry % 10) as u8);
+            carry /= 10;
+        }
+    }
+    if digits.is_empty() {
+        return "0".to_owned();
+    }
+    digits
+        .iter()
+        .rev()
+        .map(|digit| (b'0' + digit) as char)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate utilities;
+
+    use super::*;
+    use serde_json::json;
+    use utilities::test_data::vc_zkp::{EXAMPLE_AGGREGATED_PROOF, EXAMPLE_SUB_PROOF};
+
+    #[test]
+    fn can_encode_base45() -> Result<(), Box<dyn Error>> {
+        // test vectors of RFC 9285
+        for (data, text) in [
+            ("AB", "BB8"),
+            ("Hello!!", "%69 VD92EX0"),
+            ("base-45", "UJCLQE7W581"),
+            ("ietf!", "QED8WEX0"),
+        ]
+        .iter()
+        {
+            assert_eq!(to_base45(data.as_bytes()), *text);
+            assert_eq!(from_base45(text)?, data.as_bytes());
+        }
+        assert!(from_base45("GGW").is_err());
+        assert!(from_base45("ZZZZ").is_err());
+        assert!(from_base45("aB").is_err());
+
+        Ok(())
+    }
+
+    fn get_presentation_value() -> Result<Value, Box<dyn Error>> {
+        // 2048 bit numbers as used in Ursa proofs
+        let big_number = bytes_to_decimal(&[0xab; 256]);
+        let mut sub_proof: Value = serde_json::from_str(EXAMPLE_SUB_PROOF)?;
+        sub_proof["primary_proof"]["eq_proof"]["a_prime"] = Value::from(big_number.as_str());
+        sub_proof["primary_proof"]["eq_proof"]["v"] = Value::from(big_number.as_str());
+        Ok(json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1"],
+            "id": "presentation",
+            "type": ["VerifiablePresentation"],
+            "verifiableCredential": [{
+                "@context": ["https://www.w3.org/2018/credentials/v1"],
+                "id": "credential",
+                "type": ["VerifiableCredential"],
+                "issuer": "did:evan:issuer",
+                "issuanceDate": "2020-01-01T00:00:00.000Z",
+                "credentialSubject": {
+                    "id": "did:evan:subject",
+                    "data": {
+                        "name": { "raw": "{ \"a\": 1 }", "encoded": "1234" },
+                        "code": { "raw": "007", "encoded": "7" },
+                    },
+                },
+                "credentialSchema": { "id": "did:evan:schema", "type": "EvanZKPSchema" },
+                "proof": {
+                    "credentialDefinition": "did:evan:definition",
+                    "revocationRegistryDefinition": "did:evan:registry",
+                    "proof": sub_proof,
+                },
+            }],
+            "proof": {
+                "nonce": big_number,
+                "aggregatedProof": serde_json::from_str::<Value>(EXAMPLE_AGGREGATED_PROOF)?,
+            },
+        }))
+    }
+
+    #[test]
+    fn restores_json_form_of_documents() -> Result<(), Box<dyn Error>> {
+        let document = get_presentation_value()?;
+        let presentation: ProofPresentation = serde_json::from_value(document.clone())?;
+        let expected = serde_json::to_value(&presentation)?;
+
+        let encoded = document_to_cbor(&document)?;
+        assert_eq!(encoded, presentation_to_cbor(&presentation)?);
+        // base45 text for QR codes is still smaller than the JSON form
+        assert!(to_base45(&encoded).len() < serde_json::to_vec(&document)?.len());
+        let decoded = document_from_cbor(&from_base45(&to_base45(&encoded))?)?;
+        assert_eq!(
+            serde_json::to_string(&decoded)?,
+            serde_json::to_string(&expected)?
+        );
+        let decoded = document_from_cbor(&from_base64url(&to_base64url(&encoded))?)?;
+        assert_eq!(decoded, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn nests_proofs_of_presentations_embedding_json_strings() -> Result<(), Box<dyn Error>> {
+        let document = get_presentation_value()?;
+        let mut legacy = document.clone();
+        let sub_proof = legacy["verifiableCredential"][0]["proof"]["proof"].take();
+        legacy["verifiableCredential"][0]["proof"]["proof"] = Value::from(sub_proof.to_string());
+        let aggregated_proof = legacy["proof"]["aggregatedProof"].take();
+        legacy["proof"]["aggregatedProof"] = Value::from(aggregated_proof.to_string());
+
+        // proofs are encoded as nested CBOR instead of escaped strings
+        let encoded = document_to_cbor(&legacy)?;
+        assert_eq!(encoded, document_to_cbor(&document)?);
+        let decoded = document_from_cbor(&encoded)?;
+        assert_eq!(
+            decoded["verifiableCredential"][0]["proof"]["proof"],
+            sub_proof
+        );
+        assert_eq!(decoded["proof"]["aggregatedProof"], aggregated_proof);
+
+        Ok(())
+    }
+
+    #[test]
+    fn encodes_decimal_numbers_as_byte_strings() -> Result<(), Box<dyn Error>> {
+        let big_number = bytes_to_decimal(&[0xff; 256]);
+        assert_eq!(big_number.len(), 617);
+        let encoded = to_cbor(&Value::from(big_number.as_str()))?;
+        // byte string header with two bytes of length, followed by the number's 256 bytes
+        assert_eq!(encoded.len(), 3 + 256);
+        assert_eq!(from_cbor(&encoded)?, Value::from(big_number));
+
+        // strings that cannot be restored from bytes are kept as text
+        for text in ["0", "42", "007", "-1", "1.5", "", "a1"].iter() {
+            assert_eq!(
+                from_cbor(&to_cbor(&Value::from(*text))?)?,
+                Value::from(*text)
+            );
+        }
+        assert_eq!(to_cbor(&Value::from("007"))?, serde_cbor::to_vec(&"007")?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unsupported_documents() -> Result<(), Box<dyn Error>> {
+        match document_to_cbor(&json!({ "id": "did:evan:document" })) {
+            Ok(_) => return Err(Box::from("unknown document has been encoded")),
+            Err(e) => assert!(e.to_string().contains("neither a proof presentation")),
+        }
+        match document_to_cbor(&json!({ "id": "presentation", "verifiableCredential": [] })) {
+            Ok(_) => return Err(Box::from("invalid presentation has been encoded")),
+            Err(e) => assert!(e.to_string().contains("invalid proof presentation")),
+        }
+
+        Ok(())
+    }
+}
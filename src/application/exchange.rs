@@ -0,0 +1,1345 @@
+/*
+  Copyright (c) 2018-present evan GmbH.
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! State machines for the issuance and presentation exchanges of holders, issuers and verifiers.
+//!
+//! Exchanges are tracked by the ID of their DIDComm thread, see `didcomm::get_thread_id`. Every
+//! outgoing message is created and every incoming message is checked against the state of its
+//! exchange, so steps cannot be skipped or repeated and finished exchanges cannot be continued.
+//!
+//! Issuance (holder and issuer):
+//! `ProposalSent`/`ProposalReceived` (optional) -> `OfferReceived`/`OfferSent` ->
+//! `RequestSent`/`RequestReceived` -> `CredentialIssued` (issuer only) -> `Done`
+//!
+//! Presentation (holder and verifier):
+//! `RequestReceived`/`RequestSent` -> `PresentationSent` (holder only) -> `Done`
+//!
+//! Problem reports move an exchange to `Abandoned` from any state. `HolderExchanges`,
+//! `IssuerExchanges` and `VerifierExchanges` are serializable, so exchanges can be stored and
+//! continued after a restart. Holder exchanges contain blinding factors and have to be stored
+//! privately.
+
+use crate::{
+    application::{
+        datatypes::{
+            Credential,
+            CredentialDefinition,
+            CredentialOffer,
+            CredentialPrivateKey,
+            CredentialProposal,
+            CredentialRequest,
+            CredentialSchema,
+            DidCommMessage,
+            IssuanceExchange,
+            IssuanceState,
+            MasterSecret,
+            NonRevokedInterval,
+            PresentationExchange,
+            PresentationState,
+            ProofRequest,
+            ProofVerification,
+            RevocationIdInformation,
+            RevocationKeyPrivate,
+            RevocationPointInTime,
+            RevocationRegistryDefinition,
+            RevocationState,
+            SubProofRequest,
+        },
+        didcomm::{
+            ack_message,
+            credential_message,
+            credential_offer_message,
+            credential_proposal_message,
+            credential_request_message,
+            get_message_type,
+            get_thread_id,
+            parse_credential,
+            parse_credential_offer,
+            parse_credential_proposal,
+            parse_credential_request,
+            parse_presentation,
+            parse_proof_request,
+            presentation_message,
+            problem_report_message,
+            proof_request_message,
+        },
+        issuer::Issuer,
+        prover::Prover,
+        storage::UsedNonceStore,
+        verifier::Verifier,
+    },
+    utils::utils::get_now_as_iso_string,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::HashMap, error::Error, fmt::Debug};
+use ursa::cl::Witness;
+
+/// Issuance and presentation exchanges of a holder.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HolderExchanges {
+    pub did: String,
+    pub issuances: HashMap<String, IssuanceExchange>,
+    pub presentations: HashMap<String, PresentationExchange>,
+}
+
+impl HolderExchanges {
+    /// Creates a new, empty set of exchanges.
+    ///
+    /// # Arguments
+    /// * `holder_did` - DID of the holder, used as subject of credentials and prover of proofs
+    pub fn new(holder_did: &str) -> HolderExchanges {
+        HolderExchanges {
+            did: holder_did.to_owned(),
+            issuances: HashMap::new(),
+            presentations: HashMap::new(),
+        }
+    }
+
+    /// Starts a new issuance exchange by proposing a credential to an issuer.
+    ///
+    /// # Arguments
+    /// * `protocol` - Either `ISSUE_CREDENTIAL_V1` or `ISSUE_CREDENTIAL_V2`
+    /// * `issuer_did` - DID of the issuer to propose the credential to
+    /// * `schema_did` - DID of the schema to propose the credential for
+    ///
+    /// # Returns
+    /// * `DidCommMessage` - The propose-credential message to send to the issuer
+    pub fn propose_credential(
+        &mut self,
+        protocol: &str,
+        issuer_did: &str,
+        schema_did: &str,
+    ) -> Result<DidCommMessage, Box<dyn Error>> {
+        let proposal = Prover::propose_credential(issuer_did, &self.did, schema_did);
+        let message = credential_proposal_message(protocol, &proposal)?;
+        let thread_id = get_thread_id(&message).to_owned();
+        insert_new(
+            &mut self.issuances,
+            &thread_id,
+            new_issuance(
+                &thread_id,
+                protocol,
+                IssuanceState::ProposalSent,
+                issuer_did,
+                &self.did,
+                schema_did,
+            ),
+        )?;
+
+        Ok(message)
+    }
+
+    /// Receives a credential offer, either answering a proposal or starting a new exchange.
+    ///
+    /// # Arguments
+    /// * `message` - The offer-credential message
+    ///
+    /// # Returns
+    /// * `CredentialOffer` - The received offer
+    pub fn receive_credential_offer(
+        &mut self,
+        message: &DidCommMessage,
+    ) -> Result<CredentialOffer, Box<dyn Error>> {
+        let offer = parse_credential_offer(message)?;
+        if offer.subject != self.did {
+            return Err(Box::from(format!(
+                "credential offer has been created for subject {}, expected {}",
+                &offer.subject, &self.did,
+            )));
+        }
+        let thread_id = get_thread_id(message).to_owned();
+        if self.issuances.contains_key(&thread_id) {
+            let exchange = get_exchange(
+                &mut self.issuances,
+                &thread_id,
+                IssuanceState::ProposalSent,
+                Some(message),
+            )?;
+            let proposal =
+                Prover::propose_credential(&exchange.issuer, &exchange.subject, &exchange.schema);
+            Prover::check_offer_matches_proposal(&offer, &proposal)?;
+            set_issuance_state(exchange, IssuanceState::OfferReceived);
+            exchange.offer = Some(copy(&offer)?);
+        } else {
+            let protocol = get_message_type(message)?.0;
+            let mut exchange = new_issuance(
+                &thread_id,
+                &protocol,
+                IssuanceState::OfferReceived,
+                &offer.issuer,
+                &offer.subject,
+                &offer.schema,
+            );
+            exchange.offer = Some(copy(&offer)?);
+            insert_new(&mut self.issuances, &thread_id, exchange)?;
+        }
+
+        Ok(offer)
+    }
+
+    /// Requests the credential offered in an exchange.
+    ///
+    /// # Arguments
+    /// * `thread_id` - Thread of the exchange
+    /// * `credential_definition` - The credential definition referenced in the offer
+    /// * `credential_schema` - The schema referenced in the offer
+    /// * `master_secret` - The master secret to incorporate into the credential
+    /// * `credential_values` - A mapping of property names to their stringified cleartext values
+    ///
+    /// # Returns
+    /// * `DidCommMessage` - The request-credential message to send to the issuer
+    pub fn request_credential(
+        &mut self,
+        thread_id: &str,
+        credential_definition: CredentialDefinition,
+        credential_schema: CredentialSchema,
+        master_secret: MasterSecret,
+        credential_values: HashMap<String, String>,
+    ) -> Result<DidCommMessage, Box<dyn Error>> {
+        let exchange = get_exchange(
+            &mut self.issuances,
+            thread_id,
+            IssuanceState::OfferReceived,
+            None,
+        )?;
+        let offer = copy(get_offer(exchange)?)?;
+        let (request, blinding_factors) = Prover::request_credential(
+            offer,
+            credential_definition,
+            credential_schema,
+            master_secret,
+            credential_values,
+        )?;
+        let message = credential_request_message(&exchange.protocol, &request, thread_id)?;
+        set_issuance_state(exchange, IssuanceState::RequestSent);
+        exchange.request = Some(request);
+        exchange.blinding_factors = Some(blinding_factors);
+
+        Ok(message)
+    }
+
+    /// Receives and post-processes a requested credential, finishing the exchange.
+    ///
+    /// # Arguments
+    /// * `message` - The issue-credential message
+    /// * `credential_schema` - The schema of the credential
+    /// * `credential_definition` - The definition the credential was issued with
+    /// * `master_secret` - The master secret used when requesting the credential
+    /// * `revocation_registry_definition` - The revocation registry definition of the credential
+    /// * `revocation_state` - The revocation state created by the issuer alongside the credential
+    ///
+    /// # Returns
+    /// * `Credential` - The finished credential, ready to be stored
+    /// * `DidCommMessage` - The ack message to send to the issuer
+    pub fn receive_credential(
+        &mut self,
+        message: &DidCommMessage,
+        credential_schema: &CredentialSchema,
+        credential_definition: &CredentialDefinition,
+        master_secret: &MasterSecret,
+        revocation_registry_definition: &RevocationRegistryDefinition,
+        revocation_state: &RevocationState,
+    ) -> Result<(Credential, DidCommMessage), Box<dyn Error>> {
+        let thread_id = get_thread_id(message).to_owned();
+        let exchange = get_exchange(
+            &mut self.issuances,
+            &thread_id,
+            IssuanceState::RequestSent,
+            Some(message),
+        )?;
+        let mut credential = parse_credential(message)?;
+        if revocation_state.credential_id != credential.id {
+            return Err(Box::from(format!(
+                "revocation state belongs to credential {}, expected {}",
+                &revocation_state.credential_id, &credential.id,
+            )));
+        }
+        let request = exchange
+            .request
+            .as_ref()
+            .ok_or_else(|| format!("exchange {} has no credential request", &thread_id))?;
+        let blinding_factors = copy(
+            exchange
+                .blinding_factors
+                .as_ref()
+                .ok_or_else(|| format!("exchange {} has no blinding factors", &thread_id))?,
+        )?;
+        Prover::post_process_credential_signature(
+            &mut credential,
+            credential_schema,
+            request,
+            credential_definition,
+            blinding_factors,
+            master_secret,
+            revocation_registry_definition,
+            &revocation_state.witness,
+        )?;
+        let ack = ack_message(&exchange.protocol, &thread_id)?;
+        set_issuance_state(exchange, IssuanceState::Done);
+        exchange.credential_id = Some(credential.id.to_owned());
+        exchange.blinding_factors = None;
+
+        Ok((credential, ack))
+    }
+
+    /// Receives a proof request, starting a new presentation exchange.
+    ///
+    /// # Arguments
+    /// * `message` - The request-presentation message
+    ///
+    /// # Returns
+    /// * `ProofRequest` - The received proof request
+    pub fn receive_proof_request(
+        &mut self,
+        message: &DidCommMessage,
+    ) -> Result<ProofRequest, Box<dyn Error>> {
+        let proof_request = parse_proof_request(message)?;
+        if proof_request.prover != self.did {
+            return Err(Box::from(format!(
+                "proof request has been created for prover {}, expected {}",
+                &proof_request.prover, &self.did,
+            )));
+        }
+        let thread_id = get_thread_id(message).to_owned();
+        let protocol = get_message_type(message)?.0;
+        insert_new(
+            &mut self.presentations,
+            &thread_id,
+            new_presentation(
+                &thread_id,
+                &protocol,
+                PresentationState::RequestReceived,
+                copy(&proof_request)?,
+            ),
+        )?;
+
+        Ok(proof_request)
+    }
+
+    /// Answers a received proof request with a presentation.
+    ///
+    /// # Arguments
+    /// * `thread_id` - Thread of the exchange
+    /// * `credentials` - All credentials necessary for answering the proof request, indexed by their according `CredentialSchema`'s ID.
+    /// * `credential_definitions` - All credential definitions necessary for answering the proof request, indexed by their according `CredentialSchema`'s ID.
+    /// * `credential_schemas` - All credential schemas necessary for answering the proof request, indexed by their ID.
+    /// * `revocation_registries` - All revocation registry definitions necessary for answering the proof request, indexed by their according `CredentialSchema`'s ID.
+    /// * `witnesses` - All witnesses needed to prove non-revocation, indexed by their according **`Credential`'s ID**
+    /// * `master_secret` - The master secret all credentials share
+    ///
+    /// # Returns
+    /// * `DidCommMessage` - The presentation message to send to the verifier
+    pub fn present_proof(
+        &mut self,
+        thread_id: &str,
+        credentials: HashMap<String, Credential>,
+        credential_definitions: HashMap<String, CredentialDefinition>,
+        credential_schemas: HashMap<String, CredentialSchema>,
+        revocation_registries: HashMap<String, RevocationRegistryDefinition>,
+        witnesses: HashMap<String, Witness>,
+        master_secret: &MasterSecret,
+    ) -> Result<DidCommMessage, Box<dyn Error>> {
+        let exchange = get_exchange(
+            &mut self.presentations,
+            thread_id,
+            PresentationState::RequestReceived,
+            None,
+        )?;
+        let presentation = Prover::present_proof(
+            copy(&exchange.proof_request)?,
+            credentials,
+            credential_definitions,
+            credential_schemas,
+            revocation_registries,
+            witnesses,
+            master_secret,
+        )?;
+        let message = presentation_message(&exchange.protocol, &presentation, thread_id)?;
+        set_presentation_state(exchange, PresentationState::PresentationSent);
+        exchange.presentation_id = Some(presentation.id);
+
+        Ok(message)
+    }
+
+    /// Receives the verifier's ack of a sent presentation, finishing the exchange.
+    ///
+    /// # Arguments
+    /// * `message` - The ack message
+    pub fn receive_presentation_ack(
+        &mut self,
+        message: &DidCommMessage,
+    ) -> Result<(), Box<dyn Error>> {
+        check_message_name(message, "ack")?;
+        let exchange = get_exchange(
+            &mut self.presentations,
+            get_thread_id(message),
+            PresentationState::PresentationSent,
+            Some(message),
+        )?;
+        set_presentation_state(exchange, PresentationState::Done);
+
+        Ok(())
+    }
+
+    /// Receives a problem report for an issuance or presentation exchange, abandoning it.
+    ///
+    /// # Arguments
+    /// * `message` - The problem-report message
+    /// * `sender_did` - DID of the message's sender, has to be the issuer or verifier of the exchange
+    pub fn receive_problem_report(
+        &mut self,
+        message: &DidCommMessage,
+        sender_did: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let problem = parse_problem(message)?;
+        let thread_id = get_thread_id(message);
+        if let Some(exchange) = self.issuances.get_mut(thread_id) {
+            check_sender(thread_id, sender_did, &exchange.issuer)?;
+            abandon_issuance(exchange, problem)
+        } else if let Some(exchange) = self.presentations.get_mut(thread_id) {
+            check_sender(thread_id, sender_did, &exchange.verifier)?;
+            abandon_presentation(exchange, problem)
+        } else {
+            Err(Box::from(format!("unknown exchange {}", thread_id)))
+        }
+    }
+}
+
+/// Issuance exchanges of an issuer.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssuerExchanges {
+    pub did: String,
+    pub issuances: HashMap<String, IssuanceExchange>,
+}
+
+impl IssuerExchanges {
+    /// Creates a new, empty set of exchanges.
+    ///
+    /// # Arguments
+    /// * `issuer_did` - DID of the issuer
+    pub fn new(issuer_did: &str) -> IssuerExchanges {
+        IssuerExchanges {
+            did: issuer_did.to_owned(),
+            issuances: HashMap::new(),
+        }
+    }
+
+    /// Receives a credential proposal, starting a new exchange.
+    ///
+    /// # Arguments
+    /// * `message` - The propose-credential message
+    /// * `sender_did` - DID of the message's sender, that proposes a credential for itself
+    ///
+    /// # Returns
+    /// * `CredentialProposal` - The received proposal
+    pub fn receive_credential_proposal(
+        &mut self,
+        message: &DidCommMessage,
+        sender_did: &str,
+    ) -> Result<CredentialProposal, Box<dyn Error>> {
+        let proposal = parse_credential_proposal(message, sender_did)?;
+        if proposal.issuer != self.did {
+            return Err(Box::from(format!(
+                "credential proposal has been created for issuer {}, expected {}",
+                &proposal.issuer, &self.did,
+            )));
+        }
+        let thread_id = get_thread_id(message).to_owned();
+        let protocol = get_message_type(message)?.0;
+        insert_new(
+            &mut self.issuances,
+            &thread_id,
+            new_issuance(
+                &thread_id,
+                &protocol,
+                IssuanceState::ProposalReceived,
+                &proposal.issuer,
+                &proposal.subject,
+                &proposal.schema,
+            ),
+        )?;
+
+        Ok(proposal)
+    }
+
+    /// Offers a credential, starting a new exchange.
+    ///
+    /// # Arguments
+    /// * `protocol` - Either `ISSUE_CREDENTIAL_V1` or `ISSUE_CREDENTIAL_V2`
+    /// * `subject_did` - DID of the subject to offer the credential to
+    /// * `schema_did` - DID of the schema of the credential
+    /// * `credential_definition_did` - DID of the credential definition to issue the credential with
+    /// * `validity` - number of seconds the offer can be used for issuance, defaults to `DEFAULT_OFFER_VALIDITY`
+    ///
+    /// # Returns
+    /// * `DidCommMessage` - The offer-credential message to send to the subject
+    pub fn offer_credential(
+        &mut self,
+        protocol: &str,
+        subject_did: &str,
+        schema_did: &str,
+        credential_definition_did: &str,
+        validity: Option<u64>,
+    ) -> Result<DidCommMessage, Box<dyn Error>> {
+        let offer = Issuer::offer_credential(
+            &self.did,
+            subject_did,
+            schema_did,
+            credential_definition_did,
+            validity,
+        )?;
+        let message = credential_offer_message(protocol, &offer, None)?;
+        let thread_id = get_thread_id(&message).to_owned();
+        let mut exchange = new_issuance(
+            &thread_id,
+            protocol,
+            IssuanceState::OfferSent,
+            &self.did,
+            subject_did,
+            schema_did,
+        );
+        exchange.offer = Some(offer);
+        insert_new(&mut self.issuances, &thread_id, exchange)?;
+
+        Ok(message)
+    }
+
+    /// Answers a received credential proposal with an offer for the proposed schema and subject.
+    ///
+    /// # Arguments
+    /// * `thread_id` - Thread of the exchange
+    /// * `credential_definition_did` - DID of the credential definition to issue the credential with
+    /// * `validity` - number of seconds the offer can be used for issuance, defaults to `DEFAULT_OFFER_VALIDITY`
+    ///
+    /// # Returns
+    /// * `DidCommMessage` - The offer-credential message to send to the subject
+    pub fn offer_proposed_credential(
+        &mut self,
+        thread_id: &str,
+        credential_definition_did: &str,
+        validity: Option<u64>,
+    ) -> Result<DidCommMessage, Box<dyn Error>> {
+        let exchange = get_exchange(
+            &mut self.issuances,
+            thread_id,
+            IssuanceState::ProposalReceived,
+            None,
+        )?;
+        let offer = Issuer::offer_credential(
+            &self.did,
+            &exchange.subject,
+            &exchange.schema,
+            credential_definition_did,
+            validity,
+        )?;
+        let message = credential_offer_message(&exchange.protocol, &offer, Some(thread_id))?;
+        set_issuance_state(exchange, IssuanceState::OfferSent);
+        exchange.offer = Some(offer);
+
+        Ok(message)
+    }
+
+    /// Receives a credential request answering a sent offer.
+    ///
+    /// # Arguments
+    /// * `message` - The request-credential message
+    ///
+    /// # Returns
+    /// * `CredentialRequest` - The received request
+    pub fn receive_credential_request(
+        &mut self,
+        message: &DidCommMessage,
+    ) -> Result<CredentialRequest, Box<dyn Error>> {
+        let request = parse_credential_request(message)?;
+        let exchange = get_exchange(
+            &mut self.issuances,
+            get_thread_id(message),
+            IssuanceState::OfferSent,
+            Some(message),
+        )?;
+        let offer = get_offer(exchange)?;
+        if request.subject != offer.subject
+            || request.credential_definition != offer.credential_definition
+        {
+            return Err(Box::from(format!(
+                "credential request does not match offer of exchange {}",
+                &exchange.thread_id,
+            )));
+        }
+        set_issuance_state(exchange, IssuanceState::RequestReceived);
+        exchange.request = Some(copy(&request)?);
+
+        Ok(request)
+    }
+
+    /// Issues the credential requested in an exchange.
+    ///
+    /// # Arguments
+    /// * `thread_id` - Thread of the exchange
+    /// * `used_nonces` - Store of the nonces of offers that have already been used for issuance
+    /// * `credential_definition` - Definition to issue the credential with
+    /// * `credential_private_key` - Private key of the credential definition
+    /// * `credential_schema` - Schema of the credential
+    /// * `revocation_registry_definition` - Revocation registry definition to use for the credential
+    /// * `revocation_private_key` - Private key of the revocation registry definition
+    /// * `revocation_info` - Tracker of current and next revocation IDs to use
+    /// * `issuance_date` - Optional issuance date, defaults to now
+    /// * `expiration_date` - Optional expiration date of the credential
+    ///
+    /// # Returns
+    /// * `DidCommMessage` - The issue-credential message to send to the subject
+    /// * `RevocationState` - Revocation state of the credential, has to be provided to the subject as well
+    /// * `RevocationIdInformation` - Updated tracker of revocation IDs
+    pub fn issue_credential(
+        &mut self,
+        thread_id: &str,
+        used_nonces: &dyn UsedNonceStore,
+        credential_definition: CredentialDefinition,
+        credential_private_key: CredentialPrivateKey,
+        credential_schema: CredentialSchema,
+        revocation_registry_definition: &mut RevocationRegistryDefinition,
+        revocation_private_key: RevocationKeyPrivate,
+        revocation_info: &RevocationIdInformation,
+        issuance_date: Option<String>,
+        expiration_date: Option<String>,
+    ) -> Result<(DidCommMessage, RevocationState, RevocationIdInformation), Box<dyn Error>> {
+        let exchange = get_exchange(
+            &mut self.issuances,
+            thread_id,
+            IssuanceState::RequestReceived,
+            None,
+        )?;
+        let request = copy(
+            exchange
+                .request
+                .as_ref()
+                .ok_or_else(|| format!("exchange {} has no credential request", thread_id))?,
+        )?;
+        let (credential, revocation_state, revocation_info) = Issuer::issue_credential(
+            &self.did,
+            &exchange.subject,
+            get_offer(exchange)?,
+            request,
+            used_nonces,
+            credential_definition,
+            credential_private_key,
+            credential_schema,
+            revocation_registry_definition,
+            revocation_private_key,
+            revocation_info,
+            issuance_date,
+            expiration_date,
+        )?;
+        let message = credential_message(&exchange.protocol, &credential, thread_id)?;
+        set_issuance_state(exchange, IssuanceState::CredentialIssued);
+        exchange.credential_id = Some(credential.id);
+
+        Ok((message, revocation_state, revocation_info))
+    }
+
+    /// Receives the subject's ack of an issued credential, finishing the exchange.
+    ///
+    /// # Arguments
+    /// * `message` - The ack message
+    pub fn receive_credential_ack(
+        &mut self,
+        message: &DidCommMessage,
+    ) -> Result<(), Box<dyn Error>> {
+        check_message_name(message, "ack")?;
+        let exchange = get_exchange(
+            &mut self.issuances,
+            get_thread_id(message),
+            IssuanceState::CredentialIssued,
+            Some(message),
+        )?;
+        set_issuance_state(exchange, IssuanceState::Done);
+
+        Ok(())
+    }
+
+    /// Receives a problem report for an exchange, abandoning it.
+    ///
+    /// # Arguments
+    /// * `message` - The problem-report message
+    /// * `sender_did` - DID of the message's sender, has to be the subject of the exchange
+    pub fn receive_problem_report(
+        &mut self,
+        message: &DidCommMessage,
+        sender_did: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let problem = parse_problem(message)?;
+        let thread_id = get_thread_id(message);
+        let exchange = self
+            .issuances
+            .get_mut(thread_id)
+            .ok_or_else(|| format!("unknown exchange {}", thread_id))?;
+        check_sender(thread_id, sender_did, &exchange.subject)?;
+        abandon_issuance(exchange, problem)
+    }
+}
+
+/// Presentation exchanges of a verifier.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifierExchanges {
+    pub did: String,
+    pub presentations: HashMap<String, PresentationExchange>,
+}
+
+impl VerifierExchanges {
+    /// Creates a new, empty set of exchanges.
+    ///
+    /// # Arguments
+    /// * `verifier_did` - DID of the verifier
+    pub fn new(verifier_did: &str) -> VerifierExchanges {
+        VerifierExchanges {
+            did: verifier_did.to_owned(),
+            presentations: HashMap::new(),
+        }
+    }
+
+    /// Requests a proof, starting a new exchange.
+    ///
+    /// # Arguments
+    /// * `protocol` - Either `PRESENT_PROOF_V1` or `PRESENT_PROOF_V2`
+    /// * `prover_did` - DID of the prover
+    /// * `sub_proof_requests` - Collection of subproof requests to be requested from the prover
+    /// * `non_revoked` - Optional time range in which the proven credentials have to be non-revoked
+    /// * `validity` - Optional number of seconds the request can be answered for, does not expire if omitted
+    ///
+    /// # Returns
+    /// * `DidCommMessage` - The request-presentation message to send to the prover
+    pub fn request_proof(
+        &mut self,
+        protocol: &str,
+        prover_did: &str,
+        sub_proof_requests: Vec<SubProofRequest>,
+        non_revoked: Option<NonRevokedInterval>,
+        validity: Option<u64>,
+    ) -> Result<DidCommMessage, Box<dyn Error>> {
+        let proof_request = Verifier::request_proof(
+            &self.did,
+            prover_did,
+            sub_proof_requests,
+            non_revoked,
+            validity,
+        )?;
+        let message = proof_request_message(protocol, &proof_request, None)?;
+        let thread_id = get_thread_id(&message).to_owned();
+        insert_new(
+            &mut self.presentations,
+            &thread_id,
+            new_presentation(
+                &thread_id,
+                protocol,
+                PresentationState::RequestSent,
+                proof_request,
+            ),
+        )?;
+
+        Ok(message)
+    }
+
+    /// Receives and verifies a presentation answering a sent proof request, finishing the
+    /// exchange. Successfully verified presentations are answered with an ack, rejected ones with
+    /// a problem report, that abandons the exchange.
+    ///
+    /// # Arguments
+    /// * `message` - The presentation message
+    /// * `credential_definitions` - All definitions associated to the sent proofs, indexed by the according `CredentialSchema`'s ID
+    /// * `credential_schemas` - All schemas associated to the sent proofs, indexed by their ID
+    /// * `revocation_registry_definition` - All revocation registry definitions associated to the sent proofs, indexed by the according `CredentialSchema`'s ID
+    /// * `revocation_point_in_time` - Optional point in time to check non-revocation against instead of the latest registry version or, if the proof request has a `non_revoked` interval, the registry version used by the prover
+    ///
+    /// # Returns
+    /// * `ProofVerification` - States whether the verification was successful or not
+    /// * `DidCommMessage` - The ack or problem-report message to send to the prover
+    pub fn receive_presentation(
+        &mut self,
+        message: &DidCommMessage,
+        credential_definitions: HashMap<String, CredentialDefinition>,
+        credential_schemas: HashMap<String, CredentialSchema>,
+        revocation_registry_definition: HashMap<String, Option<RevocationRegistryDefinition>>,
+        revocation_point_in_time: Option<RevocationPointInTime>,
+    ) -> Result<(ProofVerification, DidCommMessage), Box<dyn Error>> {
+        let presentation = parse_presentation(message)?;
+        let thread_id = get_thread_id(message).to_owned();
+        let exchange = get_exchange(
+            &mut self.presentations,
+            &thread_id,
+            PresentationState::RequestSent,
+            Some(message),
+        )?;
+        exchange.presentation_id = Some(presentation.id.to_owned());
+        let verification = Verifier::verify_proof(
+            presentation,
+            copy(&exchange.proof_request)?,
+            credential_definitions,
+            credential_schemas,
+            revocation_registry_definition,
+            revocation_point_in_time,
+        );
+        let answer = match &verification.reason {
+            None => {
+                set_presentation_state(exchange, PresentationState::Done);
+                ack_message(&exchange.protocol, &thread_id)?
+            }
+            Some(reason) => {
+                set_presentation_state(exchange, PresentationState::Abandoned);
+                exchange.problem = Some(reason.to_owned());
+                problem_report_message(&exchange.protocol, &thread_id, "rejected", reason)?
+            }
+        };
+        exchange.verification = Some(copy(&verification)?);
+
+        Ok((verification, answer))
+    }
+
+    /// Receives a problem report for an exchange, abandoning it.
+    ///
+    /// # Arguments
+    /// * `message` - The problem-report message
+    /// * `sender_did` - DID of the message's sender, has to be the prover of the exchange
+    pub fn receive_problem_report(
+        &mut self,
+        message: &DidCommMessage,
+        sender_did: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let problem = parse_problem(message)?;
+        let thread_id = get_thread_id(message);
+        let exchange = self
+            .presentations
+            .get_mut(thread_id)
+            .ok_or_else(|| format!("unknown exchange {}", thread_id))?;
+        check_sender(thread_id, sender_did, &exchange.prover)?;
+        abandon_presentation(exchange, problem)
+    }
+}
+
+/// Copies values of types not implementing `Clone`, e.g. containing ursa types.
+fn copy<T: Serialize + DeserializeOwned>(value: &T) -> Result<T, Box<dyn Error>> {
+    Ok(serde_json::from_str(&serde_json::to_string(value)?)?)
+}
+
+fn insert_new<T>(
+    exchanges: &mut HashMap<String, T>,
+    thread_id: &str,
+    exchange: T,
+) -> Result<(), Box<dyn Error>> {
+    if exchanges.contains_key(thread_id) {
+        return Err(Box::from(format!("exchange {} already exists", thread_id)));
+    }
+    exchanges.insert(thread_id.to_owned(), exchange);
+    Ok(())
+}
+
+fn new_issuance(
+    thread_id: &str,
+    protocol: &str,
+    state: IssuanceState,
+    issuer: &str,
+    subject: &str,
+    schema: &str,
+) -> IssuanceExchange {
+    IssuanceExchange {
+        thread_id: thread_id.to_owned(),
+        protocol: protocol.to_owned(),
+        state,
+        issuer: issuer.to_owned(),
+        subject: subject.to_owned(),
+        schema: schema.to_owned(),
+        offer: None,
+        request: None,
+        blinding_factors: None,
+        credential_id: None,
+        problem: None,
+        updated_at: get_now_as_iso_string(),
+    }
+}
+
+fn new_presentation(
+    thread_id: &str,
+    protocol: &str,
+    state: PresentationState,
+    proof_request: ProofRequest,
+) -> PresentationExchange {
+    PresentationExchange {
+        thread_id: thread_id.to_owned(),
+        protocol: protocol.to_owned(),
+        state,
+        verifier: proof_request.verifier.to_owned(),
+        prover: proof_request.prover.to_owned(),
+        proof_request,
+        presentation_id: None,
+        verification: None,
+        problem: None,
+        updated_at: get_now_as_iso_string(),
+    }
+}
+
+/// Accessors shared by issuance and presentation exchanges.
+trait Exchange {
+    type State: PartialEq + Debug;
+
+    fn state(&self) -> &Self::State;
+
+    fn protocol(&self) -> &str;
+}
+
+impl Exchange for IssuanceExchange {
+    type State = IssuanceState;
+
+    fn state(&self) -> &IssuanceState {
+        &self.state
+    }
+
+    fn protocol(&self) -> &str {
+        &self.protocol
+    }
+}
+
+impl Exchange for PresentationExchange {
+    type State = PresentationState;
+
+    fn state(&self) -> &PresentationState {
+        &self.state
+    }
+
+    fn protocol(&self) -> &str {
+        &self.protocol
+    }
+}
+
+/// Gets an exchange expected to be in the given state. If given, also checks that the received
+/// message uses the exchange's protocol.
+fn get_exchange<'a, T: Exchange>(
+    exchanges: &'a mut HashMap<String, T>,
+    thread_id: &str,
+    expected_state: T::State,
+    message: Option<&DidCommMessage>,
+) -> Result<&'a mut T, Box<dyn Error>> {
+    let exchange = exchanges
+        .get_mut(thread_id)
+        .ok_or_else(|| format!("unknown exchange {}", thread_id))?;
+    if *exchange.state() != expected_state {
+        return Err(Box::from(format!(
+            "exchange {} is in state {:?}, expected {:?}",
+            thread_id,
+            exchange.state(),
+            expected_state,
+        )));
+    }
+    if let Some(message) = message {
+        check_message_protocol(message, exchange.protocol())?;
+    }
+    Ok(exchange)
+}
+
+fn get_offer(exchange: &IssuanceExchange) -> Result<&CredentialOffer, Box<dyn Error>> {
+    exchange
+        .offer
+        .as_ref()
+        .ok_or_else(|| Box::from(format!("exchange {} has no offer", &exchange.thread_id)))
+}
+
+fn set_issuance_state(exchange: &mut IssuanceExchange, state: IssuanceState) {
+    exchange.state = state;
+    exchange.updated_at = get_now_as_iso_string();
+}
+
+fn set_presentation_state(exchange: &mut PresentationExchange, state: PresentationState) {
+    exchange.state = state;
+    exchange.updated_at = get_now_as_iso_string();
+}
+
+fn abandon_issuance(
+    exchange: &mut IssuanceExchange,
+    problem: String,
+) -> Result<(), Box<dyn Error>> {
+    if exchange.state == IssuanceState::Done || exchange.state == IssuanceState::Abandoned {
+        return Err(Box::from(format!(
+            "exchange {} has already been finished",
+            &exchange.thread_id,
+        )));
+    }
+    set_issuance_state(exchange, IssuanceState::Abandoned);
+    exchange.problem = Some(problem);
+    exchange.blinding_factors = None;
+    Ok(())
+}
+
+fn abandon_presentation(
+    exchange: &mut PresentationExchange,
+    problem: String,
+) -> Result<(), Box<dyn Error>> {
+    if exchange.state == PresentationState::Done || exchange.state == PresentationState::Abandoned {
+        return Err(Box::from(format!(
+            "exchange {} has already been finished",
+            &exchange.thread_id,
+        )));
+    }
+    set_presentation_state(exchange, PresentationState::Abandoned);
+    exchange.problem = Some(problem);
+    Ok(())
+}
+
+fn check_message_protocol(message: &DidCommMessage, protocol: &str) -> Result<(), Box<dyn Error>> {
+    let message_protocol = get_message_type(message)?.0;
+    if message_protocol != protocol {
+        return Err(Box::from(format!(
+            "message {} uses protocol {}, expected {}",
+            &message.id, message_protocol, protocol,
+        )));
+    }
+    Ok(())
+}
+
+/// Checks that a message has been sent by the counterparty recorded in its exchange.
+fn check_sender(
+    thread_id: &str,
+    sender_did: &str,
+    counterparty: &str,
+) -> Result<(), Box<dyn Error>> {
+    if sender_did != counterparty {
+        return Err(Box::from(format!(
+            "message for exchange {} has been sent by {}, expected {}",
+            thread_id, sender_did, counterparty,
+        )));
+    }
+    Ok(())
+}
+
+fn check_message_name(message: &DidCommMessage, name: &str) -> Result<(), Box<dyn Error>> {
+    let message_name = get_message_type(message)?.1;
+    if message_name != name {
+        return Err(Box::from(format!(
+            "expected {} message, got {}",
+            name, &message.r#type
+        )));
+    }
+    Ok(())
+}
+
+/// Gets the description of a problem-report message, or its code if it has no description.
+fn parse_problem(message: &DidCommMessage) -> Result<String, Box<dyn Error>> {
+    check_message_name(message, "problem-report")?;
+    let description = message.content.get("description");
+    let problem = description
+        .and_then(|description| description.get("en").or_else(|| description.get("code")))
+        .and_then(|problem| problem.as_str())
+        .unwrap_or("unknown problem");
+    Ok(problem.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate utilities;
+
+    use super::*;
+    use crate::application::{
+        didcomm::{ISSUE_CREDENTIAL_V2, PRESENT_PROOF_V1},
+        storage::InMemoryUsedNonceStore,
+    };
+    use utilities::test_data::{
+        accounts::local::{ISSUER_DID, ISSUER_PRIVATE_KEY, ISSUER_PUBLIC_KEY_DID},
+        did::EXAMPLE_DID_1,
+        vc_zkp::{EXAMPLE_CREDENTIAL_SCHEMA, EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID},
+    };
+    use vade_signer::{LocalSigner, Signer};
+
+    #[test]
+    fn can_continue_issuance_exchanges_after_restart() -> Result<(), Box<dyn Error>> {
+        let mut issuer = IssuerExchanges::new("did:evan:issuer");
+        let mut holder = HolderExchanges::new("did:evan:holder");
+
+        let proposal =
+            holder.propose_credential(ISSUE_CREDENTIAL_V2, "did:evan:issuer", "did:evan:schema")?;
+        issuer.receive_credential_proposal(&proposal, "did:evan:holder")?;
+        let thread_id = get_thread_id(&proposal).to_owned();
+        let offer = issuer.offer_proposed_credential(&thread_id, "did:evan:definition", None)?;
+
+        // exchanges survive serialization
+        let mut holder: HolderExchanges = serde_json::from_str(&serde_json::to_string(&holder)?)?;
+        holder.receive_credential_offer(&offer)?;
+        assert_eq!(
+            holder.issuances[&thread_id].state,
+            IssuanceState::OfferReceived
+        );
+        assert_eq!(
+            holder.issuances[&thread_id]
+                .offer
+                .as_ref()
+                .map(|o| o.credential_definition.as_str()),
+            Some("did:evan:definition"),
+        );
+
+        // offers cannot be received twice and proposals not answered twice
+        assert!(holder.receive_credential_offer(&offer).is_err());
+        assert!(issuer
+            .offer_proposed_credential(&thread_id, "did:evan:definition", None)
+            .is_err());
+
+        let report = problem_report_message(ISSUE_CREDENTIAL_V2, &thread_id, "declined", "nope")?;
+        match issuer.receive_problem_report(&report, "did:evan:other") {
+            Ok(_) => {
+                return Err(Box::from(
+                    "problem report of unknown sender has been accepted",
+                ))
+            }
+            Err(e) => assert!(e.to_string().contains("has been sent by did:evan:other")),
+        }
+        issuer.receive_problem_report(&report, "did:evan:holder")?;
+        assert_eq!(issuer.issuances[&thread_id].state, IssuanceState::Abandoned);
+        assert_eq!(
+            issuer.issuances[&thread_id].problem.as_deref(),
+            Some("nope")
+        );
+        assert!(issuer
+            .receive_problem_report(&report, "did:evan:holder")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unexpected_presentation_messages() -> Result<(), Box<dyn Error>> {
+        let mut verifier = VerifierExchanges::new("did:evan:verifier");
+        let mut holder = HolderExchanges::new("did:evan:holder");
+
+        let request =
+            verifier.request_proof(PRESENT_PROOF_V1, "did:evan:holder", vec![], None, None)?;
+        assert!(HolderExchanges::new("did:evan:other")
+            .receive_proof_request(&request)
+            .is_err());
+        holder.receive_proof_request(&request)?;
+        assert!(holder.receive_proof_request(&request).is_err());
+
+        // nothing has been presented yet
+        let thread_id = get_thread_id(&request).to_owned();
+        let ack = ack_message(PRESENT_PROOF_V1, &thread_id)?;
+        match holder.receive_presentation_ack(&ack) {
+            Ok(_) => return Err(Box::from("ack for unsent presentation has been accepted")),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                format!(
+                    "exchange {} is in state RequestReceived, expected PresentationSent",
+                    &thread_id,
+                ),
+            ),
+        }
+        assert!(holder
+            .receive_problem_report(&ack, "did:evan:verifier")
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_issue_and_present_credentials_through_exchanges() -> Result<(), Box<dyn Error>> {
+        let schema: CredentialSchema = serde_json::from_str(&EXAMPLE_CREDENTIAL_SCHEMA)?;
+        let signer: Box<dyn Signer> = Box::new(LocalSigner::new());
+        let (definition, credential_private_key) = Issuer::create_credential_definition(
+            &EXAMPLE_DID_1,
+            &ISSUER_DID,
+            &schema,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        let (mut rev_reg_def, revocation_private_key, revocation_info) =
+            Issuer::create_revocation_registry_definition(
+                &EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID,
+                &definition,
+                &ISSUER_PUBLIC_KEY_DID,
+                &ISSUER_PRIVATE_KEY,
+                &signer,
+                42,
+            )
+            .await?;
+        let master_secret = Prover::create_master_secret()?;
+        let used_nonces = InMemoryUsedNonceStore::new();
+
+        let mut issuer = IssuerExchanges::new(&ISSUER_DID);
+        let mut holder = HolderExchanges::new(&EXAMPLE_DID_1);
+        let offer = issuer.offer_credential(
+            ISSUE_CREDENTIAL_V2,
+            &EXAMPLE_DID_1,
+            &schema.id,
+            &definition.id,
+            None,
+        )?;
+        let thread_id = get_thread_id(&offer).to_owned();
+        holder.receive_credential_offer(&offer)?;
+        let mut values = HashMap::new();
+        values.insert("test_property_string".to_owned(), "value".to_owned());
+        let request = holder.request_credential(
+            &thread_id,
+            copy(&definition)?,
+            copy(&schema)?,
+            copy(&master_secret)?,
+            values,
+        )?;
+
+        // both sides continue after a restart between request and issuance
+        let mut holder: HolderExchanges = serde_json::from_str(&serde_json::to_string(&holder)?)?;
+        let mut issuer: IssuerExchanges = serde_json::from_str(&serde_json::to_string(&issuer)?)?;
+        assert_eq!(
+            holder.issuances[&thread_id].state,
+            IssuanceState::RequestSent
+        );
+        assert!(holder.issuances[&thread_id].blinding_factors.is_some());
+
+        issuer.receive_credential_request(&request)?;
+        let (issued, revocation_state, _) = issuer.issue_credential(
+            &thread_id,
+            &used_nonces,
+            copy(&definition)?,
+            credential_private_key,
+            copy(&schema)?,
+            &mut rev_reg_def,
+            revocation_private_key,
+            &revocation_info,
+            None,
+            None,
+        )?;
+        let (credential, ack) = holder.receive_credential(
+            &issued,
+            &schema,
+            &definition,
+            &master_secret,
+            &rev_reg_def,
+            &revocation_state,
+        )?;
+        issuer.receive_credential_ack(&ack)?;
+        assert_eq!(holder.issuances[&thread_id].state, IssuanceState::Done);
+        assert_eq!(issuer.issuances[&thread_id].state, IssuanceState::Done);
+        assert!(holder.issuances[&thread_id].blinding_factors.is_none());
+        assert_eq!(
+            holder.issuances[&thread_id].credential_id.as_deref(),
+            Some(credential.id.as_str()),
+        );
+
+        let mut verifier = VerifierExchanges::new(&ISSUER_DID);
+        let sub_proof_request = SubProofRequest {
+            schema: schema.id.to_owned(),
+            revealed_attributes: vec!["test_property_string".to_owned()],
+            restrictions: Vec::new(),
+        };
+        let mut credentials = HashMap::new();
+        credentials.insert(schema.id.to_owned(), credential);
+        let mut definitions = HashMap::new();
+        definitions.insert(schema.id.to_owned(), definition);
+        let mut schemas = HashMap::new();
+        schemas.insert(schema.id.to_owned(), schema);
+        let mut witnesses = HashMap::new();
+        witnesses.insert(
+            revocation_state.credential_id.to_owned(),
+            revocation_state.witness,
+        );
+        let mut revocation_registries = HashMap::new();
+        revocation_registries.insert(sub_proof_request.schema.to_owned(), rev_reg_def);
+
+        // verified presentations are acked
+        let proof_request = verifier.request_proof(
+            PRESENT_PROOF_V1,
+            &EXAMPLE_DID_1,
+            vec![copy(&sub_proof_request)?],
+            None,
+            None,
+        )?;
+        let thread_id = get_thread_id(&proof_request).to_owned();
+        holder.receive_proof_request(&proof_request)?;
+        let presentation = holder.present_proof(
+            &thread_id,
+            copy(&credentials)?,
+            copy(&definitions)?,
+            copy(&schemas)?,
+            copy(&revocation_registries)?,
+            copy(&witnesses)?,
+            &master_secret,
+        )?;
+        let mut registries = HashMap::new();
+        registries.insert(
+            sub_proof_request.schema.to_owned(),
+            Some(copy(&revocation_registries[&sub_proof_request.schema])?),
+        );
+        let (verification, answer) = verifier.receive_presentation(
+            &presentation,
+            copy(&definitions)?,
+            copy(&schemas)?,
+            copy(&registries)?,
+            None,
+        )?;
+        assert_eq!(verification.status, "verified");
+        assert_eq!(get_message_type(&answer)?.1, "ack");
+        assert_eq!(
+            verifier.presentations[&thread_id].state,
+            PresentationState::Done
+        );
+        holder.receive_presentation_ack(&answer)?;
+        assert_eq!(
+            holder.presentations[&thread_id].state,
+            PresentationState::Done
+        );
+
+        // rejected presentations are answered with a problem report, that abandons the exchange
+        let proof_request = verifier.request_proof(
+            PRESENT_PROOF_V1,
+            &EXAMPLE_DID_1,
+            vec![sub_proof_request],
+            None,
+            None,
+        )?;
+        let thread_id = get_thread_id(&proof_request).to_owned();
+        holder.receive_proof_request(&proof_request)?;
+        let presentation = holder.present_proof(
+            &thread_id,
+            credentials,
+            copy(&definitions)?,
+            copy(&schemas)?,
+            revocation_registries,
+            witnesses,
+            &master_secret,
+        )?;
+        let (verification, answer) = verifier.receive_presentation(
+            &presentation,
+            HashMap::new(),
+            schemas,
+            registries,
+            None,
+        )?;
+        assert_eq!(verification.status, "rejected");
+        assert_eq!(get_message_type(&answer)?.1, "problem-report");
+        assert_eq!(
+            verifier.presentations[&thread_id].state,
+            PresentationState::Abandoned,
+        );
+        holder.receive_problem_report(&answer, &ISSUER_DID)?;
+        assert_eq!(
+            holder.presentations[&thread_id].state,
+            PresentationState::Abandoned,
+        );
+        assert_eq!(
+            holder.presentations[&thread_id].problem,
+            verification.reason
+        );
+
+        Ok(())
+    }
+}
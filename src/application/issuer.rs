@@ -25,6 +25,7 @@ use crate::{
             CredentialSchemaReference,
             CredentialSignature,
             CredentialSubject,
+            DeltaCheckpoint,
             DeltaHistory,
             EncodedCredentialValue,
             RevocationIdInformation,
@@ -33,9 +34,15 @@ use crate::{
             SchemaProperty,
         },
         prover::Prover,
+        storage::UsedNonceStore,
     },
     crypto::{crypto_issuer::Issuer as CryptoIssuer, crypto_utils::create_assertion_proof},
-    utils::utils::{generate_uuid, get_now_as_iso_string},
+    utils::utils::{
+        generate_uuid,
+        get_iso_string_in_seconds,
+        get_now_as_iso_string,
+        is_date_in_past,
+    },
 };
 use std::{
     collections::{HashMap, HashSet},
@@ -57,6 +64,9 @@ use std::time::{SystemTime, UNIX_EPOCH};
 #[cfg(target_arch = "wasm32")]
 use wasm_timer::{SystemTime, UNIX_EPOCH};
 
+/// Number of seconds a credential offer can be used for issuance, if not specified otherwise
+pub const DEFAULT_OFFER_VALIDITY: u64 = 86400;
+
 /// Holds the logic needed to issue and revoke credentials.
 pub struct Issuer {}
 
@@ -224,6 +234,7 @@ impl Issuer {
                 .duration_since(UNIX_EPOCH)
                 .map_err(|_| "Error generating unix timestamp for delta history")?
                 .as_secs(),
+            version: 0,
             delta: crypto_rev_def.registry_delta.clone(),
         };
 
@@ -232,7 +243,9 @@ impl Issuer {
             credential_definition: credential_definition.id.to_string(),
             registry: crypto_rev_def.registry,
             registry_delta: crypto_rev_def.registry_delta,
+            version: 0,
             delta_history: vec![delta_history],
+            checkpoint: None,
             maximum_credential_count,
             revocation_public_key: crypto_rev_def.revocation_public_key,
             tails: crypto_rev_def.tails,
@@ -266,7 +279,9 @@ impl Issuer {
     /// # Arguments
     /// * `issuer_did` - DID of the issuer
     /// * `subject_did` - DID of the subject
+    /// * `credential_offer` - Credential offer the credential request has been created for
     /// * `credential_request` - Credential request object sent by the subject
+    /// * `used_nonces` - Store to mark the nonce of the credential offer as used in
     /// * `credential_definition` - Credential definition to use for issuance as specified by the credential request
     /// * `credential_private_key` - Issuer's private key associated with the credential definition
     /// * `credential_schema` - Credential schema to be used as specified by the credential request
@@ -274,6 +289,7 @@ impl Issuer {
     /// * `revocation_private_key` - Private key associated to the revocation registry definition
     /// * `revocation_info` - Revocation info containing ID counter. Hold by credential definition owner
     /// * `issuance_date` - issuance date for credential, defaults to now, must be a date in the future if provided
+    /// * `expiration_date` - optional expiration date for credential, not part of the signed values
     ///
     /// # Returns
     /// Tuple containing
@@ -282,7 +298,9 @@ impl Issuer {
     pub fn issue_credential(
         issuer_did: &str,
         subject_did: &str,
+        credential_offer: &CredentialOffer,
         credential_request: CredentialRequest,
+        used_nonces: &dyn UsedNonceStore,
         credential_definition: CredentialDefinition,
         credential_private_key: CredentialPrivateKey,
         credential_schema: CredentialSchema,
@@ -290,7 +308,92 @@ impl Issuer {
         revocation_private_key: RevocationKeyPrivate,
         revocation_info: &RevocationIdInformation,
         issuance_date: Option<String>,
+        expiration_date: Option<String>,
     ) -> Result<(Credential, RevocationState, RevocationIdInformation), Box<dyn Error>> {
+        Issuer::accept_credential_request(
+            issuer_did,
+            subject_did,
+            credential_offer,
+            &credential_request,
+            used_nonces,
+            &credential_definition,
+            &credential_schema,
+        )?;
+
+        // Get next unused revocation ID for credential, mark as used & increment counter,
+        // IDs exceeding the registry's capacity are rejected on issuance
+        let rev_idx = revocation_info.next_unused_id;
+        let mut used_ids: HashSet<u32> = revocation_info.used_ids.clone();
+        if !used_ids.insert(rev_idx) {
+            return Err(Box::from("Could not use next revocation ID as it has already been used - Counter information seems to be corrupted"));
+        }
+
+        let new_rev_info = RevocationIdInformation {
+            definition_id: revocation_registry_definition.id.clone(),
+            next_unused_id: rev_idx + 1,
+            used_ids,
+        };
+
+        let (credential, revocation_state) = Issuer::issue_credential_with_revocation_id(
+            issuer_did,
+            subject_did,
+            credential_request,
+            credential_definition,
+            credential_private_key,
+            credential_schema,
+            revocation_registry_definition,
+            revocation_private_key,
+            rev_idx,
+            issuance_date,
+            expiration_date,
+        )?;
+
+        Ok((credential, revocation_state, new_rev_info))
+    }
+
+    /// Issue a new credential with a given revocation ID, based on a credential request received by the credential subject.
+    /// The credential request has to be accepted with `accept_credential_request` first, the revocation ID
+    /// has to be allocated afterwards, e.g. with `IssuerStorage::allocate_revocation_id`.
+    ///
+    /// # Arguments
+    /// * `issuer_did` - DID of the issuer
+    /// * `subject_did` - DID of the subject
+    /// * `credential_request` - Credential request object sent by the subject
+    /// * `credential_definition` - Credential definition to use for issuance as specified by the credential request
+    /// * `credential_private_key` - Issuer's private key associated with the credential definition
+    /// * `credential_schema` - Credential schema to be used as specified by the credential request
+    /// * `revocation_registry_definition` - Revocation registry definition to be used for issuance
+    /// * `revocation_private_key` - Private key associated to the revocation registry definition
+    /// * `revocation_id` - Unused revocation ID to issue the credential with
+    /// * `issuance_date` - issuance date for credential, defaults to now, must be a date in the future if provided
+    /// * `expiration_date` - optional expiration date for credential, not part of the signed values
+    ///
+    /// # Returns
+    /// Tuple containing
+    /// * `Credential` - Issued credential
+    /// * `RevocationState` - Initial revocation state of the credential
+    pub fn issue_credential_with_revocation_id(
+        issuer_did: &str,
+        subject_did: &str,
+        credential_request: CredentialRequest,
+        credential_definition: CredentialDefinition,
+        credential_private_key: CredentialPrivateKey,
+        credential_schema: CredentialSchema,
+        revocation_registry_definition: &mut RevocationRegistryDefinition,
+        revocation_private_key: RevocationKeyPrivate,
+        revocation_id: u32,
+        issuance_date: Option<String>,
+        expiration_date: Option<String>,
+    ) -> Result<(Credential, RevocationState), Box<dyn Error>> {
+        if revocation_id == 0
+            || revocation_id > revocation_registry_definition.maximum_credential_count
+        {
+            return Err(Box::from(format!(
+                "revocation ID {} is not part of revocation registry {}",
+                revocation_id, revocation_registry_definition.id,
+            )));
+        }
+
         let mut data: HashMap<String, EncodedCredentialValue> = HashMap::new();
         //
         // Optional value handling
@@ -333,29 +436,13 @@ impl Issuer {
             r#type: "EvanZKPSchema".to_string(),
         };
 
-        // Get next unused revocation ID for credential, mark as used & increment counter
-        if revocation_info.next_unused_id == revocation_registry_definition.maximum_credential_count
-        {
-        }
-        let rev_idx = revocation_info.next_unused_id;
-        let mut used_ids: HashSet<u32> = revocation_info.used_ids.clone();
-        if !used_ids.insert(rev_idx) {
-            return Err(Box::from("Could not use next revocation ID as it has already been used - Counter information seems to be corrupted"));
-        }
-
-        let new_rev_info = RevocationIdInformation {
-            definition_id: revocation_registry_definition.id.clone(),
-            next_unused_id: rev_idx + 1,
-            used_ids,
-        };
-
         let (signature, signature_correctness_proof, issuance_nonce, witness) =
             CryptoIssuer::sign_credential_with_revocation(
                 &processed_credential_request,
                 &credential_private_key,
                 &credential_definition.public_key,
                 revocation_registry_definition,
-                rev_idx,
+                revocation_id,
                 &revocation_private_key,
             )?;
 
@@ -367,12 +454,13 @@ impl Issuer {
 
         let revocation_state = RevocationState {
             credential_id: credential_id.clone(),
-            revocation_id: rev_idx,
+            revocation_id,
             delta,
             updated: SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .map_err(|_| "Error generating unix timestamp for delta history")?
                 .as_secs(),
+            version: Some(revocation_registry_definition.version),
             witness,
         };
 
@@ -382,7 +470,7 @@ impl Issuer {
             issuance_nonce,
             signature,
             signature_correctness_proof,
-            revocation_id: rev_idx,
+            revocation_id,
             revocation_registry_definition: revocation_registry_definition.id.clone(),
         };
 
@@ -392,11 +480,12 @@ impl Issuer {
             r#type: vec!["VerifiableCredential".to_string()],
             issuer: issuer_did.to_owned(),
             issuance_date: issuance_date.unwrap_or_else(get_now_as_iso_string),
+            expiration_date,
             credential_subject,
             credential_schema: schema_reference,
             proof: cred_signature,
         };
-        Ok((credential, revocation_state, new_rev_info))
+        Ok((credential, revocation_state))
     }
 
     /// Creates a new credential offer, as a response to a `CredentialProposal` sent by a prover.
@@ -406,6 +495,7 @@ impl Issuer {
     /// * `subject_did` - DID of the subject
     /// * `schema_did` - DID of the `CredentialSchema` to be offered
     /// * `credential_definition_did` - DID of the `CredentialDefinition` to be offered
+    /// * `validity` - number of seconds the offer can be used for issuance, defaults to `DEFAULT_OFFER_VALIDITY`
     ///
     /// # Returns
     /// * `CredentialOffer` - The message to be sent to the prover.
@@ -414,6 +504,7 @@ impl Issuer {
         subject_did: &str,
         schema_did: &str,
         credential_definition_did: &str,
+        validity: Option<u64>,
     ) -> Result<CredentialOffer, Box<dyn Error>> {
         let nonce = new_nonce().map_err(|e| format!("could not get nonce; {}", &e))?;
 
@@ -424,9 +515,136 @@ impl Issuer {
             schema: schema_did.to_owned(),
             credential_definition: credential_definition_did.to_owned(),
             nonce,
+            created_at: get_now_as_iso_string(),
+            expires_at: get_iso_string_in_seconds(validity.unwrap_or(DEFAULT_OFFER_VALIDITY)),
         })
     }
 
+    /// Accepts a credential request for issuance. Checks the request against the credential offer
+    /// it has been created for and against the schema's required properties, then marks the
+    /// offer's nonce as used, so the offer cannot be used for another credential.
+    /// Has to be called before allocating a revocation ID for the credential, so rejected
+    /// requests neither use up revocation IDs nor get signed.
+    ///
+    /// # Arguments
+    /// * `issuer_did` - DID of the issuer
+    /// * `subject_did` - DID of the subject the credential will be issued for
+    /// * `credential_offer` - Credential offer the credential request has been created for
+    /// * `credential_request` - Credential request object sent by the subject
+    /// * `used_nonces` - Store to mark the nonce of the credential offer as used in
+    /// * `credential_definition` - Credential definition to use for issuance
+    /// * `credential_schema` - Credential schema to use for issuance
+    pub fn accept_credential_request(
+        issuer_did: &str,
+        subject_did: &str,
+        credential_offer: &CredentialOffer,
+        credential_request: &CredentialRequest,
+        used_nonces: &dyn UsedNonceStore,
+        credential_definition: &CredentialDefinition,
+        credential_schema: &CredentialSchema,
+    ) -> Result<(), Box<dyn Error>> {
+        Issuer::check_credential_offer(
+            issuer_did,
+            subject_did,
+            credential_offer,
+            credential_request,
+            credential_definition,
+            credential_schema,
+        )?;
+
+        for required in &credential_schema.required {
+            if credential_schema.properties.contains_key(required)
+                && !credential_request.credential_values.contains_key(required)
+            {
+                let error = format!("Missing required schema property; {}", required);
+                return Err(Box::from(error));
+            }
+        }
+
+        used_nonces.use_nonce(
+            &serde_json::to_string(&credential_offer.nonce)?,
+            &credential_offer.expires_at,
+        )
+    }
+
+    /// Checks that a credential request has been created for a credential offer of this issuer,
+    /// that this offer has not expired yet and that it has not been used for issuance before.
+    /// As the correctness proof of the blinded credential secrets is verified against the nonce
+    /// of the credential request, the nonces of request and offer have to be equal.
+    /// The correctness proof does not cover the subject, so offer, request and the subject the
+    /// credential is issued for have to name the same subject.
+    ///
+    /// # Arguments
+    /// * `issuer_did` - DID of the issuer
+    /// * `subject_did` - DID of the subject the credential will be issued for
+    /// * `credential_offer` - Credential offer the credential request has been created for
+    /// * `credential_request` - Credential request object sent by the subject
+    /// * `credential_definition` - Credential definition to use for issuance
+    /// * `credential_schema` - Credential schema to use for issuance
+    fn check_credential_offer(
+        issuer_did: &str,
+        subject_did: &str,
+        credential_offer: &CredentialOffer,
+        credential_request: &CredentialRequest,
+        credential_definition: &CredentialDefinition,
+        credential_schema: &CredentialSchema,
+    ) -> Result<(), Box<dyn Error>> {
+        if credential_offer.issuer != issuer_did {
+            return Err(Box::from(format!(
+                "credential offer has been created by {}, not by {}",
+                &credential_offer.issuer, issuer_did,
+            )));
+        }
+        if credential_offer.subject != subject_did {
+            return Err(Box::from(format!(
+                "credential offer has been created for subject {}, not for {}",
+                &credential_offer.subject, subject_did,
+            )));
+        }
+        if credential_request.subject != subject_did {
+            return Err(Box::from(format!(
+                "credential request has been created by subject {}, not by {}",
+                &credential_request.subject, subject_did,
+            )));
+        }
+        if credential_offer.credential_definition != credential_definition.id
+            || credential_request.credential_definition != credential_definition.id
+        {
+            return Err(Box::from(format!(
+                "credential offer and request have to be created for credential definition {}",
+                &credential_definition.id,
+            )));
+        }
+        if credential_offer.schema != credential_schema.id
+            || credential_request.schema != credential_schema.id
+        {
+            return Err(Box::from(format!(
+                "credential offer and request have to be created for schema {}",
+                &credential_schema.id,
+            )));
+        }
+
+        let nonce = serde_json::to_string(&credential_offer.nonce)?;
+        if serde_json::to_string(&credential_request.credential_nonce)? != nonce {
+            return Err(Box::from(
+                "credential request has not been created for the given credential offer",
+            ));
+        }
+        if credential_offer.expires_at.is_empty() {
+            return Err(Box::from(
+                "credential offer does not have an expiration date",
+            ));
+        }
+        if is_date_in_past(&credential_offer.expires_at)? {
+            return Err(Box::from(format!(
+                "credential offer has expired at {}",
+                &credential_offer.expires_at,
+            )));
+        }
+
+        Ok(())
+    }
+
     /// Revokes a credential.
     ///
     /// # Arguments
@@ -461,8 +679,10 @@ impl Issuer {
             .duration_since(UNIX_EPOCH)
             .map_err(|_| "Error generating unix timestamp for delta history")?
             .as_secs();
+        let version = revocation_registry_definition.version + 1;
         let delta_history = DeltaHistory {
             created: unix_timestamp,
+            version,
             delta: delta.clone(),
         };
 
@@ -477,7 +697,9 @@ impl Issuer {
                 .to_owned(),
             registry: RevocationRegistry::from(full_delta.clone()),
             registry_delta: full_delta,
+            version,
             delta_history: history_vec,
+            checkpoint: revocation_registry_definition.checkpoint.clone(),
             maximum_credential_count: revocation_registry_definition.maximum_credential_count,
             revocation_public_key: revocation_registry_definition.revocation_public_key.clone(),
             tails,
@@ -497,6 +719,91 @@ impl Issuer {
 
         rev_reg_def.proof = Some(proof);
 
+        Ok(rev_reg_def)
+    }
+    /// Compacts the delta history of a revocation registry definition by merging all but the
+    /// newest `keep_latest` `DeltaHistory` entries into its `DeltaCheckpoint`.
+    /// Provers with a `RevocationState` older than the checkpoint update their witnesses with the
+    /// checkpoint first and with the remaining delta history afterwards.
+    ///
+    /// # Arguments
+    /// * `issuer` - DID of the issuer
+    /// * `revocation_registry_definition` - Revocation registry definition to compact
+    /// * `keep_latest` - Number of newest `DeltaHistory` entries to keep as they are
+    /// * `issuer_public_key_did` - DID of the public key that will be associated with the created signature
+    /// * `issuer_proving_key` - Private key of the issuer used for signing the definition
+    /// * `signer` - `Signer` to sign with
+    ///
+    /// # Returns
+    /// * `RevocationRegistryDefinition` - The compacted revocation registry definition that needs to be stored in the original revocation registry definition's place.
+    pub async fn compact_delta_history(
+        issuer: &str,
+        revocation_registry_definition: &RevocationRegistryDefinition,
+        keep_latest: usize,
+        issuer_public_key_did: &str,
+        issuer_proving_key: &str,
+        signer: &Box<dyn Signer>,
+    ) -> Result<RevocationRegistryDefinition, Box<dyn Error>> {
+        let history = &revocation_registry_definition.delta_history;
+        if history.len() <= keep_latest {
+            return Err(Box::from(format!(
+                "could not compact delta history; history has {} entries, {} are to be kept",
+                history.len(),
+                keep_latest,
+            )));
+        }
+        let (to_compact, to_keep) = history.split_at(history.len() - keep_latest);
+
+        // continue existing checkpoint or start a new one with the oldest entry
+        let (mut checkpoint, to_merge) = match &revocation_registry_definition.checkpoint {
+            Some(checkpoint) => (checkpoint.clone(), to_compact),
+            None => (
+                DeltaCheckpoint {
+                    created: to_compact[0].created,
+                    version: to_compact[0].version,
+                    delta: to_compact[0].delta.clone(),
+                },
+                &to_compact[1..],
+            ),
+        };
+        for entry in to_merge {
+            checkpoint
+                .delta
+                .merge(&entry.delta)
+                .map_err(|e| format!("could not merge delta into checkpoint; {}", &e))?;
+            checkpoint.created = entry.created;
+            checkpoint.version = entry.version;
+        }
+
+        let mut rev_reg_def = RevocationRegistryDefinition {
+            id: revocation_registry_definition.id.to_owned(),
+            credential_definition: revocation_registry_definition
+                .credential_definition
+                .to_owned(),
+            registry: revocation_registry_definition.registry.clone(),
+            registry_delta: revocation_registry_definition.registry_delta.clone(),
+            version: revocation_registry_definition.version,
+            delta_history: to_keep.to_vec(),
+            checkpoint: Some(checkpoint),
+            maximum_credential_count: revocation_registry_definition.maximum_credential_count,
+            revocation_public_key: revocation_registry_definition.revocation_public_key.clone(),
+            tails: revocation_registry_definition.tails.clone(),
+            updated_at: get_now_as_iso_string(),
+            proof: None,
+        };
+
+        let document_to_sign = serde_json::to_value(&rev_reg_def)?;
+        let proof = create_assertion_proof(
+            &document_to_sign,
+            issuer_public_key_did,
+            issuer,
+            issuer_proving_key,
+            &signer,
+        )
+        .await?;
+
+        rev_reg_def.proof = Some(proof);
+
         Ok(rev_reg_def)
     }
 }
@@ -515,14 +822,18 @@ mod tests {
 
     use super::*;
     use crate::{
-        application::datatypes::{CredentialSchema, SchemaProperty},
-        crypto::crypto_utils::check_assertion_proof,
+        application::{
+            datatypes::{CredentialSchema, SchemaProperty},
+            storage::{InMemoryIssuerStorage, InMemoryUsedNonceStore, IssuerStorage},
+        },
+        crypto::crypto_utils::{check_assertion_proof, get_revocation_delta_indices},
     };
+    use serde_json::Value;
     use std::{collections::HashMap, error::Error};
     use utilities::test_data::{
-        accounts::local::{ISSUER_ADDRESS, ISSUER_DID, ISSUER_PRIVATE_KEY},
+        accounts::local::{ISSUER_ADDRESS, ISSUER_DID, ISSUER_PRIVATE_KEY, ISSUER_PUBLIC_KEY_DID},
         did::{EXAMPLE_DID_1, EXAMPLE_DID_DOCUMENT_1},
-        vc_zkp::EXAMPLE_CREDENTIAL_SCHEMA,
+        vc_zkp::{EXAMPLE_CREDENTIAL_SCHEMA, EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID},
     };
     use vade_signer::{LocalSigner, Signer};
 
@@ -617,4 +928,229 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn can_compact_delta_history() -> Result<(), Box<dyn Error>> {
+        let schema: CredentialSchema = serde_json::from_str(&EXAMPLE_CREDENTIAL_SCHEMA).unwrap();
+        let signer: Box<dyn Signer> = Box::new(LocalSigner::new());
+        let (definition, _) = Issuer::create_credential_definition(
+            &EXAMPLE_DID_1,
+            &ISSUER_DID,
+            &schema,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        let (mut rev_reg_def, _, _) = Issuer::create_revocation_registry_definition(
+            &EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID,
+            &definition,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+            42,
+        )
+        .await?;
+        for revocation_id in 1..=3 {
+            rev_reg_def = Issuer::revoke_credential(
+                &ISSUER_DID,
+                &rev_reg_def,
+                revocation_id,
+                &ISSUER_PUBLIC_KEY_DID,
+                &ISSUER_PRIVATE_KEY,
+                &signer,
+            )
+            .await?;
+        }
+        assert_eq!(rev_reg_def.delta_history.len(), 4);
+
+        let compacted = Issuer::compact_delta_history(
+            &ISSUER_DID,
+            &rev_reg_def,
+            1,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+
+        assert_eq!(compacted.delta_history.len(), 1);
+        let checkpoint = compacted.checkpoint.as_ref().ok_or("checkpoint missing")?;
+        let (_, revoked) = get_revocation_delta_indices(&checkpoint.delta)?;
+        assert!(revoked.contains(&1));
+        assert!(revoked.contains(&2));
+        assert!(!revoked.contains(&3));
+        assert_eq!(checkpoint.created, rev_reg_def.delta_history[2].created);
+        assert_eq!(checkpoint.version, 2);
+        assert_eq!(compacted.version, 3);
+        assert_eq!(compacted.delta_history[0].version, 3);
+
+        let serialized = serde_json::to_string(&compacted).unwrap();
+        assert!(match check_assertion_proof(&serialized, ISSUER_ADDRESS) {
+            Ok(()) => true,
+            Err(e) => panic!("assertion check failed with: {}", e),
+        });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rejects_foreign_and_replayed_credential_requests() -> Result<(), Box<dyn Error>> {
+        let schema: CredentialSchema = serde_json::from_str(&EXAMPLE_CREDENTIAL_SCHEMA).unwrap();
+        let signer: Box<dyn Signer> = Box::new(LocalSigner::new());
+        let (definition, credential_private_key) = Issuer::create_credential_definition(
+            &EXAMPLE_DID_1,
+            &ISSUER_DID,
+            &schema,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        let (mut rev_reg_def, revocation_private_key, revocation_info) =
+            Issuer::create_revocation_registry_definition(
+                &EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID,
+                &definition,
+                &ISSUER_PUBLIC_KEY_DID,
+                &ISSUER_PRIVATE_KEY,
+                &signer,
+                42,
+            )
+            .await?;
+        let used_nonces = InMemoryUsedNonceStore::new();
+        let master_secret = Prover::create_master_secret()?;
+        let mut values = HashMap::new();
+        values.insert("test_property_string".to_owned(), "value".to_owned());
+
+        let mut offers = Vec::new();
+        let mut requests = Vec::new();
+        for expires_at in &[None, None, Some("2020-01-01T00:00:00.000Z")] {
+            let mut offer = Issuer::offer_credential(
+                &ISSUER_DID,
+                &EXAMPLE_DID_1,
+                &schema.id,
+                &definition.id,
+                None,
+            )?;
+            if let Some(expires_at) = expires_at {
+                offer.expires_at = expires_at.to_string();
+            }
+            let (request, _) = Prover::request_credential(
+                serde_json::from_str(&serde_json::to_string(&offer)?)?,
+                serde_json::from_str(&serde_json::to_string(&definition)?)?,
+                serde_json::from_str(&serde_json::to_string(&schema)?)?,
+                serde_json::from_str(&serde_json::to_string(&master_secret)?)?,
+                values.clone(),
+            )?;
+            offers.push(offer);
+            requests.push(serde_json::to_string(&request)?);
+        }
+
+        let serialized_definition = serde_json::to_string(&definition)?;
+        let serialized_credential_private_key = serde_json::to_string(&credential_private_key)?;
+        let serialized_schema = serde_json::to_string(&schema)?;
+        let serialized_revocation_private_key = serde_json::to_string(&revocation_private_key)?;
+        let storage = InMemoryIssuerStorage::new();
+        storage.set_revocation_info(&revocation_info)?;
+        let rev_reg_def_id = rev_reg_def.id.clone();
+        let maximum_credential_count = rev_reg_def.maximum_credential_count;
+        // issue like `vc_zkp_issue_credential` does with an issuer storage
+        let mut issue =
+            |subject: &str, offer: &CredentialOffer, request: &str| -> Result<(), Box<dyn Error>> {
+                let request: CredentialRequest = serde_json::from_str(request)?;
+                let definition: CredentialDefinition =
+                    serde_json::from_str(&serialized_definition)?;
+                let schema: CredentialSchema = serde_json::from_str(&serialized_schema)?;
+                Issuer::accept_credential_request(
+                    &ISSUER_DID,
+                    subject,
+                    offer,
+                    &request,
+                    &used_nonces,
+                    &definition,
+                    &schema,
+                )?;
+                let revocation_id =
+                    storage.allocate_revocation_id(&rev_reg_def_id, maximum_credential_count)?;
+                Issuer::issue_credential_with_revocation_id(
+                    &ISSUER_DID,
+                    subject,
+                    request,
+                    definition,
+                    serde_json::from_str(&serialized_credential_private_key)?,
+                    schema,
+                    &mut rev_reg_def,
+                    serde_json::from_str(&serialized_revocation_private_key)?,
+                    revocation_id,
+                    None,
+                    None,
+                )?;
+                Ok(())
+            };
+        let next_unused_id = || -> Result<u32, Box<dyn Error>> {
+            Ok(storage.get_revocation_info(&rev_reg_def_id)?.next_unused_id)
+        };
+
+        // credential for another subject
+        match issue("did:evan:testcore:0x0", &offers[0], &requests[0]) {
+            Ok(_) => return Err(Box::from("issuance should have failed")),
+            Err(e) => assert!(e.to_string().contains("offer has been created for subject")),
+        };
+        // request created by another subject
+        let mut request: Value = serde_json::from_str(&requests[0])?;
+        request["subject"] = Value::from("did:evan:testcore:0x0");
+        match issue(
+            &EXAMPLE_DID_1,
+            &offers[0],
+            &serde_json::to_string(&request)?,
+        ) {
+            Ok(_) => return Err(Box::from("issuance should have failed")),
+            Err(e) => assert!(e
+                .to_string()
+                .contains("request has been created by subject")),
+        };
+        // request created for another offer
+        match issue(&EXAMPLE_DID_1, &offers[1], &requests[0]) {
+            Ok(_) => return Err(Box::from("issuance should have failed")),
+            Err(e) => assert!(e.to_string().contains("has not been created for the given")),
+        };
+        // expired offer
+        match issue(&EXAMPLE_DID_1, &offers[2], &requests[2]) {
+            Ok(_) => return Err(Box::from("issuance should have failed")),
+            Err(e) => assert!(e.to_string().contains("credential offer has expired")),
+        };
+        assert_eq!(next_unused_id()?, revocation_info.next_unused_id);
+        // replayed request
+        issue(&EXAMPLE_DID_1, &offers[0], &requests[0])?;
+        assert_eq!(next_unused_id()?, revocation_info.next_unused_id + 1);
+        match issue(&EXAMPLE_DID_1, &offers[0], &requests[0]) {
+            Ok(_) => return Err(Box::from("issuance should have failed")),
+            Err(e) => assert!(e.to_string().contains("nonce has already been used")),
+        };
+        assert_eq!(next_unused_id()?, revocation_info.next_unused_id + 1);
+
+        // rejected requests neither sign nor touch the registry when issuing without storage
+        let serialized_rev_reg_def = serde_json::to_string(&rev_reg_def)?;
+        for (offer, request) in &[(&offers[2], &requests[2]), (&offers[0], &requests[0])] {
+            let result = Issuer::issue_credential(
+                &ISSUER_DID,
+                &EXAMPLE_DID_1,
+                offer,
+                serde_json::from_str(request)?,
+                &used_nonces,
+                serde_json::from_str(&serialized_definition)?,
+                serde_json::from_str(&serialized_credential_private_key)?,
+                serde_json::from_str(&serialized_schema)?,
+                &mut rev_reg_def,
+                serde_json::from_str(&serialized_revocation_private_key)?,
+                &revocation_info,
+                None,
+                None,
+            );
+            assert!(result.is_err());
+        }
+        assert_eq!(serde_json::to_string(&rev_reg_def)?, serialized_rev_reg_def);
+
+        Ok(())
+    }
 }
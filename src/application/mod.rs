@@ -16,7 +16,14 @@
 
 extern crate ursa;
 
+pub mod anoncreds;
 pub mod datatypes;
+pub mod didcomm;
+pub mod encoding;
+pub mod exchange;
 pub(crate) mod issuer;
 pub mod prover;
+pub mod storage;
 pub(crate) mod verifier;
+pub mod w3c;
+pub mod wallet;
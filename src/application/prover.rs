@@ -23,31 +23,50 @@ use crate::{
         CredentialProposal,
         CredentialRequest,
         CredentialSchema,
+        CredentialSelection,
         CredentialSubProof,
         CredentialSubject,
         DeltaHistory,
         EncodedCredentialValue,
+        HeldCredential,
+        MasterSecretBackup,
+        MasterSecretBackupContent,
         ProofCredential,
         ProofPresentation,
         ProofRequest,
         RevocationRegistryDefinition,
         RevocationState,
+        SubProofRequest,
+        PROOF_VERSION,
     },
-    crypto::{crypto_datatypes::CryptoCredentialDefinition, crypto_prover::Prover as CryptoProver},
-    utils::utils::generate_uuid,
+    crypto::{
+        crypto_datatypes::CryptoCredentialDefinition,
+        crypto_prover::Prover as CryptoProver,
+        crypto_utils::{
+            create_assertion_proof,
+            decrypt_with_passphrase,
+            encrypt_with_passphrase,
+            get_delta_between,
+            get_revocation_delta_indices,
+        },
+    },
+    utils::utils::{generate_uuid, get_now_as_iso_string, get_timestamp_millis, is_date_in_past},
 };
+use serde_json::{json, Value};
 use sha2::{Digest, Sha256};
-use std::{collections::HashMap, convert::TryInto, error::Error};
+use std::{cmp::Reverse, collections::HashMap, convert::TryInto, error::Error};
 use ursa::{
     bn::BigNumber,
     cl::{
         CredentialSecretsBlindingFactors,
         MasterSecret,
+        RevocationRegistryDelta,
         RevocationTailsGenerator,
         SimpleTailsAccessor,
         Witness,
     },
 };
+use vade_signer::Signer;
 
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -55,6 +74,9 @@ use std::time::{SystemTime, UNIX_EPOCH};
 #[cfg(target_arch = "wasm32")]
 use wasm_timer::{SystemTime, UNIX_EPOCH};
 
+const MASTER_SECRET_BACKUP_TYPE: &str = "EvanMasterSecretBackup";
+const MASTER_SECRET_BACKUP_VERSION: u32 = 1;
+
 /// Holds the logic needed to request credentials and create proofs.
 pub struct Prover {}
 
@@ -86,10 +108,12 @@ impl Prover {
     }
 
     /// Request a new credential based on a received credential offering.
+    /// Fails if credential definition and schema do not match the offering or each other.
     ///
     /// # Arguments
     /// * `credential_offering` - The received credential offering sent by the potential issuer
     /// * `credential_definition` - The credential definition that is referenced in the credential offering
+    /// * `credential_schema` - The schema that is referenced in the credential offering
     /// * `master_secret` - The master secret to incorporate into the blinded values to be signed by the issuer
     /// * `credential_values` - A mapping of property names to their stringified cleartext values
     ///
@@ -103,6 +127,11 @@ impl Prover {
         master_secret: MasterSecret,
         credential_values: HashMap<String, String>,
     ) -> Result<(CredentialRequest, CredentialSecretsBlindingFactors), Box<dyn Error>> {
+        Prover::check_offer_matches_definition(
+            &credential_offering,
+            &credential_definition,
+            &credential_schema,
+        )?;
         for required in &credential_schema.required {
             if credential_values.get(required).is_none() {
                 let error = format!("Missing required schema property; {}", required);
@@ -141,6 +170,73 @@ impl Prover {
         ))
     }
 
+    /// Checks that a credential offering has been made in response to a credential proposal, i.e.
+    /// for the proposed schema by the issuer and for the subject of the proposal.
+    ///
+    /// # Arguments
+    /// * `credential_offering` - The received credential offering sent by the potential issuer
+    /// * `credential_proposal` - The proposal previously sent to the issuer
+    pub fn check_offer_matches_proposal(
+        credential_offering: &CredentialOffer,
+        credential_proposal: &CredentialProposal,
+    ) -> Result<(), Box<dyn Error>> {
+        if credential_offering.issuer != credential_proposal.issuer {
+            return Err(Box::from(format!(
+                "credential offering has issuer {}, but {} has been proposed",
+                &credential_offering.issuer, &credential_proposal.issuer,
+            )));
+        }
+        if credential_offering.subject != credential_proposal.subject {
+            return Err(Box::from(format!(
+                "credential offering has subject {}, but {} has been proposed",
+                &credential_offering.subject, &credential_proposal.subject,
+            )));
+        }
+        if credential_offering.schema != credential_proposal.schema {
+            return Err(Box::from(format!(
+                "credential offering has schema {}, but {} has been proposed",
+                &credential_offering.schema, &credential_proposal.schema,
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that credential definition and schema are the ones referenced in a credential offering
+    /// and that the definition has been created by the offering issuer for this schema.
+    fn check_offer_matches_definition(
+        credential_offering: &CredentialOffer,
+        credential_definition: &CredentialDefinition,
+        credential_schema: &CredentialSchema,
+    ) -> Result<(), Box<dyn Error>> {
+        if credential_offering.credential_definition != credential_definition.id {
+            return Err(Box::from(format!(
+                "credential offering references credential definition {}, but {} has been given",
+                &credential_offering.credential_definition, &credential_definition.id,
+            )));
+        }
+        if credential_offering.schema != credential_schema.id {
+            return Err(Box::from(format!(
+                "credential offering references schema {}, but {} has been given",
+                &credential_offering.schema, &credential_schema.id,
+            )));
+        }
+        if credential_definition.schema != credential_schema.id {
+            return Err(Box::from(format!(
+                "credential definition {} belongs to schema {}, not to {}",
+                &credential_definition.id, &credential_definition.schema, &credential_schema.id,
+            )));
+        }
+        if credential_definition.issuer != credential_offering.issuer {
+            return Err(Box::from(format!(
+                "credential definition {} has not been created by offering issuer {}",
+                &credential_definition.id, &credential_offering.issuer,
+            )));
+        }
+
+        Ok(())
+    }
+
     /// Create a `ProofPresentation` to send to a verifier based on a received `ProofRequest`
     ///
     /// # Arguments
@@ -179,9 +275,270 @@ impl Prover {
             r#type: vec!["VerifiablePresentation".to_owned()],
             verifiable_credential: vcs,
             proof: aggregated_proof,
+            holder_binding: None,
+            assertion_proof: None,
         })
     }
 
+    /// Adds a holder binding to a proof presentation. It proves that the presenter controls the
+    /// prover DID of the proof request by signing the presentation's ID and the request's nonce
+    /// with a key of this DID.
+    ///
+    /// # Arguments
+    /// * `presentation` - Presentation to bind to the prover DID
+    /// * `proof_request` - Proof request answered by the presentation
+    /// * `prover_public_key_did` - DID of the prover's public key to validate the binding with
+    /// * `prover_proving_key` - Private key to sign the binding with
+    /// * `signer` - `Signer` to sign with
+    ///
+    /// # Returns
+    /// * `ProofPresentation` - The presentation with its `holder_binding` set
+    pub async fn add_holder_binding(
+        mut presentation: ProofPresentation,
+        proof_request: &ProofRequest,
+        prover_public_key_did: &str,
+        prover_proving_key: &str,
+        signer: &Box<dyn Signer>,
+    ) -> Result<ProofPresentation, Box<dyn Error>> {
+        let document = Prover::get_holder_binding_document(&presentation.id, proof_request)?;
+        presentation.holder_binding = Some(
+            create_assertion_proof(
+                &document,
+                prover_public_key_did,
+                &proof_request.prover,
+                prover_proving_key,
+                signer,
+            )
+            .await?,
+        );
+
+        Ok(presentation)
+    }
+
+    /// Adds an assertion proof over the envelope of a proof presentation. It covers the
+    /// presentation's and its credentials' metadata, which is not covered by the CL proof.
+    /// Has to be added after all other changes to the presentation, e.g. after its holder binding.
+    ///
+    /// # Arguments
+    /// * `presentation` - Presentation to sign
+    /// * `prover_did` - DID of the prover
+    /// * `prover_public_key_did` - DID of the prover's public key to validate the proof with
+    /// * `prover_proving_key` - Private key to sign the presentation with
+    /// * `signer` - `Signer` to sign with
+    ///
+    /// # Returns
+    /// * `ProofPresentation` - The presentation with its `assertion_proof` set
+    pub async fn sign_presentation(
+        mut presentation: ProofPresentation,
+        prover_did: &str,
+        prover_public_key_did: &str,
+        prover_proving_key: &str,
+        signer: &Box<dyn Signer>,
+    ) -> Result<ProofPresentation, Box<dyn Error>> {
+        let document = Prover::get_presentation_envelope_document(&presentation)?;
+        presentation.assertion_proof = Some(
+            create_assertion_proof(
+                &document,
+                prover_public_key_did,
+                prover_did,
+                prover_proving_key,
+                signer,
+            )
+            .await?,
+        );
+
+        Ok(presentation)
+    }
+
+    /// Gets the document signed in the assertion proof of a proof presentation.
+    ///
+    /// # Arguments
+    /// * `presentation` - Presentation to get the document for
+    ///
+    /// # Returns
+    /// * `Value` - The presentation without its assertion proof
+    pub fn get_presentation_envelope_document(
+        presentation: &ProofPresentation,
+    ) -> Result<Value, Box<dyn Error>> {
+        let mut envelope = serde_json::to_value(presentation)?;
+        envelope
+            .as_object_mut()
+            .ok_or("could not get presentation as object")?
+            .remove("assertionProof");
+
+        Ok(json!({ "presentation": envelope }))
+    }
+
+    /// Gets the document signed in the holder binding of a proof presentation.
+    ///
+    /// # Arguments
+    /// * `presentation_id` - ID of the presentation
+    /// * `proof_request` - Proof request answered by the presentation
+    ///
+    /// # Returns
+    /// * `Value` - Document binding the presentation and the request's nonce to the prover DID
+    pub fn get_holder_binding_document(
+        presentation_id: &str,
+        proof_request: &ProofRequest,
+    ) -> Result<Value, Box<dyn Error>> {
+        Ok(json!({
+            "type": "EvanZKPHolderBinding",
+            "presentation": presentation_id,
+            "prover": &proof_request.prover,
+            "verifier": &proof_request.verifier,
+            "nonce": serde_json::to_value(&proof_request.nonce)?,
+        }))
+    }
+
+    /// Selects credentials for all sub proof requests of a `ProofRequest` from a pool of held credentials.
+    /// A credential can be used for a sub proof request if it has the requested schema, matches at least one
+    /// of the request's restrictions (if any), contains all requested attributes, is not expired and has not
+    /// been revoked. If multiple credentials can be used, the most recently issued one is selected.
+    /// Revocation states of selected credentials are updated with the given revocation registry definitions.
+    ///
+    /// # Arguments
+    /// * `proof_request` - The proof request to select credentials for
+    /// * `held_credentials` - Credentials to select from, together with their revocation states
+    /// * `revocation_registries` - Revocation registry definitions of the held credentials, indexed by their DIDs
+    ///
+    /// # Returns
+    /// * `CredentialSelection` - Credentials and updated witnesses to present a proof with, fails with the
+    /// reasons for every sub proof request that cannot be satisfied
+    pub fn select_credentials(
+        proof_request: &ProofRequest,
+        held_credentials: Vec<HeldCredential>,
+        revocation_registries: &HashMap<String, RevocationRegistryDefinition>,
+    ) -> Result<CredentialSelection, Box<dyn Error>> {
+        let mut candidates = held_credentials;
+        candidates.sort_by_cached_key(|held| {
+            Reverse(get_timestamp_millis(&held.credential.issuance_date).ok())
+        });
+
+        let mut selection = CredentialSelection {
+            credentials: HashMap::new(),
+            witnesses: HashMap::new(),
+            revocation_states: HashMap::new(),
+        };
+        let mut failures: Vec<String> = Vec::new();
+        for sub_proof_request in &proof_request.sub_proof_requests {
+            let mut reasons: Vec<String> = Vec::new();
+            let mut selected_index: Option<usize> = None;
+            for (index, held) in candidates.iter().enumerate() {
+                if held.credential.credential_schema.id != sub_proof_request.schema {
+                    continue;
+                }
+                match Prover::get_ineligibility_reason(
+                    sub_proof_request,
+                    &held.credential,
+                    revocation_registries,
+                )? {
+                    Some(reason) => reasons.push(format!("{} {}", &held.credential.id, reason)),
+                    None => {
+                        selected_index = Some(index);
+                        break;
+                    }
+                }
+            }
+
+            let held = match selected_index {
+                Some(index) => candidates.remove(index),
+                None => {
+                    if reasons.is_empty() {
+                        reasons.push("no credential with this schema".to_owned());
+                    }
+                    failures.push(format!(
+                        "{}: {}",
+                        &sub_proof_request.schema,
+                        reasons.join(", ")
+                    ));
+                    continue;
+                }
+            };
+            let rev_reg_def: RevocationRegistryDefinition =
+                serde_json::from_str(&serde_json::to_string(
+                    &revocation_registries[&held.credential.proof.revocation_registry_definition],
+                )?)?;
+            let revocation_state =
+                Prover::update_revocation_state_for_credential(held.revocation_state, rev_reg_def)?;
+            selection.witnesses.insert(
+                held.credential.id.to_owned(),
+                revocation_state.witness.clone(),
+            );
+            selection
+                .revocation_states
+                .insert(held.credential.id.to_owned(), revocation_state);
+            selection
+                .credentials
+                .insert(sub_proof_request.schema.to_owned(), held.credential);
+        }
+
+        if !failures.is_empty() {
+            return Err(Box::from(format!(
+                "could not select credentials for proof request; {}",
+                failures.join("; "),
+            )));
+        }
+
+        Ok(selection)
+    }
+
+    /// Checks if a credential with the requested schema can be used for a sub proof request.
+    /// Note that `expiration_date` is not covered by the credential signature, it only helps the
+    /// holder to skip expired credentials and is not checked when verifying a proof.
+    ///
+    /// # Returns
+    /// * `Option<String>` - Reason why the credential cannot be used, `None` if it can be used
+    fn get_ineligibility_reason(
+        sub_proof_request: &SubProofRequest,
+        credential: &Credential,
+        revocation_registries: &HashMap<String, RevocationRegistryDefinition>,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        if !sub_proof_request
+            .is_satisfied_by(&credential.issuer, &credential.proof.credential_definition)
+        {
+            return Ok(Some("does not match any restriction".to_owned()));
+        }
+
+        let missing: Vec<&String> = sub_proof_request
+            .revealed_attributes
+            .iter()
+            .filter(|attribute| !credential.credential_subject.data.contains_key(*attribute))
+            .collect();
+        if !missing.is_empty() {
+            return Ok(Some(format!("is missing attributes {:?}", missing)));
+        }
+
+        if let Some(expiration_date) = &credential.expiration_date {
+            match is_date_in_past(expiration_date) {
+                Ok(false) => (),
+                Ok(true) => return Ok(Some(format!("has expired at {}", expiration_date))),
+                Err(_) => {
+                    return Ok(Some(format!(
+                        "has invalid expiration date {}",
+                        expiration_date
+                    )))
+                }
+            }
+        }
+
+        let rev_reg_def =
+            match revocation_registries.get(&credential.proof.revocation_registry_definition) {
+                Some(rev_reg_def) => rev_reg_def,
+                None => {
+                    return Ok(Some(format!(
+                        "has unknown revocation registry definition {}",
+                        &credential.proof.revocation_registry_definition,
+                    )))
+                }
+            };
+        let (_, revoked) = get_revocation_delta_indices(&rev_reg_def.registry_delta)?;
+        if revoked.contains(&credential.proof.revocation_id) {
+            return Ok(Some("has been revoked".to_owned()));
+        }
+
+        Ok(None)
+    }
+
     fn create_proof_credentials(
         proof_request: ProofRequest,
         credentials: HashMap<String, Credential>,
@@ -201,8 +558,13 @@ impl Prover {
             &witnesses,
         )?;
 
+        // Ursa does not expose the aggregated proof, so take it from the serialized proof
+        let mut serialized = serde_json::to_value(&crypto_proof)?;
+        let aggregated_proof = serde_json::from_value(serialized["aggregated_proof"].take())?;
+        let mut sub_proofs = crypto_proof.proofs.into_iter();
+
         let mut proof_creds: Vec<ProofCredential> = Vec::new();
-        for (i, sub_request) in proof_request.sub_proof_requests.into_iter().enumerate() {
+        for sub_request in proof_request.sub_proof_requests {
             let credential = credentials
                 .get(&sub_request.schema)
                 .ok_or("Requested credential not provided")?;
@@ -231,13 +593,19 @@ impl Prover {
                     .proof
                     .revocation_registry_definition
                     .to_owned(),
-                proof: serde_json::to_string(&crypto_proof.proofs[i])?,
+                revocation_registry_version: revocation_registries
+                    .get(&sub_request.schema)
+                    .map(|rev_reg_def| rev_reg_def.version),
+                proof_version: PROOF_VERSION,
+                proof: sub_proofs
+                    .next()
+                    .ok_or("Sub proof for requested credential not found")?,
             };
 
             let proof_cred = ProofCredential {
                 context: vec!["https://www.w3.org/2018/credentials/v1".to_owned()],
                 id: credential.id.to_owned(),
-                r#type: vec!["VerifiablePresentation".to_owned()],
+                r#type: vec!["VerifiableCredential".to_owned()],
                 credential_schema: credential.credential_schema.clone(),
                 credential_subject,
                 issuer: credential.issuer.to_owned(),
@@ -250,7 +618,8 @@ impl Prover {
 
         let aggregated = AggregatedProof {
             nonce: proof_request.nonce,
-            aggregated_proof: serde_json::to_value(&crypto_proof)?["aggregated_proof"].to_string(),
+            proof_version: PROOF_VERSION,
+            aggregated_proof,
         };
 
         Ok((proof_creds, aggregated))
@@ -321,7 +690,73 @@ impl Prover {
             .map_err(|err| Box::from(format!("could not create master secret; {}", &err)))
     }
 
+    /// Exports a master secret and optionally held credentials into an encrypted backup, e.g. to
+    /// move them to another device.
+    ///
+    /// # Arguments
+    /// * `master_secret` - The master secret to export
+    /// * `credentials` - Held credentials to include in the backup, may be empty
+    /// * `passphrase` - Passphrase to derive the backup's encryption key from
+    ///
+    /// # Returns
+    /// * `MasterSecretBackup` - The encrypted backup
+    pub fn export_master_secret(
+        master_secret: &MasterSecret,
+        credentials: Vec<HeldCredential>,
+        passphrase: &str,
+    ) -> Result<MasterSecretBackup, Box<dyn Error>> {
+        if passphrase.is_empty() {
+            return Err(Box::from(
+                "passphrase for master secret backup must not be empty",
+            ));
+        }
+        let content = serde_json::to_vec(&MasterSecretBackupContent {
+            master_secret: serde_json::from_str(&serde_json::to_string(master_secret)?)?,
+            credentials,
+        })?;
+
+        Ok(MasterSecretBackup {
+            r#type: MASTER_SECRET_BACKUP_TYPE.to_owned(),
+            version: MASTER_SECRET_BACKUP_VERSION,
+            created_at: get_now_as_iso_string(),
+            encrypted: encrypt_with_passphrase(&content, passphrase)?,
+        })
+    }
+
+    /// Imports a master secret backup created with `export_master_secret`. Its integrity is checked
+    /// by the authenticated encryption of the backup's content.
+    ///
+    /// # Arguments
+    /// * `backup` - The encrypted backup
+    /// * `passphrase` - Passphrase the backup has been exported with
+    ///
+    /// # Returns
+    /// * `MasterSecretBackupContent` - The master secret and credentials contained in the backup
+    pub fn import_master_secret(
+        backup: &MasterSecretBackup,
+        passphrase: &str,
+    ) -> Result<MasterSecretBackupContent, Box<dyn Error>> {
+        if backup.r#type != MASTER_SECRET_BACKUP_TYPE {
+            return Err(Box::from(format!(
+                "invalid master secret backup type {}",
+                &backup.r#type
+            )));
+        }
+        if backup.version != MASTER_SECRET_BACKUP_VERSION {
+            return Err(Box::from(format!(
+                "unsupported master secret backup version {}",
+                backup.version
+            )));
+        }
+
+        let content = decrypt_with_passphrase(&backup.encrypted, passphrase)
+            .map_err(|e| format!("could not decrypt master secret backup; {}", &e))?;
+        Ok(serde_json::from_slice(&content)
+            .map_err(|e| format!("could not parse master secret backup content; {}", &e))?)
+    }
+
     /// Incorporate the prover's master secret into the credential signature after issuance.
+    /// Fails if the credential does not match the credential request it has been issued for.
     ///
     /// # Arguments
     /// * `credential` - The credential to alter
@@ -341,6 +776,13 @@ impl Prover {
         revocation_registry_definition: &RevocationRegistryDefinition,
         witness: &Witness,
     ) -> Result<(), Box<dyn Error>> {
+        Prover::check_credential_matches_request(
+            credential,
+            credential_schema,
+            credential_request,
+            credential_definition,
+            revocation_registry_definition,
+        )?;
         let rev_reg_def: RevocationRegistryDefinition =
             serde_json::from_str(&serde_json::to_string(revocation_registry_definition)?)?;
 
@@ -373,8 +815,86 @@ impl Prover {
         Ok(())
     }
 
+    /// Checks that an issued credential has been issued for the given credential request, i.e. with the
+    /// requested credential definition, schema, subject and values, and a revocation registry definition
+    /// belonging to the same credential definition.
+    fn check_credential_matches_request(
+        credential: &Credential,
+        credential_schema: &CredentialSchema,
+        credential_request: &CredentialRequest,
+        credential_definition: &CredentialDefinition,
+        revocation_registry_definition: &RevocationRegistryDefinition,
+    ) -> Result<(), Box<dyn Error>> {
+        if credential.proof.credential_definition != credential_request.credential_definition
+            || credential_definition.id != credential_request.credential_definition
+        {
+            return Err(Box::from(format!(
+                "credential has credential definition {}, but {} has been requested",
+                &credential.proof.credential_definition, &credential_request.credential_definition,
+            )));
+        }
+        if credential.credential_schema.id != credential_request.schema
+            || credential_schema.id != credential_request.schema
+        {
+            return Err(Box::from(format!(
+                "credential has schema {}, but {} has been requested",
+                &credential.credential_schema.id, &credential_request.schema,
+            )));
+        }
+        if credential.credential_subject.id != credential_request.subject {
+            return Err(Box::from(format!(
+                "credential has been issued for subject {}, but {} has been requested",
+                &credential.credential_subject.id, &credential_request.subject,
+            )));
+        }
+
+        for (name, requested) in &credential_request.credential_values {
+            let value = credential
+                .credential_subject
+                .data
+                .get(name)
+                .ok_or_else(|| format!("credential value {} is missing", name))?;
+            if value.raw != requested.raw || value.encoded != requested.encoded {
+                return Err(Box::from(format!(
+                    "credential value {} differs from requested value",
+                    name
+                )));
+            }
+        }
+        if let Some(name) = credential
+            .credential_subject
+            .data
+            .keys()
+            .find(|name| !credential_request.credential_values.contains_key(*name))
+        {
+            return Err(Box::from(format!(
+                "credential contains value {} that has not been requested",
+                name
+            )));
+        }
+
+        if credential.proof.revocation_registry_definition != revocation_registry_definition.id {
+            return Err(Box::from(format!(
+                "credential has revocation registry definition {}, but {} has been given",
+                &credential.proof.revocation_registry_definition,
+                &revocation_registry_definition.id,
+            )));
+        }
+        if revocation_registry_definition.credential_definition != credential_definition.id {
+            return Err(Box::from(format!(
+                "revocation registry definition {} does not belong to credential definition {}",
+                &revocation_registry_definition.id, &credential_definition.id,
+            )));
+        }
+
+        Ok(())
+    }
+
     /// Updates the revocation state associated with a credential.
-    /// Not used at the moment but will be needed for revocation.
+    /// If the revocation state is older than the `DeltaCheckpoint` of the revocation registry definition,
+    /// the checkpoint is applied before the remaining `DeltaHistory`.
+    /// Deltas are applied by their registry `version`, missing versions are rejected. Revocation states
+    /// without a `version` are updated based on their `updated` timestamp and track the version afterwards.
     ///
     /// # Arguments
     /// * `revocation_state` - Current revocation state (that is to be updated)
@@ -382,38 +902,46 @@ impl Prover {
     ///
     /// # Returns
     /// * `RevocationState` - The updated revocation state
-    #[allow(dead_code)]
     pub fn update_revocation_state_for_credential(
         revocation_state: RevocationState,
         rev_reg_def: RevocationRegistryDefinition,
     ) -> Result<RevocationState, Box<dyn Error>> {
         let mut witness: Witness = revocation_state.witness.clone();
 
-        let mut deltas: Vec<DeltaHistory> = rev_reg_def
-            .delta_history
-            .iter()
-            .cloned()
-            .filter(|entry| entry.created > revocation_state.updated)
-            .collect();
-        deltas.sort_by(|a, b| a.created.cmp(&b.created));
+        let pending_deltas = match revocation_state.version {
+            Some(version) => {
+                Prover::get_pending_deltas_by_version(&revocation_state, version, &rev_reg_def)?
+            }
+            None => Prover::get_pending_deltas_by_timestamp(&revocation_state, &rev_reg_def)?,
+        };
+
         let max_cred = rev_reg_def.maximum_credential_count;
         let mut generator: RevocationTailsGenerator = rev_reg_def.tails;
         let mut big_delta = revocation_state.delta.clone();
-        for delta in deltas {
+        let mut witness_delta: Option<RevocationRegistryDelta> = None;
+        for delta in pending_deltas {
             big_delta
-                .merge(&delta.delta)
+                .merge(&delta)
                 .map_err(|e| format!("could not merge revocation state delta; {}", &e))?;
+            match witness_delta.as_mut() {
+                Some(witness_delta) => witness_delta
+                    .merge(&delta)
+                    .map_err(|e| format!("could not merge witness delta; {}", &e))?,
+                None => witness_delta = Some(delta),
+            };
         }
 
-        witness
-            .update(
-                revocation_state.revocation_id,
-                max_cred,
-                &big_delta,
-                &SimpleTailsAccessor::new(&mut generator)
-                    .map_err(|e| format!("could not create new SimpleTailsAccessor; {}", &e))?,
-            )
-            .map_err(|e| format!("could not update witness; {}", &e))?;
+        if let Some(witness_delta) = witness_delta {
+            witness
+                .update(
+                    revocation_state.revocation_id,
+                    max_cred,
+                    &witness_delta,
+                    &SimpleTailsAccessor::new(&mut generator)
+                        .map_err(|e| format!("could not create new SimpleTailsAccessor; {}", &e))?,
+                )
+                .map_err(|e| format!("could not update witness; {}", &e))?;
+        }
 
         Ok(RevocationState {
             credential_id: revocation_state.credential_id.clone(),
@@ -424,8 +952,91 @@ impl Prover {
                 .duration_since(UNIX_EPOCH)
                 .map_err(|_| "Error generating unix timestamp for delta history")?
                 .as_secs(),
+            version: Some(rev_reg_def.version),
         })
     }
+
+    /// Collects the deltas a versioned revocation state is missing, checkpoint first.
+    /// Fails if the versions of the deltas do not continue the state's version without gaps
+    /// or do not reach the current version of the revocation registry definition.
+    fn get_pending_deltas_by_version(
+        revocation_state: &RevocationState,
+        version: u64,
+        rev_reg_def: &RevocationRegistryDefinition,
+    ) -> Result<Vec<RevocationRegistryDelta>, Box<dyn Error>> {
+        if version > rev_reg_def.version {
+            return Err(Box::from(format!(
+                "revocation state version {} is newer than revocation registry version {}",
+                version, rev_reg_def.version,
+            )));
+        }
+
+        let mut last_applied = version;
+        let mut pending_deltas: Vec<RevocationRegistryDelta> = Vec::new();
+        if let Some(checkpoint) = &rev_reg_def.checkpoint {
+            if checkpoint.version > last_applied {
+                pending_deltas.push(get_delta_between(
+                    &revocation_state.delta,
+                    &checkpoint.delta,
+                )?);
+                last_applied = checkpoint.version;
+            }
+        }
+
+        let mut deltas: Vec<&DeltaHistory> = rev_reg_def
+            .delta_history
+            .iter()
+            .filter(|entry| entry.version > last_applied)
+            .collect();
+        deltas.sort_by(|a, b| a.version.cmp(&b.version));
+        for entry in deltas {
+            if entry.version != last_applied + 1 {
+                return Err(Box::from(format!(
+                    "could not update revocation state; delta history is missing version {}",
+                    last_applied + 1,
+                )));
+            }
+            pending_deltas.push(entry.delta.clone());
+            last_applied = entry.version;
+        }
+
+        if last_applied != rev_reg_def.version {
+            return Err(Box::from(format!(
+                "could not update revocation state; deltas end at version {}, registry is at {}",
+                last_applied, rev_reg_def.version,
+            )));
+        }
+
+        Ok(pending_deltas)
+    }
+
+    /// Collects the deltas a revocation state without version is missing, based on its `updated` timestamp.
+    fn get_pending_deltas_by_timestamp(
+        revocation_state: &RevocationState,
+        rev_reg_def: &RevocationRegistryDefinition,
+    ) -> Result<Vec<RevocationRegistryDelta>, Box<dyn Error>> {
+        let mut last_update = revocation_state.updated;
+        let mut pending_deltas: Vec<RevocationRegistryDelta> = Vec::new();
+        if let Some(checkpoint) = &rev_reg_def.checkpoint {
+            if checkpoint.created > last_update {
+                pending_deltas.push(get_delta_between(
+                    &revocation_state.delta,
+                    &checkpoint.delta,
+                )?);
+                last_update = checkpoint.created;
+            }
+        }
+
+        let mut deltas: Vec<&DeltaHistory> = rev_reg_def
+            .delta_history
+            .iter()
+            .filter(|entry| entry.created > last_update)
+            .collect();
+        deltas.sort_by(|a, b| a.created.cmp(&b.created));
+        pending_deltas.extend(deltas.into_iter().map(|entry| entry.delta.clone()));
+
+        Ok(pending_deltas)
+    }
 }
 
 impl Default for Prover {
@@ -439,7 +1050,19 @@ mod tests {
     extern crate utilities;
 
     use super::*;
+    use crate::application::{
+        datatypes::CredentialRestriction,
+        issuer::Issuer,
+        storage::InMemoryUsedNonceStore,
+        verifier::Verifier,
+    };
     use std::{collections::HashMap, error::Error};
+    use utilities::test_data::{
+        accounts::local::{ISSUER_ADDRESS, ISSUER_DID, ISSUER_PRIVATE_KEY, ISSUER_PUBLIC_KEY_DID},
+        did::EXAMPLE_DID_1,
+        vc_zkp::{EXAMPLE_CREDENTIAL_SCHEMA, EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID},
+    };
+    use vade_signer::{LocalSigner, Signer};
 
     #[test]
     fn encodes_values_correctly() -> Result<(), Box<dyn Error>> {
@@ -499,4 +1122,652 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn can_update_revocation_state_with_checkpoint() -> Result<(), Box<dyn Error>> {
+        let schema: CredentialSchema = serde_json::from_str(&EXAMPLE_CREDENTIAL_SCHEMA)?;
+        let signer: Box<dyn Signer> = Box::new(LocalSigner::new());
+        let (definition, _) = Issuer::create_credential_definition(
+            &EXAMPLE_DID_1,
+            &ISSUER_DID,
+            &schema,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        let (mut rev_reg_def, _, _) = Issuer::create_revocation_registry_definition(
+            &EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID,
+            &definition,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+            42,
+        )
+        .await?;
+
+        // holder of credential with revocation ID 5, state created together with registry
+        let revocation_id = 5;
+        let mut tails = rev_reg_def.tails.clone();
+        let revocation_state = RevocationState {
+            credential_id: "test".to_string(),
+            revocation_id,
+            updated: rev_reg_def.delta_history[0].created,
+            version: Some(rev_reg_def.version),
+            delta: serde_json::from_str(&serde_json::to_string(&rev_reg_def.registry)?)?,
+            witness: Witness::new(
+                revocation_id,
+                rev_reg_def.maximum_credential_count,
+                true,
+                &rev_reg_def.registry_delta,
+                &SimpleTailsAccessor::new(&mut tails)
+                    .map_err(|e| format!("could not create SimpleTailsAccessor; {}", &e))?,
+            )
+            .map_err(|e| format!("could not create witness; {}", &e))?,
+        };
+
+        // revoke other credentials and compact all of their history entries
+        for other_revocation_id in 1..=2 {
+            rev_reg_def = Issuer::revoke_credential(
+                &ISSUER_DID,
+                &rev_reg_def,
+                other_revocation_id,
+                &ISSUER_PUBLIC_KEY_DID,
+                &ISSUER_PRIVATE_KEY,
+                &signer,
+            )
+            .await?;
+        }
+        let rev_reg_def = Issuer::compact_delta_history(
+            &ISSUER_DID,
+            &rev_reg_def,
+            0,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        assert_eq!(rev_reg_def.delta_history.len(), 0);
+
+        // witness updated from checkpoint has to match a witness created for the current registry
+        let mut tails = rev_reg_def.tails.clone();
+        let expected_witness = Witness::new(
+            revocation_id,
+            rev_reg_def.maximum_credential_count,
+            true,
+            &rev_reg_def.registry_delta,
+            &SimpleTailsAccessor::new(&mut tails)
+                .map_err(|e| format!("could not create SimpleTailsAccessor; {}", &e))?,
+        )
+        .map_err(|e| format!("could not create witness; {}", &e))?;
+        let updated_state =
+            Prover::update_revocation_state_for_credential(revocation_state, rev_reg_def)?;
+        assert_eq!(
+            serde_json::to_string(&updated_state.witness)?,
+            serde_json::to_string(&expected_witness)?,
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rejects_revocation_state_update_with_missing_versions() -> Result<(), Box<dyn Error>> {
+        let schema: CredentialSchema = serde_json::from_str(&EXAMPLE_CREDENTIAL_SCHEMA)?;
+        let signer: Box<dyn Signer> = Box::new(LocalSigner::new());
+        let (definition, _) = Issuer::create_credential_definition(
+            &EXAMPLE_DID_1,
+            &ISSUER_DID,
+            &schema,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        let (mut rev_reg_def, _, _) = Issuer::create_revocation_registry_definition(
+            &EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID,
+            &definition,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+            42,
+        )
+        .await?;
+
+        let revocation_id = 5;
+        let mut tails = rev_reg_def.tails.clone();
+        let revocation_state = RevocationState {
+            credential_id: "test".to_string(),
+            revocation_id,
+            updated: rev_reg_def.delta_history[0].created,
+            version: Some(rev_reg_def.version),
+            delta: serde_json::from_str(&serde_json::to_string(&rev_reg_def.registry)?)?,
+            witness: Witness::new(
+                revocation_id,
+                rev_reg_def.maximum_credential_count,
+                true,
+                &rev_reg_def.registry_delta,
+                &SimpleTailsAccessor::new(&mut tails)
+                    .map_err(|e| format!("could not create SimpleTailsAccessor; {}", &e))?,
+            )
+            .map_err(|e| format!("could not create witness; {}", &e))?,
+        };
+
+        // revocations within the same second must not be skipped
+        for other_revocation_id in 1..=2 {
+            rev_reg_def = Issuer::revoke_credential(
+                &ISSUER_DID,
+                &rev_reg_def,
+                other_revocation_id,
+                &ISSUER_PUBLIC_KEY_DID,
+                &ISSUER_PRIVATE_KEY,
+                &signer,
+            )
+            .await?;
+        }
+        assert_eq!(rev_reg_def.version, 2);
+        assert_eq!(rev_reg_def.delta_history[1].version, 1);
+        assert_eq!(rev_reg_def.delta_history[2].version, 2);
+
+        // drop delta for version 1
+        rev_reg_def.delta_history.remove(1);
+        match Prover::update_revocation_state_for_credential(revocation_state, rev_reg_def) {
+            Ok(_) => panic!("revocation state update with missing version should fail"),
+            Err(e) => assert!(e.to_string().contains("missing version 1")),
+        };
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_select_credentials_for_proof_request() -> Result<(), Box<dyn Error>> {
+        let schema: CredentialSchema = serde_json::from_str(&EXAMPLE_CREDENTIAL_SCHEMA)?;
+        let signer: Box<dyn Signer> = Box::new(LocalSigner::new());
+        let (definition, credential_private_key) = Issuer::create_credential_definition(
+            &EXAMPLE_DID_1,
+            &ISSUER_DID,
+            &schema,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        let (mut rev_reg_def, revocation_private_key, mut revocation_info) =
+            Issuer::create_revocation_registry_definition(
+                &EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID,
+                &definition,
+                &ISSUER_PUBLIC_KEY_DID,
+                &ISSUER_PRIVATE_KEY,
+                &signer,
+                42,
+            )
+            .await?;
+
+        // issue a revoked, an expired, an active and a newer one with invalid expiration date
+        let master_secret = Prover::create_master_secret()?;
+        let used_nonces = InMemoryUsedNonceStore::new();
+        let mut held_credentials = Vec::new();
+        for expiration_date in &[None, Some("2020-01-01T01:00:00+01:00"), None, None] {
+            let offer = Issuer::offer_credential(
+                &ISSUER_DID,
+                &EXAMPLE_DID_1,
+                &schema.id,
+                &definition.id,
+                None,
+            )?;
+            let mut values = HashMap::new();
+            values.insert("test_property_string".to_owned(), "value".to_owned());
+            let (request, _) = Prover::request_credential(
+                serde_json::from_str(&serde_json::to_string(&offer)?)?,
+                serde_json::from_str(&serde_json::to_string(&definition)?)?,
+                serde_json::from_str(&serde_json::to_string(&schema)?)?,
+                serde_json::from_str(&serde_json::to_string(&master_secret)?)?,
+                values,
+            )?;
+            let (credential, revocation_state, next_revocation_info) = Issuer::issue_credential(
+                &ISSUER_DID,
+                &EXAMPLE_DID_1,
+                &offer,
+                request,
+                &used_nonces,
+                serde_json::from_str(&serde_json::to_string(&definition)?)?,
+                serde_json::from_str(&serde_json::to_string(&credential_private_key)?)?,
+                serde_json::from_str(&serde_json::to_string(&schema)?)?,
+                &mut rev_reg_def,
+                serde_json::from_str(&serde_json::to_string(&revocation_private_key)?)?,
+                &revocation_info,
+                None,
+                expiration_date.map(|date| date.to_owned()),
+            )?;
+            revocation_info = next_revocation_info;
+            held_credentials.push(HeldCredential {
+                credential,
+                revocation_state,
+            });
+        }
+        rev_reg_def = Issuer::revoke_credential(
+            &ISSUER_DID,
+            &rev_reg_def,
+            held_credentials[0].credential.proof.revocation_id,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        let active_id = held_credentials[2].credential.id.to_owned();
+        // expiration date is not covered by the signature, so it can be set afterwards, the
+        // newest credential is checked first, also if issued in the same millisecond
+        let mut invalid = held_credentials.remove(3);
+        invalid.credential.expiration_date = Some("tomorrow".to_owned());
+        held_credentials.insert(0, invalid);
+        let mut revocation_registries = HashMap::new();
+        revocation_registries.insert(rev_reg_def.id.to_owned(), rev_reg_def);
+
+        let sub_proof_request = SubProofRequest {
+            schema: schema.id.to_owned(),
+            revealed_attributes: vec!["test_property_string".to_owned()],
+            restrictions: vec![CredentialRestriction {
+                issuer: Some(ISSUER_DID.to_owned()),
+                credential_definition: None,
+            }],
+        };
+        let proof_request = Verifier::request_proof(
+            &ISSUER_DID,
+            &EXAMPLE_DID_1,
+            vec![sub_proof_request],
+            None,
+            None,
+        )?;
+        let mut selection =
+            Prover::select_credentials(&proof_request, held_credentials, &revocation_registries)?;
+        assert_eq!(
+            selection
+                .credentials
+                .get(&schema.id)
+                .ok_or("credential not selected")?
+                .id,
+            active_id,
+        );
+
+        // reasons are reported if no credential can be used
+        let sub_proof_request = SubProofRequest {
+            schema: schema.id.to_owned(),
+            revealed_attributes: vec!["unknown_property".to_owned()],
+            restrictions: Vec::new(),
+        };
+        let proof_request = Verifier::request_proof(
+            &ISSUER_DID,
+            &EXAMPLE_DID_1,
+            vec![sub_proof_request],
+            None,
+            None,
+        )?;
+        let held_credentials = vec![HeldCredential {
+            credential: selection
+                .credentials
+                .remove(&schema.id)
+                .ok_or("credential not selected")?,
+            revocation_state: selection
+                .revocation_states
+                .remove(&active_id)
+                .ok_or("revocation state not selected")?,
+        }];
+        match Prover::select_credentials(&proof_request, held_credentials, &revocation_registries) {
+            Ok(_) => return Err(Box::from("selection should have failed")),
+            Err(e) => assert!(e.to_string().contains("is missing attributes")),
+        };
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_export_and_import_master_secret() -> Result<(), Box<dyn Error>> {
+        let master_secret = Prover::create_master_secret()?;
+        let backup = Prover::export_master_secret(&master_secret, Vec::new(), "passphrase")?;
+        assert_eq!(backup.version, MASTER_SECRET_BACKUP_VERSION);
+
+        let imported = Prover::import_master_secret(&backup, "passphrase")?;
+        assert_eq!(
+            serde_json::to_string(&imported.master_secret)?,
+            serde_json::to_string(&master_secret)?,
+        );
+        assert_eq!(imported.credentials.len(), 0);
+
+        assert!(Prover::import_master_secret(&backup, "wrong passphrase").is_err());
+        let mut tampered = backup;
+        tampered.encrypted.ciphertext =
+            encrypt_with_passphrase(b"other content", "passphrase")?.ciphertext;
+        match Prover::import_master_secret(&tampered, "passphrase") {
+            Ok(_) => return Err(Box::from("import should have failed")),
+            Err(e) => assert!(e
+                .to_string()
+                .contains("could not decrypt master secret backup")),
+        };
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rejects_credential_not_matching_request() -> Result<(), Box<dyn Error>> {
+        let schema: CredentialSchema = serde_json::from_str(&EXAMPLE_CREDENTIAL_SCHEMA)?;
+        let signer: Box<dyn Signer> = Box::new(LocalSigner::new());
+        let (definition, credential_private_key) = Issuer::create_credential_definition(
+            &EXAMPLE_DID_1,
+            &ISSUER_DID,
+            &schema,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        let (mut rev_reg_def, revocation_private_key, revocation_info) =
+            Issuer::create_revocation_registry_definition(
+                &EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID,
+                &definition,
+                &ISSUER_PUBLIC_KEY_DID,
+                &ISSUER_PRIVATE_KEY,
+                &signer,
+                42,
+            )
+            .await?;
+
+        let master_secret = Prover::create_master_secret()?;
+        let used_nonces = InMemoryUsedNonceStore::new();
+        let offer = Issuer::offer_credential(
+            &ISSUER_DID,
+            &EXAMPLE_DID_1,
+            &schema.id,
+            &definition.id,
+            None,
+        )?;
+        let mut values = HashMap::new();
+        values.insert("test_property_string".to_owned(), "value".to_owned());
+        let (request, blinding_factors) = Prover::request_credential(
+            serde_json::from_str(&serde_json::to_string(&offer)?)?,
+            serde_json::from_str(&serde_json::to_string(&definition)?)?,
+            serde_json::from_str(&serde_json::to_string(&schema)?)?,
+            serde_json::from_str(&serde_json::to_string(&master_secret)?)?,
+            values,
+        )?;
+        let (mut credential, revocation_state, _) = Issuer::issue_credential(
+            &ISSUER_DID,
+            &EXAMPLE_DID_1,
+            &offer,
+            serde_json::from_str(&serde_json::to_string(&request)?)?,
+            &used_nonces,
+            serde_json::from_str(&serde_json::to_string(&definition)?)?,
+            credential_private_key,
+            serde_json::from_str(&serde_json::to_string(&schema)?)?,
+            &mut rev_reg_def,
+            revocation_private_key,
+            &revocation_info,
+            None,
+            None,
+        )?;
+
+        // credential issued for another subject
+        credential.credential_subject.id = "did:evan:testcore:0x0".to_owned();
+        match Prover::post_process_credential_signature(
+            &mut credential,
+            &schema,
+            &request,
+            &definition,
+            blinding_factors,
+            &master_secret,
+            &rev_reg_def,
+            &revocation_state.witness,
+        ) {
+            Ok(_) => return Err(Box::from("processing should have failed")),
+            Err(e) => assert!(e.to_string().contains("has been issued for subject")),
+        };
+
+        // credential with altered value
+        credential.credential_subject.id = EXAMPLE_DID_1.to_owned();
+        credential
+            .credential_subject
+            .data
+            .get_mut("test_property_string")
+            .ok_or("value missing")?
+            .raw = "other value".to_owned();
+        let result = Prover::check_credential_matches_request(
+            &credential,
+            &schema,
+            &request,
+            &definition,
+            &rev_reg_def,
+        );
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_bind_and_sign_presentation() -> Result<(), Box<dyn Error>> {
+        let schema: CredentialSchema = serde_json::from_str(&EXAMPLE_CREDENTIAL_SCHEMA)?;
+        let signer: Box<dyn Signer> = Box::new(LocalSigner::new());
+        let (definition, credential_private_key) = Issuer::create_credential_definition(
+            &EXAMPLE_DID_1,
+            &ISSUER_DID,
+            &schema,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        let (mut rev_reg_def, revocation_private_key, revocation_info) =
+            Issuer::create_revocation_registry_definition(
+                &EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID,
+                &definition,
+                &ISSUER_PUBLIC_KEY_DID,
+                &ISSUER_PRIVATE_KEY,
+                &signer,
+                42,
+            )
+            .await?;
+
+        let master_secret = Prover::create_master_secret()?;
+        let used_nonces = InMemoryUsedNonceStore::new();
+        let offer = Issuer::offer_credential(
+            &ISSUER_DID,
+            &EXAMPLE_DID_1,
+            &schema.id,
+            &definition.id,
+            None,
+        )?;
+        let mut values = HashMap::new();
+        values.insert("test_property_string".to_owned(), "value".to_owned());
+        let (request, blinding_factors) = Prover::request_credential(
+            serde_json::from_str(&serde_json::to_string(&offer)?)?,
+            serde_json::from_str(&serde_json::to_string(&definition)?)?,
+            serde_json::from_str(&serde_json::to_string(&schema)?)?,
+            serde_json::from_str(&serde_json::to_string(&master_secret)?)?,
+            values,
+        )?;
+        let (mut credential, revocation_state, _) = Issuer::issue_credential(
+            &ISSUER_DID,
+            &EXAMPLE_DID_1,
+            &offer,
+            serde_json::from_str(&serde_json::to_string(&request)?)?,
+            &used_nonces,
+            serde_json::from_str(&serde_json::to_string(&definition)?)?,
+            credential_private_key,
+            serde_json::from_str(&serde_json::to_string(&schema)?)?,
+            &mut rev_reg_def,
+            revocation_private_key,
+            &revocation_info,
+            None,
+            None,
+        )?;
+        Prover::post_process_credential_signature(
+            &mut credential,
+            &schema,
+            &request,
+            &definition,
+            blinding_factors,
+            &master_secret,
+            &rev_reg_def,
+            &revocation_state.witness,
+        )?;
+
+        let sub_proof_request = SubProofRequest {
+            schema: schema.id.to_owned(),
+            revealed_attributes: vec!["test_property_string".to_owned()],
+            restrictions: Vec::new(),
+        };
+        let proof_request = Verifier::request_proof(
+            &ISSUER_DID,
+            &EXAMPLE_DID_1,
+            vec![sub_proof_request],
+            None,
+            None,
+        )?;
+        let mut witnesses = HashMap::new();
+        witnesses.insert(credential.id.to_owned(), revocation_state.witness);
+        let mut credentials = HashMap::new();
+        credentials.insert(schema.id.to_owned(), credential);
+        let mut definitions = HashMap::new();
+        definitions.insert(schema.id.to_owned(), definition);
+        let mut revocation_registries = HashMap::new();
+        revocation_registries.insert(schema.id.to_owned(), rev_reg_def);
+        let mut schemas = HashMap::new();
+        schemas.insert(schema.id.to_owned(), schema);
+        let presentation = Prover::present_proof(
+            serde_json::from_str(&serde_json::to_string(&proof_request)?)?,
+            credentials,
+            definitions,
+            schemas,
+            revocation_registries,
+            witnesses,
+            &master_secret,
+        )?;
+        let did_document = json!({
+            "id": EXAMPLE_DID_1,
+            "publicKey": [{ "id": ISSUER_PUBLIC_KEY_DID, "ethereumAddress": ISSUER_ADDRESS }],
+        });
+        assert!(
+            Verifier::check_holder_binding(&presentation, &proof_request, &did_document).is_err()
+        );
+
+        let presentation = Prover::add_holder_binding(
+            presentation,
+            &proof_request,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        Verifier::check_holder_binding(&presentation, &proof_request, &did_document)?;
+
+        // binding created for another proof request
+        let other_request =
+            Verifier::request_proof(&ISSUER_DID, &EXAMPLE_DID_1, Vec::new(), None, None)?;
+        match Verifier::check_holder_binding(&presentation, &other_request, &did_document) {
+            Ok(_) => return Err(Box::from("check should have failed")),
+            Err(e) => assert!(e.to_string().contains("invalid holder binding")),
+        };
+
+        // key not controlled by prover
+        let other_document = json!({ "id": EXAMPLE_DID_1, "publicKey": [] });
+        match Verifier::check_holder_binding(&presentation, &proof_request, &other_document) {
+            Ok(_) => return Err(Box::from("check should have failed")),
+            Err(e) => assert!(e.to_string().contains("could not find ethereum address")),
+        };
+
+        // malformed signatures are rejected without panicking
+        for (jws, reason) in &[
+            ("", "expected 3 segments but got 1"),
+            ("e30.e30", "expected 3 segments but got 2"),
+            ("e30.e30.AAAA.e30", "expected 3 segments but got 4"),
+            ("e30.e30.AAAA", "invalid jws signature length 3"),
+        ] {
+            let mut malformed = serde_json::to_value(&presentation)?;
+            malformed["holderBinding"]["jws"] = Value::from(*jws);
+            match Verifier::check_holder_binding(
+                &serde_json::from_value(malformed)?,
+                &proof_request,
+                &did_document,
+            ) {
+                Ok(_) => return Err(Box::from("check should have failed")),
+                Err(e) => assert!(e.to_string().contains(reason)),
+            };
+        }
+
+        let mut presentation = Prover::sign_presentation(
+            presentation,
+            &EXAMPLE_DID_1,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        Verifier::check_presentation_envelope(&presentation, &proof_request, &did_document)?;
+        Verifier::check_holder_binding(&presentation, &proof_request, &did_document)?;
+
+        // malformed envelope signature
+        let mut malformed = serde_json::to_value(&presentation)?;
+        malformed["assertionProof"]["jws"] = Value::from("e30.e30");
+        match Verifier::check_presentation_envelope(
+            &serde_json::from_value(malformed)?,
+            &proof_request,
+            &did_document,
+        ) {
+            Ok(_) => return Err(Box::from("check should have failed")),
+            Err(e) => assert!(e.to_string().contains("expected 3 segments but got 2")),
+        };
+
+        // envelope altered after signing
+        presentation.verifiable_credential[0].issuance_date = "2112-12-12T12:12:12.000Z".to_owned();
+        match Verifier::check_presentation_envelope(&presentation, &proof_request, &did_document) {
+            Ok(_) => return Err(Box::from("check should have failed")),
+            Err(e) => assert!(e
+                .to_string()
+                .contains("invalid presentation assertion proof")),
+        };
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rejects_offer_not_matching_definition_or_proposal() -> Result<(), Box<dyn Error>> {
+        let schema: CredentialSchema = serde_json::from_str(&EXAMPLE_CREDENTIAL_SCHEMA)?;
+        let signer: Box<dyn Signer> = Box::new(LocalSigner::new());
+        let (definition, _) = Issuer::create_credential_definition(
+            &EXAMPLE_DID_1,
+            &ISSUER_DID,
+            &schema,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        let proposal = Prover::propose_credential(&ISSUER_DID, &EXAMPLE_DID_1, &schema.id);
+        let mut offer = Issuer::offer_credential(
+            &ISSUER_DID,
+            &EXAMPLE_DID_1,
+            &schema.id,
+            &definition.id,
+            None,
+        )?;
+        Prover::check_offer_matches_proposal(&offer, &proposal)?;
+        Prover::check_offer_matches_definition(&offer, &definition, &schema)?;
+
+        // offer for another schema
+        offer.schema = "did:evan:zkp:0x0".to_owned();
+        assert!(Prover::check_offer_matches_proposal(&offer, &proposal).is_err());
+        let mut values = HashMap::new();
+        values.insert("test_property_string".to_owned(), "value".to_owned());
+        match Prover::request_credential(
+            offer,
+            definition,
+            schema,
+            Prover::create_master_secret()?,
+            values,
+        ) {
+            Ok(_) => return Err(Box::from("request should have failed")),
+            Err(e) => assert!(e
+                .to_string()
+                .contains("credential offering references schema")),
+        };
+
+        Ok(())
+    }
 }
@@ -0,0 +1,689 @@
+/*
+  Copyright (c) 2018-present evan GmbH.
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::crypto::{crypto_datatypes::EncryptedData, crypto_utils::PassphraseKey};
+use crate::{
+    application::datatypes::{
+        CredentialOffer,
+        CredentialPrivateKey,
+        RevocationIdInformation,
+        RevocationKeyPrivate,
+    },
+    utils::utils::{get_timestamp_millis, is_date_in_past},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, error::Error, sync::Mutex};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+use ursa::cl::Nonce;
+
+/// Prefix of the IDs credential offers are stored under in an `IssuerStorage`
+const CREDENTIAL_OFFER_ID_PREFIX: &str = "credential-offer:";
+
+/// Persistence for the private state of an issuer, i.e. private keys of credential definitions
+/// and revocation registry definitions, the revocation ID counters of the latter and credential
+/// offers, that have not been used for issuance yet.
+/// Secrets are stored by ID, which defaults to the DID of the definition they belong to.
+pub trait IssuerStorage {
+    /// Stores a serialized secret under the given ID, replacing existing ones
+    fn set_secret(&self, id: &str, secret: &str) -> Result<(), Box<dyn Error>>;
+
+    /// Gets the serialized secret stored under the given ID
+    fn get_secret(&self, id: &str) -> Result<String, Box<dyn Error>>;
+
+    /// Removes the secret stored under the given ID, does nothing if no secret is stored under it
+    fn remove_secret(&self, id: &str) -> Result<(), Box<dyn Error>>;
+
+    /// Gets the IDs of all stored secrets starting with the given prefix
+    fn get_secret_ids(&self, prefix: &str) -> Result<Vec<String>, Box<dyn Error>>;
+
+    /// Stores revocation ID information of a revocation registry definition, replacing existing ones
+    fn set_revocation_info(&self, info: &RevocationIdInformation) -> Result<(), Box<dyn Error>>;
+
+    /// Gets the revocation ID information of a revocation registry definition
+    fn get_revocation_info(
+        &self,
+        definition_id: &str,
+    ) -> Result<RevocationIdInformation, Box<dyn Error>>;
+
+    /// Marks the next unused revocation ID of a revocation registry definition as used and returns it.
+    /// Fails without using an ID if all `maximum_credential_count` IDs have been used.
+    /// Implementations have to make sure that no ID is returned twice, even for concurrent callers.
+    fn allocate_revocation_id(
+        &self,
+        definition_id: &str,
+        maximum_credential_count: u32,
+    ) -> Result<u32, Box<dyn Error>>;
+
+    /// Stores the private key of a credential definition
+    fn set_credential_private_key(
+        &self,
+        id: &str,
+        key: &CredentialPrivateKey,
+    ) -> Result<(), Box<dyn Error>> {
+        self.set_secret(id, &serde_json::to_string(key)?)
+    }
+
+    /// Gets the private key of a credential definition
+    fn get_credential_private_key(&self, id: &str) -> Result<CredentialPrivateKey, Box<dyn Error>> {
+        Ok(serde_json::from_str(&self.get_secret(id)?)?)
+    }
+
+    /// Stores the private key of a revocation registry definition
+    fn set_revocation_private_key(
+        &self,
+        id: &str,
+        key: &RevocationKeyPrivate,
+    ) -> Result<(), Box<dyn Error>> {
+        self.set_secret(id, &serde_json::to_string(key)?)
+    }
+
+    /// Gets the private key of a revocation registry definition
+    fn get_revocation_private_key(&self, id: &str) -> Result<RevocationKeyPrivate, Box<dyn Error>> {
+        Ok(serde_json::from_str(&self.get_secret(id)?)?)
+    }
+
+    /// Stores a credential offer created by the issuer, so it can be looked up by its nonce on issuance.
+    /// Expired offers are removed before storing the new one.
+    fn set_credential_offer(&self, offer: &CredentialOffer) -> Result<(), Box<dyn Error>> {
+        self.remove_expired_credential_offers()?;
+        self.set_secret(
+            &get_credential_offer_id(&offer.nonce)?,
+            &serde_json::to_string(offer)?,
+        )
+    }
+
+    /// Gets the credential offer with the given nonce
+    fn get_credential_offer(&self, nonce: &Nonce) -> Result<CredentialOffer, Box<dyn Error>> {
+        let offer = self
+            .get_secret(&get_credential_offer_id(nonce)?)
+            .map_err(|_| "no credential offer stored for nonce of credential request")?;
+        Ok(serde_json::from_str(&offer)?)
+    }
+
+    /// Removes the credential offer with the given nonce, e.g. after it has been used for issuance
+    fn remove_credential_offer(&self, nonce: &Nonce) -> Result<(), Box<dyn Error>> {
+        self.remove_secret(&get_credential_offer_id(nonce)?)
+    }
+
+    /// Removes all credential offers, that have expired and cannot be used for issuance anymore
+    fn remove_expired_credential_offers(&self) -> Result<(), Box<dyn Error>> {
+        for id in self.get_secret_ids(CREDENTIAL_OFFER_ID_PREFIX)? {
+            // offer may have been removed by a concurrent caller in the meantime
+            let offer: CredentialOffer = match self.get_secret(&id) {
+                Ok(offer) => serde_json::from_str(&offer)?,
+                Err(_) => continue,
+            };
+            // offers without valid expiration date are rejected on issuance as well
+            if is_date_in_past(&offer.expires_at).unwrap_or(true) {
+                self.remove_secret(&id)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn get_credential_offer_id(nonce: &Nonce) -> Result<String, Box<dyn Error>> {
+    Ok(format!(
+        "{}{}",
+        CREDENTIAL_OFFER_ID_PREFIX,
+        serde_json::to_string(nonce)?
+    ))
+}
+
+/// Content of an `IssuerStorage`, shared by its implementations.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct IssuerStorageContent {
+    secrets: HashMap<String, String>,
+    revocation_info: HashMap<String, RevocationIdInformation>,
+}
+
+impl IssuerStorageContent {
+    fn get_secret(&self, id: &str) -> Result<String, Box<dyn Error>> {
+        self.secrets
+            .get(id)
+            .cloned()
+            .ok_or_else(|| Box::from(format!("no secret stored with id {}", id)))
+    }
+
+    fn get_secret_ids(&self, prefix: &str) -> Vec<String> {
+        self.secrets
+            .keys()
+            .filter(|id| id.starts_with(prefix))
+            .cloned()
+            .collect()
+    }
+
+    fn get_revocation_info(
+        &self,
+        definition_id: &str,
+    ) -> Result<RevocationIdInformation, Box<dyn Error>> {
+        self.revocation_info
+            .get(definition_id)
+            .cloned()
+            .ok_or_else(|| {
+                Box::from(format!(
+                    "no revocation info stored for revocation registry definition {}",
+                    definition_id,
+                ))
+            })
+    }
+
+    fn allocate_revocation_id(
+        &mut self,
+        definition_id: &str,
+        maximum_credential_count: u32,
+    ) -> Result<u32, Box<dyn Error>> {
+        let info = self.revocation_info.get_mut(definition_id).ok_or_else(|| {
+            format!(
+                "no revocation info stored for revocation registry definition {}",
+                definition_id,
+            )
+        })?;
+        let revocation_id = info.next_unused_id;
+        if revocation_id > maximum_credential_count {
+            return Err(Box::from(format!(
+                "all {} revocation IDs of revocation registry definition {} have been used",
+                maximum_credential_count, definition_id,
+            )));
+        }
+        if !info.used_ids.insert(revocation_id) {
+            return Err(Box::from("Could not use next revocation ID as it has already been used - Counter information seems to be corrupted"));
+        }
+        info.next_unused_id = revocation_id + 1;
+
+        Ok(revocation_id)
+    }
+}
+
+/// `IssuerStorage` keeping all data in memory, content is lost when dropped.
+#[derive(Default)]
+pub struct InMemoryIssuerStorage {
+    content: Mutex<IssuerStorageContent>,
+}
+
+impl InMemoryIssuerStorage {
+    pub fn new() -> InMemoryIssuerStorage {
+        InMemoryIssuerStorage::default()
+    }
+}
+
+impl IssuerStorage for InMemoryIssuerStorage {
+    fn set_secret(&self, id: &str, secret: &str) -> Result<(), Box<dyn Error>> {
+        self.content
+            .lock()
+            .map_err(|e| format!("could not lock issuer storage; {}", &e))?
+            .secrets
+            .insert(id.to_owned(), secret.to_owned());
+        Ok(())
+    }
+
+    fn get_secret(&self, id: &str) -> Result<String, Box<dyn Error>> {
+        self.content
+            .lock()
+            .map_err(|e| format!("could not lock issuer storage; {}", &e))?
+            .get_secret(id)
+    }
+
+    fn remove_secret(&self, id: &str) -> Result<(), Box<dyn Error>> {
+        self.content
+            .lock()
+            .map_err(|e| format!("could not lock issuer storage; {}", &e))?
+            .secrets
+            .remove(id);
+        Ok(())
+    }
+
+    fn get_secret_ids(&self, prefix: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(self
+            .content
+            .lock()
+            .map_err(|e| format!("could not lock issuer storage; {}", &e))?
+            .get_secret_ids(prefix))
+    }
+
+    fn set_revocation_info(&self, info: &RevocationIdInformation) -> Result<(), Box<dyn Error>> {
+        self.content
+            .lock()
+            .map_err(|e| format!("could not lock issuer storage; {}", &e))?
+            .revocation_info
+            .insert(info.definition_id.to_owned(), info.clone());
+        Ok(())
+    }
+
+    fn get_revocation_info(
+        &self,
+        definition_id: &str,
+    ) -> Result<RevocationIdInformation, Box<dyn Error>> {
+        self.content
+            .lock()
+            .map_err(|e| format!("could not lock issuer storage; {}", &e))?
+            .get_revocation_info(definition_id)
+    }
+
+    fn allocate_revocation_id(
+        &self,
+        definition_id: &str,
+        maximum_credential_count: u32,
+    ) -> Result<u32, Box<dyn Error>> {
+        self.content
+            .lock()
+            .map_err(|e| format!("could not lock issuer storage; {}", &e))?
+            .allocate_revocation_id(definition_id, maximum_credential_count)
+    }
+}
+
+/// `IssuerStorage` persisting all data in a file, encrypted with a key derived from a passphrase.
+/// The key is derived once and kept as long as the storage lives. Every change is guarded by an
+/// exclusive OS lock on a lock file next to the storage file, so multiple processes can share the
+/// same file, and the lock is released by the OS if a process dies while holding it. Content is
+/// written to a temporary file first, which then replaces the storage file.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct EncryptedFileIssuerStorage {
+    path: PathBuf,
+    passphrase: String,
+    key: Mutex<Option<PassphraseKey>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl EncryptedFileIssuerStorage {
+    /// Creates a new `EncryptedFileIssuerStorage`, the file at `path` is created on first write.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the file to store issuer data in
+    /// * `passphrase` - Passphrase to derive the file's encryption key from
+    pub fn new<P: AsRef<Path>>(path: P, passphrase: &str) -> EncryptedFileIssuerStorage {
+        EncryptedFileIssuerStorage {
+            path: path.as_ref().to_path_buf(),
+            passphrase: passphrase.to_owned(),
+            key: Mutex::new(None),
+        }
+    }
+
+    fn with_extension(&self, extension: &str) -> PathBuf {
+        let mut file_name = self.path.as_os_str().to_owned();
+        file_name.push(extension);
+        PathBuf::from(file_name)
+    }
+
+    fn read(&self) -> Result<IssuerStorageContent, Box<dyn Error>> {
+        let encrypted: EncryptedData = match fs::read_to_string(&self.path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| format!("could not parse issuer storage; {}", &e))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(IssuerStorageContent::default()),
+            Err(e) => return Err(Box::from(format!("could not read issuer storage; {}", &e))),
+        };
+        let mut key = self
+            .key
+            .lock()
+            .map_err(|e| format!("could not lock issuer storage key; {}", &e))?;
+        if !key.as_ref().map_or(false, |key| key.matches(&encrypted)) {
+            *key = Some(PassphraseKey::for_encrypted(&self.passphrase, &encrypted)?);
+        }
+        let decrypted = key
+            .as_ref()
+            .ok_or("could not get issuer storage key")?
+            .decrypt(&encrypted)?;
+
+        Ok(serde_json::from_slice(&decrypted)?)
+    }
+
+    fn write(&self, content: &IssuerStorageContent) -> Result<(), Box<dyn Error>> {
+        let mut key = self
+            .key
+            .lock()
+            .map_err(|e| format!("could not lock issuer storage key; {}", &e))?;
+        if key.is_none() {
+            *key = Some(PassphraseKey::new(&self.passphrase)?);
+        }
+        let encrypted = key
+            .as_ref()
+            .ok_or("could not get issuer storage key")?
+            .encrypt(&serde_json::to_vec(content)?)?;
+        let temp_path = self.with_extension(".tmp");
+        fs::write(&temp_path, serde_json::to_string(&encrypted)?)
+            .map_err(|e| format!("could not write issuer storage; {}", &e))?;
+        fs::rename(&temp_path, &self.path)
+            .map_err(|e| format!("could not replace issuer storage; {}", &e))?;
+        Ok(())
+    }
+
+    /// Runs `f` on the file's content while holding the file lock and writes the content back
+    /// if `f` succeeds.
+    fn update<T, F>(&self, f: F) -> Result<T, Box<dyn Error>>
+    where
+        F: FnOnce(&mut IssuerStorageContent) -> Result<T, Box<dyn Error>>,
+    {
+        let lock_file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .open(self.with_extension(".lock"))
+            .map_err(|e| format!("could not open issuer storage lock file; {}", &e))?;
+        lock_file
+            .lock_exclusive()
+            .map_err(|e| format!("could not lock issuer storage file; {}", &e))?;
+
+        let result = self.read().and_then(|mut content| {
+            let result = f(&mut content)?;
+            self.write(&content)?;
+            Ok(result)
+        });
+        lock_file
+            .unlock()
+            .map_err(|e| format!("could not unlock issuer storage file; {}", &e))?;
+
+        result
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl IssuerStorage for EncryptedFileIssuerStorage {
+    fn set_secret(&self, id: &str, secret: &str) -> Result<(), Box<dyn Error>> {
+        self.update(|content| {
+            content.secrets.insert(id.to_owned(), secret.to_owned());
+            Ok(())
+        })
+    }
+
+    fn get_secret(&self, id: &str) -> Result<String, Box<dyn Error>> {
+        self.read()?.get_secret(id)
+    }
+
+    fn remove_secret(&self, id: &str) -> Result<(), Box<dyn Error>> {
+        self.update(|content| {
+            content.secrets.remove(id);
+            Ok(())
+        })
+    }
+
+    fn get_secret_ids(&self, prefix: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(self.read()?.get_secret_ids(prefix))
+    }
+
+    fn set_revocation_info(&self, info: &RevocationIdInformation) -> Result<(), Box<dyn Error>> {
+        self.update(|content| {
+            content
+                .revocation_info
+                .insert(info.definition_id.to_owned(), info.clone());
+            Ok(())
+        })
+    }
+
+    fn get_revocation_info(
+        &self,
+        definition_id: &str,
+    ) -> Result<RevocationIdInformation, Box<dyn Error>> {
+        self.read()?.get_revocation_info(definition_id)
+    }
+
+    fn allocate_revocation_id(
+        &self,
+        definition_id: &str,
+        maximum_credential_count: u32,
+    ) -> Result<u32, Box<dyn Error>> {
+        self.update(|content| {
+            content.allocate_revocation_id(definition_id, maximum_credential_count)
+        })
+    }
+}
+
+/// Keeps track of the nonces of credential offers that have already been used for issuance,
+/// so credential requests cannot be replayed.
+pub trait UsedNonceStore {
+    /// Marks a nonce as used. Fails if it has already been used before.
+    /// Implementations have to make sure that no nonce can be used twice, even for concurrent callers.
+    ///
+    /// # Arguments
+    /// * `nonce` - serialized nonce of a credential offer
+    /// * `expires_at` - ISO 8601 expiration date of the credential offer, nonce can be forgotten afterwards
+    fn use_nonce(&self, nonce: &str, expires_at: &str) -> Result<(), Box<dyn Error>>;
+}
+
+/// `UsedNonceStore` keeping used nonces in memory until their offers expire.
+#[derive(Default)]
+pub struct InMemoryUsedNonceStore {
+    nonces: Mutex<HashMap<String, String>>,
+}
+
+impl InMemoryUsedNonceStore {
+    pub fn new() -> InMemoryUsedNonceStore {
+        InMemoryUsedNonceStore::default()
+    }
+}
+
+impl UsedNonceStore for InMemoryUsedNonceStore {
+    fn use_nonce(&self, nonce: &str, expires_at: &str) -> Result<(), Box<dyn Error>> {
+        let mut nonces = self
+            .nonces
+            .lock()
+            .map_err(|e| format!("could not lock used nonce store; {}", &e))?;
+        get_timestamp_millis(expires_at)?;
+        nonces.retain(|_, nonce_expires_at| !is_date_in_past(nonce_expires_at).unwrap_or(false));
+        if nonces.contains_key(nonce) {
+            return Err(Box::from("credential offer nonce has already been used"));
+        }
+        nonces.insert(nonce.to_owned(), expires_at.to_owned());
+
+        Ok(())
+    }
+}
+
+/// Keeps track of the sessions of a verifier, i.e. the nonces of proof requests that have already
+/// been answered with a successfully verified presentation, so presentations cannot be replayed.
+pub trait VerifierSessionStore {
+    /// Marks the nonce of a proof request as consumed. Fails if it has already been consumed before.
+    /// Implementations have to make sure that no nonce can be consumed twice, even for concurrent callers.
+    ///
+    /// # Arguments
+    /// * `nonce` - serialized nonce of a proof request
+    /// * `expires_at` - ISO 8601 expiration date of the proof request, nonce can be forgotten afterwards,
+    /// has to be kept forever if omitted
+    fn consume_nonce(&self, nonce: &str, expires_at: Option<&str>) -> Result<(), Box<dyn Error>>;
+}
+
+/// `VerifierSessionStore` keeping consumed nonces in memory until their proof requests expire.
+#[derive(Default)]
+pub struct InMemoryVerifierSessionStore {
+    nonces: Mutex<HashMap<String, Option<String>>>,
+}
+
+impl InMemoryVerifierSessionStore {
+    pub fn new() -> InMemoryVerifierSessionStore {
+        InMemoryVerifierSessionStore::default()
+    }
+}
+
+impl VerifierSessionStore for InMemoryVerifierSessionStore {
+    fn consume_nonce(&self, nonce: &str, expires_at: Option<&str>) -> Result<(), Box<dyn Error>> {
+        let mut nonces = self
+            .nonces
+            .lock()
+            .map_err(|e| format!("could not lock verifier session store; {}", &e))?;
+        if let Some(expires_at) = expires_at {
+            get_timestamp_millis(expires_at)?;
+        }
+        nonces.retain(|_, nonce_expires_at| match nonce_expires_at {
+            Some(nonce_expires_at) => !is_date_in_past(nonce_expires_at).unwrap_or(false),
+            None => true,
+        });
+        if nonces.contains_key(nonce) {
+            return Err(Box::from("proof request has already been answered"));
+        }
+        nonces.insert(nonce.to_owned(), expires_at.map(|e| e.to_owned()));
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::issuer::Issuer;
+    use std::collections::HashSet;
+
+    fn new_revocation_info(definition_id: &str) -> RevocationIdInformation {
+        RevocationIdInformation {
+            definition_id: definition_id.to_owned(),
+            next_unused_id: 1,
+            used_ids: HashSet::new(),
+        }
+    }
+
+    #[test]
+    fn can_allocate_revocation_ids_in_memory() -> Result<(), Box<dyn Error>> {
+        let storage = InMemoryIssuerStorage::new();
+        storage.set_revocation_info(&new_revocation_info("did:evan:registry"))?;
+
+        assert_eq!(storage.allocate_revocation_id("did:evan:registry", 2)?, 1);
+        assert_eq!(storage.allocate_revocation_id("did:evan:registry", 2)?, 2);
+        // full registries do not use up IDs
+        assert!(storage
+            .allocate_revocation_id("did:evan:registry", 2)
+            .is_err());
+        let info = storage.get_revocation_info("did:evan:registry")?;
+        assert_eq!(info.next_unused_id, 3);
+        assert!(info.used_ids.contains(&1) && info.used_ids.contains(&2));
+        assert!(storage
+            .allocate_revocation_id("did:evan:unknown", 2)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn can_store_credential_offers() -> Result<(), Box<dyn Error>> {
+        let storage = InMemoryIssuerStorage::new();
+        let offer = Issuer::offer_credential(
+            "did:evan:issuer",
+            "did:evan:subject",
+            "did:evan:schema",
+            "did:evan:definition",
+            None,
+        )?;
+        storage.set_credential_offer(&offer)?;
+        assert_eq!(
+            serde_json::to_string(&storage.get_credential_offer(&offer.nonce)?)?,
+            serde_json::to_string(&offer)?,
+        );
+        let other_offer = Issuer::offer_credential(
+            "did:evan:issuer",
+            "did:evan:subject",
+            "did:evan:schema",
+            "did:evan:definition",
+            None,
+        )?;
+        assert!(storage.get_credential_offer(&other_offer.nonce).is_err());
+
+        storage.remove_credential_offer(&offer.nonce)?;
+        assert!(storage.get_credential_offer(&offer.nonce).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn removes_expired_credential_offers() -> Result<(), Box<dyn Error>> {
+        let storage = InMemoryIssuerStorage::new();
+        let new_offer = || {
+            Issuer::offer_credential(
+                "did:evan:issuer",
+                "did:evan:subject",
+                "did:evan:schema",
+                "did:evan:definition",
+                None,
+            )
+        };
+        let mut expired_offer = new_offer()?;
+        expired_offer.expires_at = "2000-01-01T00:00:00.000Z".to_owned();
+        storage.set_credential_offer(&expired_offer)?;
+        let valid_offer = new_offer()?;
+        storage.set_credential_offer(&valid_offer)?;
+        assert!(storage.get_credential_offer(&valid_offer.nonce).is_ok());
+        assert!(storage.get_credential_offer(&expired_offer.nonce).is_err());
+        assert_eq!(
+            storage.get_secret_ids(CREDENTIAL_OFFER_ID_PREFIX)?,
+            vec![get_credential_offer_id(&valid_offer.nonce)?],
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_used_nonces() -> Result<(), Box<dyn Error>> {
+        let store = InMemoryUsedNonceStore::new();
+        store.use_nonce("\"1234\"", "2999-01-01T00:00:00.000Z")?;
+        store.use_nonce("\"5678\"", "2999-01-01T00:00:00.000Z")?;
+        assert!(store
+            .use_nonce("\"1234\"", "2999-01-01T00:00:00.000Z")
+            .is_err());
+        assert!(store.use_nonce("\"9012\"", "tomorrow").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_consumed_proof_request_nonces() -> Result<(), Box<dyn Error>> {
+        let store = InMemoryVerifierSessionStore::new();
+        store.consume_nonce("\"1234\"", None)?;
+        store.consume_nonce("\"5678\"", Some("2999-01-01T00:00:00.000Z"))?;
+        assert!(store.consume_nonce("\"1234\"", None).is_err());
+        assert!(store
+            .consume_nonce("\"5678\"", Some("2999-01-01T00:00:00.000Z"))
+            .is_err());
+
+        // nonces of expired requests are forgotten
+        store.consume_nonce("\"9012\"", Some("2000-01-01T00:00:00.000Z"))?;
+        store.consume_nonce("\"9012\"", Some("2000-01-01T01:00:00+01:00"))?;
+        assert!(store.consume_nonce("\"3456\"", Some("tomorrow")).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn can_store_issuer_data_in_encrypted_file() -> Result<(), Box<dyn Error>> {
+        let path = std::env::temp_dir().join(format!(
+            "vade-evan-cl-issuer-storage-{}.json",
+            crate::utils::utils::generate_uuid(),
+        ));
+        let storage = EncryptedFileIssuerStorage::new(&path, "passphrase");
+        // lock files left behind, e.g. by killed processes, do not block the storage
+        fs::write(storage.with_extension(".lock"), "")?;
+        storage.set_secret("did:evan:definition", "secret")?;
+        storage.set_revocation_info(&new_revocation_info("did:evan:registry"))?;
+        assert_eq!(storage.allocate_revocation_id("did:evan:registry", 42)?, 1);
+        assert!(!fs::read_to_string(&path)?.contains("secret"));
+
+        // data is read from file by new instances with the same passphrase
+        let storage = EncryptedFileIssuerStorage::new(&path, "passphrase");
+        assert_eq!(storage.get_secret("did:evan:definition")?, "secret");
+        assert_eq!(storage.allocate_revocation_id("did:evan:registry", 42)?, 2);
+        let other_storage = EncryptedFileIssuerStorage::new(&path, "wrong");
+        assert!(other_storage.get_secret("did:evan:definition").is_err());
+
+        fs::remove_file(storage.with_extension(".lock"))?;
+        fs::remove_file(&path)?;
+
+        Ok(())
+    }
+}
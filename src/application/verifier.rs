@@ -15,21 +15,35 @@
 */
 
 use crate::{
-    application::datatypes::{
-        CredentialDefinition,
-        CredentialSchema,
-        ProofPresentation,
-        ProofRequest,
-        ProofVerification,
-        RevocationRegistryDefinition,
-        SubProofRequest,
-        CL_TYPE,
+    application::{
+        datatypes::{
+            CredentialDefinition,
+            CredentialSchema,
+            NonRevokedInterval,
+            ProofPresentation,
+            ProofRequest,
+            ProofVerification,
+            RevocationPointInTime,
+            RevocationRegistryDefinition,
+            RevocationStatus,
+            SubProofRequest,
+            CL_TYPE,
+        },
+        prover::Prover,
+        storage::VerifierSessionStore,
     },
-    crypto::crypto_verifier::verifier::CredVerifier,
-    utils::utils::get_now_as_iso_string,
+    crypto::{
+        crypto_utils::{check_assertion_proof, get_revocation_delta_indices},
+        crypto_verifier::verifier::CredVerifier,
+    },
+    utils::utils::{get_iso_string_in_seconds, get_now_as_iso_string, is_date_in_past},
+};
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
 };
-use std::{collections::HashMap, error::Error};
-use ursa::cl::new_nonce;
+use ursa::cl::{new_nonce, RevocationRegistry};
 
 /// Holds the logic needed to verify proofs
 pub struct Verifier {}
@@ -45,6 +59,8 @@ impl Verifier {
     /// * `verifier_did` - DID of the verifier
     /// * `prover_did` - DID of the prover
     /// * `sub_proof_requests` - Collection of subproof requests to be requested from the prover
+    /// * `non_revoked` - Optional time range in which the proven credentials have to be non-revoked
+    /// * `validity` - Optional number of seconds the request can be answered for, does not expire if omitted
     ///
     /// # Returns
     /// * `ProofRequest` - The message to be sent to a prover
@@ -52,14 +68,18 @@ impl Verifier {
         verifier_did: &str,
         prover_did: &str,
         sub_proof_requests: Vec<SubProofRequest>,
+        non_revoked: Option<NonRevokedInterval>,
+        validity: Option<u64>,
     ) -> Result<ProofRequest, Box<dyn Error>> {
         Ok(ProofRequest {
             verifier: verifier_did.to_owned(),
             prover: prover_did.to_owned(),
             created_at: get_now_as_iso_string(),
+            expires_at: validity.map(get_iso_string_in_seconds),
             r#type: CL_TYPE.to_string(),
             nonce: new_nonce().map_err(|e| format!("could not get new nonce; {}", &e))?,
             sub_proof_requests,
+            non_revoked,
         })
     }
 
@@ -71,6 +91,7 @@ impl Verifier {
     /// * `credential_definitions` - All definitions associated to the sent proofs, indexed by the according `CredentialSchema`'s ID
     /// * `credential_schemas` - All schemas associated to the sent proofs, indexed by their ID
     /// * `revocation_registry_definition` - All revocation registry definitions associated to the sent proofs, indexed by the according `CredentialSchema`'s ID
+    /// * `revocation_point_in_time` - Optional point in time to check non-revocation against instead of the latest registry version or, if the proof request has a `non_revoked` interval, the registry version used by the prover, audits presentations of expired proof requests if set
     ///
     /// # Returns
     /// * `ProofVerification` - States whether the verification was successful or not
@@ -79,17 +100,45 @@ impl Verifier {
         proof_request: ProofRequest,
         credential_definitions: HashMap<String, CredentialDefinition>,
         credential_schemas: HashMap<String, CredentialSchema>,
-        revocation_registry_definition: HashMap<String, Option<RevocationRegistryDefinition>>,
+        mut revocation_registry_definition: HashMap<String, Option<RevocationRegistryDefinition>>,
+        revocation_point_in_time: Option<RevocationPointInTime>,
     ) -> ProofVerification {
         let status: &str;
         let mut reason: Option<String> = None;
-        match CredVerifier::verify_proof(
-            &presented_proof,
-            &proof_request,
-            &credential_definitions,
-            &credential_schemas,
-            &revocation_registry_definition,
-        ) {
+        // audits check presentations at historical revocation registry versions, which are
+        // usually made after the proof request has expired
+        let expiry_check = match revocation_point_in_time {
+            Some(_) => Ok(()),
+            None => Verifier::check_proof_request_expiry(&proof_request),
+        };
+        let result = expiry_check
+            .and_then(|_| Verifier::check_presentation_structure(&presented_proof, &proof_request))
+            .and_then(|_| {
+                Verifier::select_revocation_registries(
+                    &presented_proof,
+                    &proof_request,
+                    &mut revocation_registry_definition,
+                    revocation_point_in_time.as_ref(),
+                )
+            })
+            .and_then(|_| {
+                Verifier::check_credential_metadata(
+                    &presented_proof,
+                    &credential_definitions,
+                    &revocation_registry_definition,
+                )
+            })
+            .and_then(|_| Verifier::check_restrictions(&presented_proof, &proof_request))
+            .and_then(|_| {
+                CredVerifier::verify_proof(
+                    &presented_proof,
+                    &proof_request,
+                    &credential_definitions,
+                    &credential_schemas,
+                    &revocation_registry_definition,
+                )
+            });
+        match result {
             Ok(()) => status = "verified",
             Err(e) => {
                 status = "rejected";
@@ -103,6 +152,519 @@ impl Verifier {
             reason,
         }
     }
+
+    /// Marks a proof request as answered after its presentation has been verified, so it cannot
+    /// be answered again. Audits, i.e. verifications against a `revocation_point_in_time`,
+    /// re-verify presentations of answered requests and do not consume them.
+    ///
+    /// # Arguments
+    /// * `proof_request` - The proof request the verified presentation answers
+    /// * `revocation_point_in_time` - Point in time the presentation has been verified against
+    /// * `verifier_sessions` - Store keeping track of answered proof requests
+    pub fn consume_proof_request(
+        proof_request: &ProofRequest,
+        revocation_point_in_time: Option<&RevocationPointInTime>,
+        verifier_sessions: &dyn VerifierSessionStore,
+    ) -> Result<(), Box<dyn Error>> {
+        if revocation_point_in_time.is_some() {
+            return Ok(());
+        }
+        verifier_sessions.consume_nonce(
+            &serde_json::to_string(&proof_request.nonce)?,
+            proof_request.expires_at.as_deref(),
+        )
+    }
+
+    /// Checks the holder binding of a proof presentation, i.e. that the presentation's ID and the
+    /// proof request's nonce have been signed with a key of the prover DID and that all presented
+    /// credentials have been issued for the prover DID.
+    ///
+    /// # Arguments
+    /// * `presented_proof` - The proof presentation from a prover
+    /// * `proof_request` - The proof request sent by the verifier to the prover beforehand
+    /// * `prover_did_document` - Resolved DID document of the proof request's prover
+    pub fn check_holder_binding(
+        presented_proof: &ProofPresentation,
+        proof_request: &ProofRequest,
+        prover_did_document: &Value,
+    ) -> Result<(), Box<dyn Error>> {
+        let holder_binding = presented_proof
+            .holder_binding
+            .as_ref()
+            .ok_or("proof presentation does not contain a holder binding")?;
+        for credential in &presented_proof.verifiable_credential {
+            if credential.credential_subject.id != proof_request.prover {
+                return Err(Box::from(format!(
+                    "credential {} has been issued for {}, not for prover {}",
+                    &credential.id, &credential.credential_subject.id, &proof_request.prover,
+                )));
+            }
+        }
+
+        let address = Verifier::get_prover_address(
+            prover_did_document,
+            &proof_request.prover,
+            &holder_binding.verification_method,
+        )?;
+        let mut document = Prover::get_holder_binding_document(&presented_proof.id, proof_request)?;
+        document["proof"] = serde_json::to_value(holder_binding)?;
+        check_assertion_proof(&document.to_string(), &address)
+            .map_err(|e| format!("invalid holder binding; {}", &e))?;
+
+        Ok(())
+    }
+
+    /// Checks the assertion proof over the envelope of a proof presentation, i.e. that the
+    /// presentation including the metadata of its credentials has been signed with a key of the
+    /// prover DID. Metadata, that can be derived from credential definitions, is checked against
+    /// them in `verify_proof`.
+    ///
+    /// # Arguments
+    /// * `presented_proof` - The proof presentation from a prover
+    /// * `proof_request` - The proof request sent by the verifier to the prover beforehand
+    /// * `prover_did_document` - Resolved DID document of the proof request's prover
+    pub fn check_presentation_envelope(
+        presented_proof: &ProofPresentation,
+        proof_request: &ProofRequest,
+        prover_did_document: &Value,
+    ) -> Result<(), Box<dyn Error>> {
+        let assertion_proof = presented_proof
+            .assertion_proof
+            .as_ref()
+            .ok_or("proof presentation does not contain an assertion proof")?;
+        let address = Verifier::get_prover_address(
+            prover_did_document,
+            &proof_request.prover,
+            &assertion_proof.verification_method,
+        )?;
+        let mut document = Prover::get_presentation_envelope_document(presented_proof)?;
+        document["proof"] = serde_json::to_value(assertion_proof)?;
+        check_assertion_proof(&document.to_string(), &address)
+            .map_err(|e| format!("invalid presentation assertion proof; {}", &e))?;
+
+        Ok(())
+    }
+
+    /// Checks that a proof request has not expired yet. Requests without expiration date do not expire.
+    ///
+    /// # Arguments
+    /// * `proof_request` - The proof request sent by the verifier to the prover beforehand
+    fn check_proof_request_expiry(proof_request: &ProofRequest) -> Result<(), Box<dyn Error>> {
+        if let Some(expires_at) = &proof_request.expires_at {
+            if is_date_in_past(expires_at)? {
+                return Err(Box::from(format!(
+                    "proof request has expired at {}",
+                    expires_at
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that a proof presentation answers every sub proof request of a proof request in
+    /// order, i.e. that it contains one credential per sub proof request, with the requested
+    /// schema and exactly the requested attributes revealed.
+    ///
+    /// # Arguments
+    /// * `presented_proof` - The proof presentation from a prover
+    /// * `proof_request` - The proof request sent by the verifier to the prover beforehand
+    fn check_presentation_structure(
+        presented_proof: &ProofPresentation,
+        proof_request: &ProofRequest,
+    ) -> Result<(), Box<dyn Error>> {
+        if presented_proof.verifiable_credential.len() != proof_request.sub_proof_requests.len() {
+            return Err(Box::from(format!(
+                "proof presentation contains {} credentials, but {} have been requested",
+                presented_proof.verifiable_credential.len(),
+                proof_request.sub_proof_requests.len(),
+            )));
+        }
+        for (index, (sub_proof_request, credential)) in proof_request
+            .sub_proof_requests
+            .iter()
+            .zip(presented_proof.verifiable_credential.iter())
+            .enumerate()
+        {
+            if credential.credential_schema.id != sub_proof_request.schema {
+                return Err(Box::from(format!(
+                    "credential {} at position {} has schema {}, but schema {} has been requested",
+                    &credential.id,
+                    index,
+                    &credential.credential_schema.id,
+                    &sub_proof_request.schema,
+                )));
+            }
+            let requested: HashSet<&String> =
+                sub_proof_request.revealed_attributes.iter().collect();
+            let revealed: HashSet<&String> = credential.credential_subject.data.keys().collect();
+            if requested != revealed {
+                let mut requested: Vec<&String> = requested.into_iter().collect();
+                requested.sort();
+                let mut revealed: Vec<&String> = revealed.into_iter().collect();
+                revealed.sort();
+                return Err(Box::from(format!(
+                    "credential {} reveals attributes {:?}, but {:?} have been requested",
+                    &credential.id, revealed, requested,
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that presented credentials satisfy the restrictions of their sub proof requests.
+    /// Issuers and credential definitions of the credentials have to be checked against the
+    /// definitions used for verification beforehand, see `check_credential_metadata`.
+    ///
+    /// # Arguments
+    /// * `presented_proof` - The proof presentation from a prover
+    /// * `proof_request` - The proof request sent by the verifier to the prover beforehand
+    fn check_restrictions(
+        presented_proof: &ProofPresentation,
+        proof_request: &ProofRequest,
+    ) -> Result<(), Box<dyn Error>> {
+        for (sub_proof_request, credential) in proof_request
+            .sub_proof_requests
+            .iter()
+            .zip(presented_proof.verifiable_credential.iter())
+        {
+            if !sub_proof_request
+                .is_satisfied_by(&credential.issuer, &credential.proof.credential_definition)
+            {
+                return Err(Box::from(format!(
+                    "credential {} of issuer {} does not match any restriction for schema {}",
+                    &credential.id, &credential.issuer, &sub_proof_request.schema,
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the metadata of presented credentials, which is not covered by the CL proof,
+    /// matches the credential definitions and revocation registry definitions used to verify them.
+    ///
+    /// # Arguments
+    /// * `presented_proof` - The proof presentation from a prover
+    /// * `credential_definitions` - All definitions associated to the sent proofs, indexed by the according `CredentialSchema`'s ID
+    /// * `revocation_registry_definitions` - All revocation registry definitions associated to the sent proofs, indexed by the according `CredentialSchema`'s ID
+    fn check_credential_metadata(
+        presented_proof: &ProofPresentation,
+        credential_definitions: &HashMap<String, CredentialDefinition>,
+        revocation_registry_definitions: &HashMap<String, Option<RevocationRegistryDefinition>>,
+    ) -> Result<(), Box<dyn Error>> {
+        for credential in &presented_proof.verifiable_credential {
+            let schema = &credential.credential_schema.id;
+            let definition = credential_definitions
+                .get(schema)
+                .ok_or_else(|| format!("no credential definition given for schema {}", schema))?;
+            if definition.id != credential.proof.credential_definition {
+                return Err(Box::from(format!(
+                    "credential {} references credential definition {}, but got {} for schema {}",
+                    &credential.id, &credential.proof.credential_definition, &definition.id, schema,
+                )));
+            }
+            if definition.issuer != credential.issuer {
+                return Err(Box::from(format!(
+                    "credential {} claims issuer {}, but its credential definition has issuer {}",
+                    &credential.id, &credential.issuer, &definition.issuer,
+                )));
+            }
+            if definition.schema != *schema {
+                return Err(Box::from(format!(
+                    "credential {} claims schema {}, but its credential definition has schema {}",
+                    &credential.id, schema, &definition.schema,
+                )));
+            }
+            if let Some(Some(rev_reg_def)) = revocation_registry_definitions.get(schema) {
+                if rev_reg_def.id != credential.proof.revocation_registry_definition {
+                    return Err(Box::from(format!(
+                        "credential {} references revocation registry definition {}, but got {}",
+                        &credential.id,
+                        &credential.proof.revocation_registry_definition,
+                        &rev_reg_def.id,
+                    )));
+                }
+                if rev_reg_def.credential_definition != definition.id {
+                    return Err(Box::from(format!(
+                        "revocation registry {} belongs to credential definition {}, not to {}",
+                        &rev_reg_def.id, &rev_reg_def.credential_definition, &definition.id,
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gets the address of a key of the prover DID from the prover's DID document.
+    ///
+    /// # Arguments
+    /// * `prover_did_document` - Resolved DID document of the prover
+    /// * `prover_did` - DID of the prover
+    /// * `verification_method` - DID of the key
+    ///
+    /// # Returns
+    /// * `String` - Lower case ethereum address of the key
+    fn get_prover_address(
+        prover_did_document: &Value,
+        prover_did: &str,
+        verification_method: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        if prover_did_document["id"].as_str() != Some(prover_did) {
+            return Err(Box::from(format!(
+                "given DID document does not belong to prover {}",
+                prover_did,
+            )));
+        }
+        let address = prover_did_document["publicKey"]
+            .as_array()
+            .and_then(|keys| {
+                keys.iter()
+                    .find(|key| key["id"].as_str() == Some(verification_method))
+            })
+            .and_then(|key| key["ethereumAddress"].as_str())
+            .ok_or_else(|| {
+                format!(
+                    "could not find ethereum address of key {} in DID document of prover {}",
+                    verification_method, prover_did,
+                )
+            })?;
+
+        Ok(address.to_lowercase())
+    }
+
+    /// Gets the revocation status of a credential by evaluating the `registry_delta` of its
+    /// revocation registry definition.
+    ///
+    /// # Arguments
+    /// * `rev_reg_def` - Revocation registry definition the credential belongs to
+    /// * `revocation_id` - Revocation ID of the credential
+    ///
+    /// # Returns
+    /// * `RevocationStatus` - States whether the credential is "active" or "revoked"
+    pub fn get_revocation_status(
+        rev_reg_def: &RevocationRegistryDefinition,
+        revocation_id: u32,
+    ) -> Result<RevocationStatus, Box<dyn Error>> {
+        if revocation_id == 0 || revocation_id > rev_reg_def.maximum_credential_count {
+            return Err(Box::from(format!(
+                "revocation ID {} is not part of revocation registry {}",
+                revocation_id, rev_reg_def.id,
+            )));
+        }
+        let (_, revoked) = get_revocation_delta_indices(&rev_reg_def.registry_delta)?;
+        let status = if revoked.contains(&revocation_id) {
+            "revoked"
+        } else {
+            "active"
+        };
+
+        Ok(RevocationStatus {
+            revocation_registry_definition: rev_reg_def.id.to_owned(),
+            revocation_id,
+            version: rev_reg_def.version,
+            status: status.to_owned(),
+        })
+    }
+
+    /// Rebuilds the revocation registry of a revocation registry definition as it has been at the given version.
+    /// The version has to be contained in the definition's `DeltaHistory` or be the version of its `DeltaCheckpoint`.
+    ///
+    /// # Arguments
+    /// * `rev_reg_def` - Revocation registry definition to rebuild the registry from
+    /// * `version` - Version to rebuild the registry for
+    ///
+    /// # Returns
+    /// * `RevocationRegistry` - The registry (accumulator) at the given version
+    pub fn get_revocation_registry_at_version(
+        rev_reg_def: &RevocationRegistryDefinition,
+        version: u64,
+    ) -> Result<RevocationRegistry, Box<dyn Error>> {
+        if version == rev_reg_def.version {
+            return Ok(rev_reg_def.registry.clone());
+        }
+        if version > rev_reg_def.version {
+            return Err(Box::from(format!(
+                "revocation registry version {} does not exist, latest version is {}",
+                version, rev_reg_def.version,
+            )));
+        }
+
+        let (mut delta, mut last_version) =
+            match (&rev_reg_def.checkpoint, rev_reg_def.delta_history.first()) {
+                (Some(checkpoint), _) if checkpoint.version <= version => {
+                    (checkpoint.delta.clone(), checkpoint.version)
+                }
+                (Some(_), _) => {
+                    return Err(Box::from(format!(
+                        "revocation registry version {} has been compacted and cannot be rebuilt",
+                        version,
+                    )))
+                }
+                (None, Some(first)) => (first.delta.clone(), first.version),
+                (None, None) => return Err(Box::from("revocation registry has no delta history")),
+            };
+
+        let mut entries: Vec<_> = rev_reg_def
+            .delta_history
+            .iter()
+            .filter(|entry| entry.version > last_version && entry.version <= version)
+            .collect();
+        entries.sort_by(|a, b| a.version.cmp(&b.version));
+        for entry in entries {
+            if entry.version != last_version + 1 {
+                return Err(Box::from(format!(
+                    "could not rebuild revocation registry; delta history is missing version {}",
+                    last_version + 1,
+                )));
+            }
+            delta
+                .merge(&entry.delta)
+                .map_err(|e| format!("could not merge revocation registry delta; {}", &e))?;
+            last_version = entry.version;
+        }
+        if last_version != version {
+            return Err(Box::from(format!(
+                "could not rebuild revocation registry for version {}",
+                version,
+            )));
+        }
+
+        Ok(RevocationRegistry::from(delta))
+    }
+
+    /// Gets the version of a revocation registry definition that has been current at the given time.
+    ///
+    /// # Arguments
+    /// * `rev_reg_def` - Revocation registry definition to look up the version in
+    /// * `timestamp` - Unix timestamp in seconds
+    ///
+    /// # Returns
+    /// * `u64` - Version that has been current at `timestamp`
+    pub fn get_revocation_registry_version_at(
+        rev_reg_def: &RevocationRegistryDefinition,
+        timestamp: u64,
+    ) -> Result<u64, Box<dyn Error>> {
+        Verifier::get_revocation_registry_versions(rev_reg_def)
+            .into_iter()
+            .filter(|(_, created)| *created <= timestamp)
+            .map(|(version, _)| version)
+            .max()
+            .ok_or_else(|| {
+                Box::from(format!(
+                    "no revocation registry version available for timestamp {}",
+                    timestamp,
+                ))
+            })
+    }
+
+    /// Collects all versions of a revocation registry definition that can be rebuilt with their
+    /// creation timestamps, ordered by version.
+    fn get_revocation_registry_versions(
+        rev_reg_def: &RevocationRegistryDefinition,
+    ) -> Vec<(u64, u64)> {
+        let mut versions: Vec<(u64, u64)> = rev_reg_def
+            .delta_history
+            .iter()
+            .map(|entry| (entry.version, entry.created))
+            .collect();
+        if let Some(checkpoint) = &rev_reg_def.checkpoint {
+            versions.push((checkpoint.version, checkpoint.created));
+        }
+        versions.sort();
+
+        versions
+    }
+
+    /// Checks whether a registry version has been current at any time in the given interval.
+    fn check_non_revoked_interval(
+        rev_reg_def: &RevocationRegistryDefinition,
+        version: u64,
+        interval: &NonRevokedInterval,
+    ) -> Result<(), Box<dyn Error>> {
+        let versions = Verifier::get_revocation_registry_versions(rev_reg_def);
+        let valid_from = versions
+            .iter()
+            .filter(|(v, _)| *v == version)
+            .map(|(_, created)| *created)
+            .max()
+            .ok_or_else(|| format!("revocation registry version {} is not available", version))?;
+        let valid_until = versions
+            .iter()
+            .filter(|(v, _)| *v > version)
+            .map(|(_, created)| *created)
+            .min();
+
+        let from = interval.from.unwrap_or(interval.to);
+        if valid_from > interval.to || valid_until.map_or(false, |until| until <= from) {
+            return Err(Box::from(format!(
+                "revocation registry version {} is not within requested non-revocation interval",
+                version,
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the registries of the given revocation registry definitions with the registries
+    /// to check the presented proofs against and enforces the requested non-revocation interval.
+    fn select_revocation_registries(
+        presented_proof: &ProofPresentation,
+        proof_request: &ProofRequest,
+        revocation_registry_definition: &mut HashMap<String, Option<RevocationRegistryDefinition>>,
+        revocation_point_in_time: Option<&RevocationPointInTime>,
+    ) -> Result<(), Box<dyn Error>> {
+        for vc in &presented_proof.verifiable_credential {
+            let rev_reg_def = match revocation_registry_definition
+                .get_mut(&vc.credential_schema.id)
+                .and_then(|rev_reg_def| rev_reg_def.as_mut())
+            {
+                Some(rev_reg_def) => rev_reg_def,
+                None => continue,
+            };
+
+            let version = match revocation_point_in_time {
+                Some(RevocationPointInTime {
+                    version: Some(version),
+                    timestamp: None,
+                }) => *version,
+                Some(RevocationPointInTime {
+                    version: None,
+                    timestamp: Some(timestamp),
+                }) => Verifier::get_revocation_registry_version_at(rev_reg_def, *timestamp)?,
+                Some(_) => {
+                    return Err(Box::from(
+                        "revocation point in time needs either a timestamp or a version",
+                    ))
+                }
+                // the version used by the prover is only accepted within a requested interval,
+                // otherwise holders could prove non-revocation against outdated registries
+                None => match (&proof_request.non_revoked, vc.proof.revocation_registry_version) {
+                    (Some(_), Some(version)) => version,
+                    (None, Some(version)) if version != rev_reg_def.version => {
+                        return Err(Box::from(format!(
+                            "proof of credential {} uses revocation registry version {}, latest is {}",
+                            &vc.id, version, rev_reg_def.version,
+                        )))
+                    }
+                    _ => rev_reg_def.version,
+                },
+            };
+
+            if let Some(interval) = &proof_request.non_revoked {
+                Verifier::check_non_revoked_interval(rev_reg_def, version, interval)?;
+            }
+
+            if version != rev_reg_def.version {
+                rev_reg_def.registry =
+                    Verifier::get_revocation_registry_at_version(rev_reg_def, version)?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl Default for Verifier {
@@ -110,3 +672,829 @@ impl Default for Verifier {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate utilities;
+
+    use super::*;
+    use crate::application::{
+        datatypes::{CredentialRestriction, PROOF_VERSION},
+        issuer::Issuer,
+        storage::{InMemoryUsedNonceStore, InMemoryVerifierSessionStore},
+    };
+    use serde_json::json;
+    use utilities::test_data::{
+        accounts::local::{ISSUER_DID, ISSUER_PRIVATE_KEY, ISSUER_PUBLIC_KEY_DID},
+        did::EXAMPLE_DID_1,
+        vc_zkp::{
+            EXAMPLE_AGGREGATED_PROOF,
+            EXAMPLE_CREDENTIAL_SCHEMA,
+            EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID,
+            EXAMPLE_SUB_PROOF,
+        },
+    };
+    use vade_signer::{LocalSigner, Signer};
+
+    fn get_presentation_value(
+        schema: &str,
+        definition: &str,
+        registry: &str,
+    ) -> Result<Value, Box<dyn Error>> {
+        Ok(json!({
+            "@context": ["https://www.w3.org/2018/credentials/v1"],
+            "id": "presentation",
+            "type": ["VerifiablePresentation"],
+            "verifiableCredential": [{
+                "@context": ["https://www.w3.org/2018/credentials/v1"],
+                "id": "credential",
+                "type": ["VerifiableCredential"],
+                "issuer": ISSUER_DID,
+                "issuanceDate": "2020-01-01T00:00:00.000Z",
+                "credentialSubject": {
+                    "id": EXAMPLE_DID_1,
+                    "data": { "test_property_string": { "raw": "value", "encoded": "1" } },
+                },
+                "credentialSchema": { "id": schema, "type": "EvanZKPSchema" },
+                "proof": {
+                    "credentialDefinition": definition,
+                    "revocationRegistryDefinition": registry,
+                    "proof": serde_json::from_str::<Value>(EXAMPLE_SUB_PROOF)?,
+                },
+            }],
+            "proof": {
+                "nonce": serde_json::to_value(new_nonce().map_err(|e| format!("{}", &e))?)?,
+                "aggregatedProof": serde_json::from_str::<Value>(EXAMPLE_AGGREGATED_PROOF)?,
+            },
+        }))
+    }
+
+    #[tokio::test]
+    async fn can_rebuild_revocation_registry_at_version() -> Result<(), Box<dyn Error>> {
+        let schema: CredentialSchema = serde_json::from_str(&EXAMPLE_CREDENTIAL_SCHEMA)?;
+        let signer: Box<dyn Signer> = Box::new(LocalSigner::new());
+        let (definition, _) = Issuer::create_credential_definition(
+            &EXAMPLE_DID_1,
+            &ISSUER_DID,
+            &schema,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        let (mut rev_reg_def, _, _) = Issuer::create_revocation_registry_definition(
+            &EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID,
+            &definition,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+            42,
+        )
+        .await?;
+
+        let mut registries = vec![serde_json::to_string(&rev_reg_def.registry)?];
+        for revocation_id in 1..=2 {
+            rev_reg_def = Issuer::revoke_credential(
+                &ISSUER_DID,
+                &rev_reg_def,
+                revocation_id,
+                &ISSUER_PUBLIC_KEY_DID,
+                &ISSUER_PRIVATE_KEY,
+                &signer,
+            )
+            .await?;
+            registries.push(serde_json::to_string(&rev_reg_def.registry)?);
+        }
+
+        for (version, registry) in registries.iter().enumerate() {
+            let rebuilt =
+                Verifier::get_revocation_registry_at_version(&rev_reg_def, version as u64)?;
+            assert_eq!(&serde_json::to_string(&rebuilt)?, registry);
+        }
+        assert!(Verifier::get_revocation_registry_at_version(&rev_reg_def, 3).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_get_revocation_status() -> Result<(), Box<dyn Error>> {
+        let schema: CredentialSchema = serde_json::from_str(&EXAMPLE_CREDENTIAL_SCHEMA)?;
+        let signer: Box<dyn Signer> = Box::new(LocalSigner::new());
+        let (definition, _) = Issuer::create_credential_definition(
+            &EXAMPLE_DID_1,
+            &ISSUER_DID,
+            &schema,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        let (rev_reg_def, _, _) = Issuer::create_revocation_registry_definition(
+            &EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID,
+            &definition,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+            42,
+        )
+        .await?;
+        let rev_reg_def = Issuer::revoke_credential(
+            &ISSUER_DID,
+            &rev_reg_def,
+            1,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+
+        assert_eq!(
+            Verifier::get_revocation_status(&rev_reg_def, 1)?.status,
+            "revoked"
+        );
+        assert_eq!(
+            Verifier::get_revocation_status(&rev_reg_def, 2)?.status,
+            "active"
+        );
+        assert!(Verifier::get_revocation_status(&rev_reg_def, 43).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_check_non_revoked_interval() -> Result<(), Box<dyn Error>> {
+        let schema: CredentialSchema = serde_json::from_str(&EXAMPLE_CREDENTIAL_SCHEMA)?;
+        let signer: Box<dyn Signer> = Box::new(LocalSigner::new());
+        let (definition, _) = Issuer::create_credential_definition(
+            &EXAMPLE_DID_1,
+            &ISSUER_DID,
+            &schema,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        let (rev_reg_def, _, _) = Issuer::create_revocation_registry_definition(
+            &EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID,
+            &definition,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+            42,
+        )
+        .await?;
+        let mut rev_reg_def = Issuer::revoke_credential(
+            &ISSUER_DID,
+            &rev_reg_def,
+            1,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        // version 0 current in [100, 200), version 1 since 200
+        rev_reg_def.delta_history[0].created = 100;
+        rev_reg_def.delta_history[1].created = 200;
+
+        assert!(Verifier::get_revocation_registry_version_at(&rev_reg_def, 99).is_err());
+        assert_eq!(
+            Verifier::get_revocation_registry_version_at(&rev_reg_def, 150)?,
+            0
+        );
+        assert_eq!(
+            Verifier::get_revocation_registry_version_at(&rev_reg_def, 200)?,
+            1
+        );
+
+        let is_in_interval = |version: u64, from: Option<u64>, to: u64| {
+            Verifier::check_non_revoked_interval(
+                &rev_reg_def,
+                version,
+                &NonRevokedInterval { from, to },
+            )
+            .is_ok()
+        };
+        assert!(is_in_interval(0, None, 150));
+        assert!(!is_in_interval(0, None, 250));
+        assert!(is_in_interval(0, Some(150), 250));
+        assert!(!is_in_interval(1, None, 150));
+        assert!(is_in_interval(1, None, 250));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rejects_proofs_against_outdated_registry_versions() -> Result<(), Box<dyn Error>> {
+        let schema: CredentialSchema = serde_json::from_str(&EXAMPLE_CREDENTIAL_SCHEMA)?;
+        let signer: Box<dyn Signer> = Box::new(LocalSigner::new());
+        let (definition, _) = Issuer::create_credential_definition(
+            &EXAMPLE_DID_1,
+            &ISSUER_DID,
+            &schema,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        let (rev_reg_def, _, _) = Issuer::create_revocation_registry_definition(
+            &EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID,
+            &definition,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+            42,
+        )
+        .await?;
+        // credential has been revoked in version 1, proof has been created with version 0
+        let mut rev_reg_def = Issuer::revoke_credential(
+            &ISSUER_DID,
+            &rev_reg_def,
+            1,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        rev_reg_def.delta_history[0].created = 100;
+        rev_reg_def.delta_history[1].created = 200;
+        let rev_reg_def_json = serde_json::to_string(&rev_reg_def)?;
+        let mut presentation = get_presentation_value(&schema.id, &definition.id, &rev_reg_def.id)?;
+        presentation["verifiableCredential"][0]["proof"]["revocationRegistryVersion"] = json!(0);
+        let sub_proof_requests = vec![SubProofRequest {
+            schema: schema.id.to_owned(),
+            revealed_attributes: vec!["test_property_string".to_owned()],
+            restrictions: Vec::new(),
+        }];
+        let schema_id = schema.id.to_owned();
+        let mut definitions = HashMap::new();
+        definitions.insert(schema_id.to_owned(), definition);
+        let mut schemas = HashMap::new();
+        schemas.insert(schema_id.to_owned(), schema);
+        let mut rev_reg_defs = HashMap::new();
+        rev_reg_defs.insert(schema_id.to_owned(), Some(rev_reg_def));
+
+        let proof_request =
+            Verifier::request_proof(&ISSUER_DID, &EXAMPLE_DID_1, sub_proof_requests, None, None)?;
+        let verification = Verifier::verify_proof(
+            serde_json::from_value(presentation.clone())?,
+            proof_request,
+            definitions,
+            schemas,
+            rev_reg_defs,
+            None,
+        );
+        assert_eq!(verification.status, "rejected");
+        assert!(verification
+            .reason
+            .ok_or("no reason given")?
+            .contains("uses revocation registry version 0, latest is 1"));
+
+        // outdated versions are only accepted if requested by the verifier
+        let select = |non_revoked: Option<NonRevokedInterval>,
+                      point_in_time: Option<RevocationPointInTime>|
+         -> Result<bool, Box<dyn Error>> {
+            let proof_request = Verifier::request_proof(
+                &ISSUER_DID,
+                &EXAMPLE_DID_1,
+                Vec::new(),
+                non_revoked,
+                None,
+            )?;
+            let mut rev_reg_defs = HashMap::new();
+            rev_reg_defs.insert(
+                schema_id.to_owned(),
+                Some(serde_json::from_str(&rev_reg_def_json)?),
+            );
+            Ok(Verifier::select_revocation_registries(
+                &serde_json::from_value(presentation.clone())?,
+                &proof_request,
+                &mut rev_reg_defs,
+                point_in_time.as_ref(),
+            )
+            .is_ok())
+        };
+        assert!(select(
+            Some(NonRevokedInterval {
+                from: None,
+                to: 150
+            }),
+            None
+        )?);
+        assert!(!select(
+            Some(NonRevokedInterval {
+                from: None,
+                to: 250
+            }),
+            None
+        )?);
+        assert!(select(
+            None,
+            Some(RevocationPointInTime {
+                timestamp: None,
+                version: Some(0),
+            })
+        )?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rejects_credential_metadata_not_matching_definitions() -> Result<(), Box<dyn Error>> {
+        let schema: CredentialSchema = serde_json::from_str(&EXAMPLE_CREDENTIAL_SCHEMA)?;
+        let signer: Box<dyn Signer> = Box::new(LocalSigner::new());
+        let (definition, _) = Issuer::create_credential_definition(
+            &EXAMPLE_DID_1,
+            &ISSUER_DID,
+            &schema,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        let (rev_reg_def, _, _) = Issuer::create_revocation_registry_definition(
+            &EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID,
+            &definition,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+            42,
+        )
+        .await?;
+        let presentation = get_presentation_value(&schema.id, &definition.id, &rev_reg_def.id)?;
+        // definition of the original schema, given for another schema
+        let other_schema_definition: CredentialDefinition =
+            serde_json::from_str(&serde_json::to_string(&definition)?)?;
+        let mut definitions = HashMap::new();
+        definitions.insert(schema.id.to_owned(), definition);
+        definitions.insert("did:evan:0x0".to_owned(), other_schema_definition);
+        let mut rev_reg_defs = HashMap::new();
+        rev_reg_defs.insert(schema.id.to_owned(), Some(rev_reg_def));
+
+        let check = |path: &str, value: &str| -> Result<String, Box<dyn Error>> {
+            let mut altered = presentation.clone();
+            *altered.pointer_mut(path).ok_or("invalid path")? = Value::from(value);
+            match Verifier::check_credential_metadata(
+                &serde_json::from_value(altered)?,
+                &definitions,
+                &rev_reg_defs,
+            ) {
+                Ok(_) => Ok("".to_owned()),
+                Err(e) => Ok(e.to_string()),
+            }
+        };
+        assert_eq!(check("/id", "presentation")?, "");
+        assert!(
+            check("/verifiableCredential/0/issuer", "did:evan:testcore:0x0")?
+                .contains("claims issuer")
+        );
+        assert!(check(
+            "/verifiableCredential/0/proof/credentialDefinition",
+            "did:evan:0x0"
+        )?
+        .contains("references credential definition"));
+        assert!(check(
+            "/verifiableCredential/0/proof/revocationRegistryDefinition",
+            "did:evan:0x0"
+        )?
+        .contains("references revocation registry definition"));
+        assert!(check(
+            "/verifiableCredential/0/credentialSchema/id",
+            "did:evan:0x0"
+        )?
+        .contains("claims schema"));
+        assert!(check(
+            "/verifiableCredential/0/credentialSchema/id",
+            "did:evan:0x1"
+        )?
+        .contains("no credential definition given"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_presentation_not_answering_proof_request() -> Result<(), Box<dyn Error>> {
+        let presentation = get_presentation_value("did:evan:schema", "did:evan:1", "did:evan:2")?;
+        let sub_proof_request = |schema: &str, attributes: &[&str]| SubProofRequest {
+            schema: schema.to_owned(),
+            revealed_attributes: attributes.iter().map(|a| a.to_string()).collect(),
+            restrictions: Vec::new(),
+        };
+        let check = |sub_proof_requests: Vec<SubProofRequest>| -> Result<String, Box<dyn Error>> {
+            let proof_request = Verifier::request_proof(
+                &ISSUER_DID,
+                &EXAMPLE_DID_1,
+                sub_proof_requests,
+                None,
+                None,
+            )?;
+            match Verifier::check_presentation_structure(
+                &serde_json::from_value(presentation.clone())?,
+                &proof_request,
+            ) {
+                Ok(_) => Ok("".to_owned()),
+                Err(e) => Ok(e.to_string()),
+            }
+        };
+
+        let answered = sub_proof_request("did:evan:schema", &["test_property_string"]);
+        assert_eq!(check(vec![answered])?, "");
+        assert!(check(Vec::new())?.contains("contains 1 credentials, but 0 have been requested"));
+        assert!(check(vec![
+            sub_proof_request("did:evan:schema", &["test_property_string"]),
+            sub_proof_request("did:evan:schema", &["test_property_string"]),
+        ])?
+        .contains("contains 1 credentials, but 2 have been requested"));
+        assert!(check(vec![sub_proof_request(
+            "did:evan:other",
+            &["test_property_string"]
+        )])?
+        .contains("at position 0 has schema did:evan:schema"));
+        assert!(check(vec![sub_proof_request("did:evan:schema", &[])])?
+            .contains("reveals attributes [\"test_property_string\"], but [] have been requested"));
+        assert!(check(vec![sub_proof_request(
+            "did:evan:schema",
+            &["test_property_string", "x"]
+        )])?
+        .contains("reveals attributes"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_credentials_not_matching_restrictions() -> Result<(), Box<dyn Error>> {
+        let presentation = get_presentation_value("did:evan:schema", "did:evan:1", "did:evan:2")?;
+        let check = |restrictions: Vec<CredentialRestriction>| -> Result<String, Box<dyn Error>> {
+            let sub_proof_request = SubProofRequest {
+                schema: "did:evan:schema".to_owned(),
+                revealed_attributes: vec!["test_property_string".to_owned()],
+                restrictions,
+            };
+            let proof_request = Verifier::request_proof(
+                &ISSUER_DID,
+                &EXAMPLE_DID_1,
+                vec![sub_proof_request],
+                None,
+                None,
+            )?;
+            match Verifier::check_restrictions(
+                &serde_json::from_value(presentation.clone())?,
+                &proof_request,
+            ) {
+                Ok(_) => Ok("".to_owned()),
+                Err(e) => Ok(e.to_string()),
+            }
+        };
+        let restriction = |issuer: Option<&str>, definition: Option<&str>| CredentialRestriction {
+            issuer: issuer.map(|i| i.to_owned()),
+            credential_definition: definition.map(|d| d.to_owned()),
+        };
+
+        assert_eq!(check(Vec::new())?, "");
+        assert_eq!(check(vec![restriction(Some(&ISSUER_DID), None)])?, "");
+        assert_eq!(
+            check(vec![
+                restriction(Some("did:evan:other"), None),
+                restriction(Some(&ISSUER_DID), Some("did:evan:1")),
+            ])?,
+            "",
+        );
+        assert!(check(vec![restriction(Some("did:evan:other"), None)])?
+            .contains("does not match any restriction for schema did:evan:schema"));
+        assert!(
+            check(vec![restriction(Some(&ISSUER_DID), Some("did:evan:3"))])?
+                .contains("does not match any restriction")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_expired_proof_requests() -> Result<(), Box<dyn Error>> {
+        let mut proof_request =
+            Verifier::request_proof(&ISSUER_DID, &EXAMPLE_DID_1, Vec::new(), None, None)?;
+        assert!(proof_request.expires_at.is_none());
+        Verifier::check_proof_request_expiry(&proof_request)?;
+
+        proof_request =
+            Verifier::request_proof(&ISSUER_DID, &EXAMPLE_DID_1, Vec::new(), None, Some(3600))?;
+        let expires_at = proof_request.expires_at.as_deref().ok_or("no expiry")?;
+        assert!(!is_date_in_past(expires_at)?);
+        Verifier::check_proof_request_expiry(&proof_request)?;
+
+        proof_request.expires_at = Some("2000-01-01T00:00:00.000Z".to_owned());
+        match Verifier::check_proof_request_expiry(&proof_request) {
+            Ok(_) => Err(Box::from("check should have failed")),
+            Err(e) => {
+                assert_eq!(
+                    e.to_string(),
+                    "proof request has expired at 2000-01-01T00:00:00.000Z",
+                );
+                Ok(())
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn can_audit_answered_proof_requests() -> Result<(), Box<dyn Error>> {
+        let schema: CredentialSchema = serde_json::from_str(&EXAMPLE_CREDENTIAL_SCHEMA)?;
+        let signer: Box<dyn Signer> = Box::new(LocalSigner::new());
+        let (definition, credential_private_key) = Issuer::create_credential_definition(
+            &EXAMPLE_DID_1,
+            &ISSUER_DID,
+            &schema,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        let (mut rev_reg_def, revocation_private_key, revocation_info) =
+            Issuer::create_revocation_registry_definition(
+                &EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID,
+                &definition,
+                &ISSUER_PUBLIC_KEY_DID,
+                &ISSUER_PRIVATE_KEY,
+                &signer,
+                42,
+            )
+            .await?;
+        let master_secret = Prover::create_master_secret()?;
+        let offer = Issuer::offer_credential(
+            &ISSUER_DID,
+            &EXAMPLE_DID_1,
+            &schema.id,
+            &definition.id,
+            None,
+        )?;
+        let mut values = HashMap::new();
+        values.insert("test_property_string".to_owned(), "value".to_owned());
+        let (request, blinding_factors) = Prover::request_credential(
+            serde_json::from_str(&serde_json::to_string(&offer)?)?,
+            serde_json::from_str(&serde_json::to_string(&definition)?)?,
+            serde_json::from_str(&serde_json::to_string(&schema)?)?,
+            serde_json::from_str(&serde_json::to_string(&master_secret)?)?,
+            values,
+        )?;
+        let (mut credential, revocation_state, _) = Issuer::issue_credential(
+            &ISSUER_DID,
+            &EXAMPLE_DID_1,
+            &offer,
+            serde_json::from_str(&serde_json::to_string(&request)?)?,
+            &InMemoryUsedNonceStore::new(),
+            serde_json::from_str(&serde_json::to_string(&definition)?)?,
+            credential_private_key,
+            serde_json::from_str(&serde_json::to_string(&schema)?)?,
+            &mut rev_reg_def,
+            revocation_private_key,
+            &revocation_info,
+            None,
+            None,
+        )?;
+        Prover::post_process_credential_signature(
+            &mut credential,
+            &schema,
+            &request,
+            &definition,
+            blinding_factors,
+            &master_secret,
+            &rev_reg_def,
+            &revocation_state.witness,
+        )?;
+
+        let sub_proof_requests = vec![SubProofRequest {
+            schema: schema.id.to_owned(),
+            revealed_attributes: vec!["test_property_string".to_owned()],
+            restrictions: Vec::new(),
+        }];
+        let mut proof_request = Verifier::request_proof(
+            &ISSUER_DID,
+            &EXAMPLE_DID_1,
+            sub_proof_requests,
+            None,
+            Some(3600),
+        )?;
+        let schema_id = schema.id.to_owned();
+        let mut witnesses = HashMap::new();
+        witnesses.insert(credential.id.to_owned(), revocation_state.witness);
+        let mut credentials = HashMap::new();
+        credentials.insert(schema_id.to_owned(), credential);
+        let mut definitions = HashMap::new();
+        definitions.insert(schema_id.to_owned(), definition);
+        let mut revocation_registries = HashMap::new();
+        revocation_registries.insert(schema_id.to_owned(), rev_reg_def);
+        let mut schemas = HashMap::new();
+        schemas.insert(schema_id.to_owned(), schema);
+        let presentation = serde_json::to_string(&Prover::present_proof(
+            serde_json::from_str(&serde_json::to_string(&proof_request)?)?,
+            credentials,
+            serde_json::from_str(&serde_json::to_string(&definitions)?)?,
+            serde_json::from_str(&serde_json::to_string(&schemas)?)?,
+            serde_json::from_str(&serde_json::to_string(&revocation_registries)?)?,
+            witnesses,
+            &master_secret,
+        )?)?;
+        let rev_reg_def = revocation_registries
+            .remove(&schema_id)
+            .ok_or("registry missing")?;
+
+        let sessions = InMemoryVerifierSessionStore::new();
+        let verify = |proof_request: &ProofRequest,
+                      rev_reg_def: &RevocationRegistryDefinition,
+                      point_in_time: Option<RevocationPointInTime>|
+         -> Result<ProofVerification, Box<dyn Error>> {
+            let mut registries = HashMap::new();
+            registries.insert(
+                schema_id.to_owned(),
+                Some(serde_json::from_str(&serde_json::to_string(rev_reg_def)?)?),
+            );
+            let verification = Verifier::verify_proof(
+                serde_json::from_str(&presentation)?,
+                serde_json::from_str(&serde_json::to_string(proof_request)?)?,
+                serde_json::from_str(&serde_json::to_string(&definitions)?)?,
+                serde_json::from_str(&serde_json::to_string(&schemas)?)?,
+                registries,
+                point_in_time.clone(),
+            );
+            if verification.status == "verified" {
+                Verifier::consume_proof_request(proof_request, point_in_time.as_ref(), &sessions)?;
+            }
+            Ok(verification)
+        };
+        let verification = verify(&proof_request, &rev_reg_def, None)?;
+        assert_eq!(verification.reason, None);
+        match verify(&proof_request, &rev_reg_def, None) {
+            Ok(_) => return Err(Box::from("answered proof request has been accepted again")),
+            Err(e) => assert!(e.to_string().contains("has already been answered")),
+        };
+
+        // credential has been revoked and proof request has expired since, audits still work
+        let rev_reg_def = Issuer::revoke_credential(
+            &ISSUER_DID,
+            &rev_reg_def,
+            revocation_state.revocation_id,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        proof_request.expires_at = Some("2000-01-01T00:00:00.000Z".to_owned());
+        let point_in_time = RevocationPointInTime {
+            timestamp: None,
+            version: Some(0),
+        };
+        for _ in 0..2 {
+            let verification = verify(&proof_request, &rev_reg_def, Some(point_in_time.clone()))?;
+            assert_eq!(verification.reason, None);
+            assert_eq!(verification.status, "verified");
+        }
+        let verification = verify(&proof_request, &rev_reg_def, None)?;
+        assert_eq!(verification.status, "rejected");
+        assert!(verification
+            .reason
+            .ok_or("no reason given")?
+            .contains("proof request has expired"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_verify_presentations_with_proofs_embedded_as_json_strings(
+    ) -> Result<(), Box<dyn Error>> {
+        let schema: CredentialSchema = serde_json::from_str(&EXAMPLE_CREDENTIAL_SCHEMA)?;
+        let signer: Box<dyn Signer> = Box::new(LocalSigner::new());
+        let (definition, credential_private_key) = Issuer::create_credential_definition(
+            &EXAMPLE_DID_1,
+            &ISSUER_DID,
+            &schema,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        let (mut rev_reg_def, revocation_private_key, revocation_info) =
+            Issuer::create_revocation_registry_definition(
+                &EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID,
+                &definition,
+                &ISSUER_PUBLIC_KEY_DID,
+                &ISSUER_PRIVATE_KEY,
+                &signer,
+                42,
+            )
+            .await?;
+        let master_secret = Prover::create_master_secret()?;
+        let offer = Issuer::offer_credential(
+            &ISSUER_DID,
+            &EXAMPLE_DID_1,
+            &schema.id,
+            &definition.id,
+            None,
+        )?;
+        let mut values = HashMap::new();
+        values.insert("test_property_string".to_owned(), "value".to_owned());
+        let (request, blinding_factors) = Prover::request_credential(
+            serde_json::from_str(&serde_json::to_string(&offer)?)?,
+            serde_json::from_str(&serde_json::to_string(&definition)?)?,
+            serde_json::from_str(&serde_json::to_string(&schema)?)?,
+            serde_json::from_str(&serde_json::to_string(&master_secret)?)?,
+            values,
+        )?;
+        let (mut credential, revocation_state, _) = Issuer::issue_credential(
+            &ISSUER_DID,
+            &EXAMPLE_DID_1,
+            &offer,
+            serde_json::from_str(&serde_json::to_string(&request)?)?,
+            &InMemoryUsedNonceStore::new(),
+            serde_json::from_str(&serde_json::to_string(&definition)?)?,
+            credential_private_key,
+            serde_json::from_str(&serde_json::to_string(&schema)?)?,
+            &mut rev_reg_def,
+            revocation_private_key,
+            &revocation_info,
+            None,
+            None,
+        )?;
+        Prover::post_process_credential_signature(
+            &mut credential,
+            &schema,
+            &request,
+            &definition,
+            blinding_factors,
+            &master_secret,
+            &rev_reg_def,
+            &revocation_state.witness,
+        )?;
+
+        let sub_proof_requests = vec![SubProofRequest {
+            schema: schema.id.to_owned(),
+            revealed_attributes: vec!["test_property_string".to_owned()],
+            restrictions: Vec::new(),
+        }];
+        let proof_request =
+            Verifier::request_proof(&ISSUER_DID, &EXAMPLE_DID_1, sub_proof_requests, None, None)?;
+        let schema_id = schema.id.to_owned();
+        let mut witnesses = HashMap::new();
+        witnesses.insert(credential.id.to_owned(), revocation_state.witness);
+        let mut credentials = HashMap::new();
+        credentials.insert(schema_id.to_owned(), credential);
+        let mut definitions = HashMap::new();
+        definitions.insert(schema_id.to_owned(), definition);
+        let mut revocation_registries = HashMap::new();
+        revocation_registries.insert(schema_id.to_owned(), rev_reg_def);
+        let mut schemas = HashMap::new();
+        schemas.insert(schema_id.to_owned(), schema);
+        let presentation = Prover::present_proof(
+            serde_json::from_str(&serde_json::to_string(&proof_request)?)?,
+            credentials,
+            serde_json::from_str(&serde_json::to_string(&definitions)?)?,
+            serde_json::from_str(&serde_json::to_string(&schemas)?)?,
+            serde_json::from_str(&serde_json::to_string(&revocation_registries)?)?,
+            witnesses,
+            &master_secret,
+        )?;
+
+        // presentations of version 1 embedded their proofs as JSON strings, without proof version
+        let mut legacy = serde_json::to_value(&presentation)?;
+        assert_eq!(legacy["proof"]["proofVersion"], PROOF_VERSION);
+        let sub_proof = legacy["verifiableCredential"][0]["proof"]["proof"].take();
+        legacy["verifiableCredential"][0]["proof"]["proof"] = Value::from(sub_proof.to_string());
+        let aggregated_proof = legacy["proof"]["aggregatedProof"].take();
+        legacy["proof"]["aggregatedProof"] = Value::from(aggregated_proof.to_string());
+        legacy["verifiableCredential"][0]["proof"]["proofVersion"].take();
+        legacy["proof"]["proofVersion"].take();
+        let mut registries = HashMap::new();
+        for (schema_id, rev_reg_def) in revocation_registries {
+            registries.insert(schema_id, Some(rev_reg_def));
+        }
+        let verification = Verifier::verify_proof(
+            serde_json::from_value(legacy.clone())?,
+            proof_request,
+            definitions,
+            schemas,
+            registries,
+            None,
+        );
+        assert_eq!(verification.reason, None);
+        assert_eq!(verification.status, "verified");
+
+        // proof versions are not guessed if given
+        let mut versioned = legacy.clone();
+        versioned["proof"]["proofVersion"] = Value::from(1);
+        serde_json::from_value::<ProofPresentation>(versioned.clone())?;
+        versioned["proof"]["proofVersion"] = Value::from(PROOF_VERSION);
+        match serde_json::from_value::<ProofPresentation>(versioned) {
+            Ok(_) => return Err(Box::from("proof of wrong version has been accepted")),
+            Err(e) => assert!(e.to_string().contains("invalid proof;")),
+        };
+
+        // malformed embedded proofs are reported as such
+        legacy["proof"]["aggregatedProof"] = Value::from("{\"c_hash\":");
+        match serde_json::from_value::<ProofPresentation>(legacy) {
+            Ok(_) => Err(Box::from("malformed proof has been accepted")),
+            Err(e) => {
+                assert!(e
+                    .to_string()
+                    .contains("invalid proof embedded as JSON string"));
+                Ok(())
+            }
+        }
+    }
+}
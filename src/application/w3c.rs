@@ -0,0 +1,473 @@
+/*
+  Copyright (c) 2018-present evan GmbH.
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+//! Conversion of credentials and proof presentations from and to JSON-LD documents following the
+//! [W3C Verifiable Credentials Data Model 1.1](https://www.w3.org/TR/vc-data-model/).
+//!
+//! Exported documents reference the CL context next to the W3C context, list claims with their
+//! raw values directly in `credentialSubject`, reference the revocation registry definition in
+//! `credentialStatus` and use `CLSignature2019` as proof type. Encoded claim values are omitted
+//! and recreated with `Prover::encode_values` on import.
+
+use crate::application::{
+    datatypes::{
+        AggregatedProof,
+        Credential,
+        CredentialSignature,
+        CredentialSubProof,
+        CredentialSubject,
+        EncodedCredentialValue,
+        ProofCredential,
+        ProofPresentation,
+        W3cAggregatedProof,
+        W3cCredential,
+        W3cCredentialSignature,
+        W3cCredentialStatus,
+        W3cCredentialSubject,
+        W3cPresentation,
+        W3cProofCredential,
+        W3cSubProof,
+        PROOF_VERSION,
+    },
+    prover::Prover,
+};
+use serde_json::Value;
+use std::{collections::HashMap, error::Error};
+
+/// Base context of all W3C verifiable credentials and presentations
+pub const W3C_CONTEXT: &str = "https://www.w3.org/2018/credentials/v1";
+/// Context defining the CL specific terms of exported credentials and presentations
+pub const CL_CONTEXT: &str = "https://schema.evan.network/zkp/cl/v1";
+/// Proof type of CL signatures and proofs
+pub const CL_SIGNATURE_TYPE: &str = "CLSignature2019";
+/// Type of `credentialStatus` entries
+pub const CL_REVOCATION_STATUS_TYPE: &str = "CLRevocationRegistry2019";
+
+const VERIFIABLE_CREDENTIAL_TYPE: &str = "VerifiableCredential";
+const VERIFIABLE_PRESENTATION_TYPE: &str = "VerifiablePresentation";
+
+/// Converts a credential into a W3C conformant verifiable credential.
+///
+/// # Arguments
+/// * `credential` - Credential to convert
+///
+/// # Returns
+/// * `W3cCredential` - The converted credential
+pub fn credential_to_w3c(credential: Credential) -> W3cCredential {
+    let revocation_registry_definition = credential.proof.revocation_registry_definition;
+    W3cCredential {
+        context: to_w3c_context(credential.context),
+        id: credential.id,
+        r#type: to_w3c_types(credential.r#type, VERIFIABLE_CREDENTIAL_TYPE),
+        issuer: credential.issuer,
+        issuance_date: credential.issuance_date,
+        expiration_date: credential.expiration_date,
+        credential_subject: to_w3c_subject(credential.credential_subject),
+        credential_schema: credential.credential_schema,
+        credential_status: W3cCredentialStatus {
+            id: format!(
+                "{}#{}",
+                &revocation_registry_definition, credential.proof.revocation_id,
+            ),
+            r#type: CL_REVOCATION_STATUS_TYPE.to_owned(),
+            revocation_registry_definition,
+            revocation_id: Some(credential.proof.revocation_id),
+            revocation_registry_version: None,
+        },
+        proof: W3cCredentialSignature {
+            r#type: credential.proof.r#type,
+            credential_definition: credential.proof.credential_definition,
+            signature: credential.proof.signature,
+            signature_correctness_proof: credential.proof.signature_correctness_proof,
+            issuance_nonce: credential.proof.issuance_nonce,
+        },
+    }
+}
+
+/// Converts a W3C verifiable credential created with `credential_to_w3c` back into a credential.
+///
+/// # Arguments
+/// * `credential` - W3C credential to convert
+///
+/// # Returns
+/// * `Credential` - The converted credential
+pub fn credential_from_w3c(credential: W3cCredential) -> Result<Credential, Box<dyn Error>> {
+    check_w3c_document(
+        &credential.id,
+        &credential.context,
+        &credential.r#type,
+        VERIFIABLE_CREDENTIAL_TYPE,
+        &credential.proof.r#type,
+    )?;
+    check_w3c_status(&credential.id, &credential.credential_status)?;
+    let revocation_id = credential.credential_status.revocation_id.ok_or_else(|| {
+        format!(
+            "credential status of credential {} is missing a revocation id",
+            &credential.id,
+        )
+    })?;
+
+    Ok(Credential {
+        context: from_w3c_context(credential.context),
+        id: credential.id,
+        r#type: credential.r#type,
+        issuer: credential.issuer,
+        issuance_date: credential.issuance_date,
+        expiration_date: credential.expiration_date,
+        credential_subject: from_w3c_subject(credential.credential_subject)?,
+        credential_schema: credential.credential_schema,
+        proof: CredentialSignature {
+            r#type: credential.proof.r#type,
+            credential_definition: credential.proof.credential_definition,
+            signature: credential.proof.signature,
+            signature_correctness_proof: credential.proof.signature_correctness_proof,
+            issuance_nonce: credential.proof.issuance_nonce,
+            revocation_id,
+            revocation_registry_definition: credential
+                .credential_status
+                .revocation_registry_definition,
+        },
+    })
+}
+
+/// Converts a proof presentation into a W3C conformant verifiable presentation.
+///
+/// # Arguments
+/// * `presentation` - Presentation to convert
+///
+/// # Returns
+/// * `W3cPresentation` - The converted presentation
+pub fn presentation_to_w3c(presentation: ProofPresentation) -> W3cPresentation {
+    W3cPresentation {
+        context: to_w3c_context(presentation.context),
+        id: presentation.id,
+        r#type: to_w3c_types(presentation.r#type, VERIFIABLE_PRESENTATION_TYPE),
+        verifiable_credential: presentation
+            .verifiable_credential
+            .into_iter()
+            .map(proof_credential_to_w3c)
+            .collect(),
+        proof: W3cAggregatedProof {
+            r#type: CL_SIGNATURE_TYPE.to_owned(),
+            nonce: presentation.proof.nonce,
+            aggregated_proof: presentation.proof.aggregated_proof,
+        },
+        holder_binding: presentation.holder_binding,
+        assertion_proof: presentation.assertion_proof,
+    }
+}
+
+/// Converts a W3C verifiable presentation created with `presentation_to_w3c` back into a proof
+/// presentation.
+///
+/// # Arguments
+/// * `presentation` - W3C presentation to convert
+///
+/// # Returns
+/// * `ProofPresentation` - The converted presentation
+pub fn presentation_from_w3c(
+    presentation: W3cPresentation,
+) -> Result<ProofPresentation, Box<dyn Error>> {
+    check_w3c_document(
+        &presentation.id,
+        &presentation.context,
+        &presentation.r#type,
+        VERIFIABLE_PRESENTATION_TYPE,
+        &presentation.proof.r#type,
+    )?;
+    let mut verifiable_credential: Vec<ProofCredential> = Vec::new();
+    for credential in presentation.verifiable_credential {
+        verifiable_credential.push(proof_credential_from_w3c(credential)?);
+    }
+
+    Ok(ProofPresentation {
+        context: from_w3c_context(presentation.context),
+        id: presentation.id,
+        r#type: presentation.r#type,
+        verifiable_credential,
+        proof: AggregatedProof {
+            nonce: presentation.proof.nonce,
+            proof_version: PROOF_VERSION,
+            aggregated_proof: presentation.proof.aggregated_proof,
+        },
+        holder_binding: presentation.holder_binding,
+        assertion_proof: presentation.assertion_proof,
+    })
+}
+
+/// Checks if a credential or presentation, internal or W3C conformant, is a presentation.
+///
+/// # Arguments
+/// * `document` - Credential or presentation to check
+pub fn is_presentation(document: &Value) -> bool {
+    document["type"].as_array().map_or(false, |types| {
+        types.iter().any(|t| t == VERIFIABLE_PRESENTATION_TYPE)
+    })
+}
+
+fn proof_credential_to_w3c(credential: ProofCredential) -> W3cProofCredential {
+    W3cProofCredential {
+        context: to_w3c_context(credential.context),
+        id: credential.id,
+        r#type: to_w3c_types(credential.r#type, VERIFIABLE_CREDENTIAL_TYPE),
+        issuer: credential.issuer,
+        issuance_date: credential.issuance_date,
+        credential_subject: to_w3c_subject(credential.credential_subject),
+        credential_schema: credential.credential_schema,
+        credential_status: W3cCredentialStatus {
+            id: credential.proof.revocation_registry_definition.to_owned(),
+            r#type: CL_REVOCATION_STATUS_TYPE.to_owned(),
+            revocation_registry_definition: credential.proof.revocation_registry_definition,
+            revocation_id: None,
+            revocation_registry_version: credential.proof.revocation_registry_version,
+        },
+        proof: W3cSubProof {
+            r#type: CL_SIGNATURE_TYPE.to_owned(),
+            credential_definition: credential.proof.credential_definition,
+            proof: credential.proof.proof,
+        },
+    }
+}
+
+fn proof_credential_from_w3c(
+    credential: W3cProofCredential,
+) -> Result<ProofCredential, Box<dyn Error>> {
+    check_w3c_document(
+        &credential.id,
+        &credential.context,
+        &credential.r#type,
+        VERIFIABLE_CREDENTIAL_TYPE,
+        &credential.proof.r#type,
+    )?;
+    check_w3c_status(&credential.id, &credential.credential_status)?;
+
+    Ok(ProofCredential {
+        context: from_w3c_context(credential.context),
+        id: credential.id,
+        r#type: from_w3c_proof_credential_types(credential.r#type),
+        issuer: credential.issuer,
+        issuance_date: credential.issuance_date,
+        credential_subject: from_w3c_subject(credential.credential_subject)?,
+        credential_schema: credential.credential_schema,
+        proof: CredentialSubProof {
+            credential_definition: credential.proof.credential_definition,
+            revocation_registry_definition: credential
+                .credential_status
+                .revocation_registry_definition,
+            revocation_registry_version: credential.credential_status.revocation_registry_version,
+            proof_version: PROOF_VERSION,
+            proof: credential.proof.proof,
+        },
+    })
+}
+
+/// Checks context, type and proof type of an imported W3C document.
+fn check_w3c_document(
+    id: &str,
+    context: &[String],
+    types: &[String],
+    expected_type: &str,
+    proof_type: &str,
+) -> Result<(), Box<dyn Error>> {
+    if context.first().map(|c| c.as_str()) != Some(W3C_CONTEXT) {
+        return Err(Box::from(format!(
+            "{} does not start with context {}",
+            id, W3C_CONTEXT,
+        )));
+    }
+    if !context.iter().any(|c| c == CL_CONTEXT) {
+        return Err(Box::from(format!(
+            "{} is missing context {}",
+            id, CL_CONTEXT
+        )));
+    }
+    if !types.iter().any(|t| t == expected_type) {
+        return Err(Box::from(format!(
+            "{} is not of type {}",
+            id, expected_type
+        )));
+    }
+    if proof_type != CL_SIGNATURE_TYPE {
+        return Err(Box::from(format!(
+            "{} has unsupported proof type {}",
+            id, proof_type,
+        )));
+    }
+
+    Ok(())
+}
+
+fn check_w3c_status(id: &str, status: &W3cCredentialStatus) -> Result<(), Box<dyn Error>> {
+    if status.r#type != CL_REVOCATION_STATUS_TYPE {
+        return Err(Box::from(format!(
+            "{} has unsupported credential status type {}",
+            id, &status.r#type,
+        )));
+    }
+
+    Ok(())
+}
+
+fn to_w3c_context(mut context: Vec<String>) -> Vec<String> {
+    if !context.iter().any(|c| c == CL_CONTEXT) {
+        context.push(CL_CONTEXT.to_owned());
+    }
+    context
+}
+
+fn from_w3c_context(context: Vec<String>) -> Vec<String> {
+    context.into_iter().filter(|c| c != CL_CONTEXT).collect()
+}
+
+/// Adds the type required by the W3C data model if missing. Older versions set the type of
+/// presented credentials to "VerifiablePresentation" only, these types are kept, so assertion
+/// proofs over presentations stay valid after converting them back with `from_w3c_types`.
+fn to_w3c_types(mut types: Vec<String>, expected_type: &str) -> Vec<String> {
+    if !types.iter().any(|t| t == expected_type) {
+        types.insert(0, expected_type.to_owned());
+    }
+    types
+}
+
+/// Removes the type added by `to_w3c_types` to presented credentials of older versions.
+fn from_w3c_proof_credential_types(mut types: Vec<String>) -> Vec<String> {
+    if types.len() > 1
+        && types[0] == VERIFIABLE_CREDENTIAL_TYPE
+        && types.iter().any(|t| t == VERIFIABLE_PRESENTATION_TYPE)
+    {
+        types.remove(0);
+    }
+    types
+}
+
+fn to_w3c_subject(subject: CredentialSubject) -> W3cCredentialSubject {
+    W3cCredentialSubject {
+        id: subject.id,
+        claims: subject
+            .data
+            .into_iter()
+            .map(|(name, value)| (name, Value::from(value.raw)))
+            .collect(),
+    }
+}
+
+/// Converts claims back into credential values, claims that are not strings, e.g. numbers in
+/// documents created by other tools, are used with their JSON representation as raw value.
+fn from_w3c_subject(subject: W3cCredentialSubject) -> Result<CredentialSubject, Box<dyn Error>> {
+    let claims: HashMap<String, String> = subject
+        .claims
+        .into_iter()
+        .map(|(name, value)| match value {
+            Value::String(raw) => (name, raw),
+            other => (name, other.to_string()),
+        })
+        .collect();
+    let data: HashMap<String, EncodedCredentialValue> = Prover::encode_values(claims)?;
+    Ok(CredentialSubject {
+        id: subject.id,
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate utilities;
+
+    use super::*;
+    use serde_json::json;
+    use utilities::test_data::vc_zkp::{EXAMPLE_AGGREGATED_PROOF, EXAMPLE_SUB_PROOF};
+
+    fn get_presentation() -> Result<ProofPresentation, Box<dyn Error>> {
+        let nonce = ursa::cl::new_nonce().map_err(|e| format!("{}", &e))?;
+        Ok(serde_json::from_value(json!({
+            "@context": [W3C_CONTEXT],
+            "id": "presentation",
+            "type": ["VerifiablePresentation"],
+            "verifiableCredential": [{
+                "@context": [W3C_CONTEXT],
+                "id": "credential",
+                "type": ["VerifiablePresentation"],
+                "issuer": "did:evan:issuer",
+                "issuanceDate": "2020-01-01T00:00:00.000Z",
+                "credentialSubject": {
+                    "id": "did:evan:subject",
+                    "data": {
+                        // integers are not hashed, so the encoded value is the raw value
+                        "test_property_string": { "raw": "1234", "encoded": "1234" },
+                    },
+                },
+                "credentialSchema": { "id": "did:evan:schema", "type": "EvanZKPSchema" },
+                "proof": {
+                    "credentialDefinition": "did:evan:definition",
+                    "revocationRegistryDefinition": "did:evan:registry",
+                    "revocationRegistryVersion": 3,
+                    "proof": serde_json::from_str::<Value>(EXAMPLE_SUB_PROOF)?,
+                },
+            }],
+            "proof": {
+                "nonce": nonce,
+                "aggregatedProof": serde_json::from_str::<Value>(EXAMPLE_AGGREGATED_PROOF)?,
+            },
+        }))?)
+    }
+
+    #[test]
+    fn can_convert_presentations_from_and_to_w3c() -> Result<(), Box<dyn Error>> {
+        let presentation = get_presentation()?;
+        let expected = serde_json::to_value(&presentation)?;
+
+        let w3c = serde_json::to_value(&presentation_to_w3c(presentation))?;
+        assert_eq!(w3c["@context"], json!([W3C_CONTEXT, CL_CONTEXT]));
+        let credential = &w3c["verifiableCredential"][0];
+        assert_eq!(
+            credential["type"],
+            json!(["VerifiableCredential", "VerifiablePresentation"]),
+        );
+        assert_eq!(
+            credential["credentialSubject"],
+            json!({ "id": "did:evan:subject", "test_property_string": "1234" }),
+        );
+        assert_eq!(credential["credentialStatus"]["id"], "did:evan:registry");
+        assert_eq!(
+            credential["credentialStatus"]["revocationRegistryVersion"],
+            3
+        );
+        assert_eq!(credential["proof"]["type"], CL_SIGNATURE_TYPE);
+
+        // presented credentials of older versions keep their type, so the envelope is unchanged
+        let imported = presentation_from_w3c(serde_json::from_value(w3c.clone())?)?;
+        assert_eq!(serde_json::to_value(&imported)?, expected);
+
+        // claims given as other JSON values than strings are accepted
+        let mut with_number_claim = w3c.clone();
+        with_number_claim["verifiableCredential"][0]["credentialSubject"]["test_property_string"] =
+            json!(1234);
+        let imported = presentation_from_w3c(serde_json::from_value(with_number_claim)?)?;
+        assert_eq!(serde_json::to_value(&imported)?, expected);
+
+        let mut without_context: Value = w3c;
+        without_context["@context"] = json!([W3C_CONTEXT]);
+        match presentation_from_w3c(serde_json::from_value(without_context)?) {
+            Ok(_) => return Err(Box::from("import should have failed")),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                format!("presentation is missing context {}", CL_CONTEXT),
+            ),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,732 @@
+/*
+  Copyright (c) 2018-present evan GmbH.
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::crypto::{crypto_datatypes::EncryptedData, crypto_utils::PassphraseKey};
+use crate::{
+    application::{
+        datatypes::{
+            Credential,
+            CredentialRequest,
+            CredentialSecretsBlindingFactors,
+            CredentialSelection,
+            HeldCredential,
+            MasterSecret,
+            ProofRequest,
+            RevocationRegistryDefinition,
+            RevocationState,
+        },
+        prover::Prover,
+    },
+    utils::utils::get_timestamp_millis,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, error::Error, sync::Mutex};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+/// Criteria to look up held credentials with, omitted criteria match all credentials.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialFilter {
+    /// DID of the credential's schema
+    #[serde(default)]
+    pub schema: Option<String>,
+    /// DID of the credential's definition
+    #[serde(default)]
+    pub credential_definition: Option<String>,
+    /// DID of the credential's issuer
+    #[serde(default)]
+    pub issuer: Option<String>,
+}
+
+impl CredentialFilter {
+    fn matches(&self, credential: &Credential) -> bool {
+        self.schema
+            .as_ref()
+            .map_or(true, |schema| schema == &credential.credential_schema.id)
+            && self
+                .credential_definition
+                .as_ref()
+                .map_or(true, |definition| {
+                    definition == &credential.proof.credential_definition
+                })
+            && self
+                .issuer
+                .as_ref()
+                .map_or(true, |issuer| issuer == &credential.issuer)
+    }
+}
+
+/// Content of a holder wallet, persisted as a whole by `WalletStorage` implementations.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletContent {
+    /// The holder's master secret
+    #[serde(default)]
+    pub master_secret: Option<MasterSecret>,
+    /// Blinding factors of pending credential requests, referenced by the requests' nonces
+    #[serde(default)]
+    pub blinding_factors: HashMap<String, CredentialSecretsBlindingFactors>,
+    /// Held credentials, referenced by their IDs
+    #[serde(default)]
+    pub credentials: HashMap<String, HeldCredential>,
+}
+
+/// Persistence for the private state of a holder, i.e. master secret, blinding factors of pending
+/// credential requests and received credentials with their revocation states.
+pub trait WalletStorage {
+    /// Loads the wallet's content, returns an empty content if nothing has been stored yet
+    fn load(&self) -> Result<WalletContent, Box<dyn Error>>;
+
+    /// Stores the wallet's content, replacing the existing one
+    fn save(&self, content: &WalletContent) -> Result<(), Box<dyn Error>>;
+
+    /// Loads the wallet's content, changes it with `update` and stores it if `update` succeeds.
+    /// Implementations shared by multiple threads or processes have to guard the whole cycle, so
+    /// concurrent updates do not overwrite each other.
+    fn update(
+        &self,
+        update: Box<dyn FnOnce(&mut WalletContent) -> Result<(), Box<dyn Error>> + '_>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut content = self.load()?;
+        update(&mut content)?;
+        self.save(&content)
+    }
+}
+
+/// `WalletStorage` keeping all data in memory, content is lost when dropped.
+#[derive(Default)]
+pub struct InMemoryWalletStorage {
+    content: Mutex<Option<String>>,
+}
+
+impl InMemoryWalletStorage {
+    pub fn new() -> InMemoryWalletStorage {
+        InMemoryWalletStorage::default()
+    }
+}
+
+impl WalletStorage for InMemoryWalletStorage {
+    fn load(&self) -> Result<WalletContent, Box<dyn Error>> {
+        match &*self
+            .content
+            .lock()
+            .map_err(|e| format!("could not lock wallet storage; {}", &e))?
+        {
+            Some(content) => Ok(serde_json::from_str(content)?),
+            None => Ok(WalletContent::default()),
+        }
+    }
+
+    fn save(&self, content: &WalletContent) -> Result<(), Box<dyn Error>> {
+        *self
+            .content
+            .lock()
+            .map_err(|e| format!("could not lock wallet storage; {}", &e))? =
+            Some(serde_json::to_string(content)?);
+        Ok(())
+    }
+
+    fn update(
+        &self,
+        update: Box<dyn FnOnce(&mut WalletContent) -> Result<(), Box<dyn Error>> + '_>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut stored = self
+            .content
+            .lock()
+            .map_err(|e| format!("could not lock wallet storage; {}", &e))?;
+        let mut content = match &*stored {
+            Some(content) => serde_json::from_str(content)?,
+            None => WalletContent::default(),
+        };
+        update(&mut content)?;
+        *stored = Some(serde_json::to_string(&content)?);
+        Ok(())
+    }
+}
+
+/// `WalletStorage` persisting all data in a file, encrypted with a key derived from a passphrase.
+/// The key is derived once and kept as long as the storage lives, i.e. while the wallet is
+/// unlocked. Updates are guarded by an exclusive OS lock on a lock file next to the wallet file,
+/// so multiple processes can share the same wallet, and the lock is released by the OS if a
+/// process dies while holding it. Content is written to a temporary file first, which then
+/// replaces the wallet file.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct EncryptedFileWalletStorage {
+    path: PathBuf,
+    passphrase: String,
+    key: Mutex<Option<PassphraseKey>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl EncryptedFileWalletStorage {
+    /// Creates a new `EncryptedFileWalletStorage`, the file at `path` is created on first write.
+    ///
+    /// # Arguments
+    /// * `path` - Path of the file to store the wallet in
+    /// * `passphrase` - Passphrase to derive the wallet's encryption key from
+    pub fn new<P: AsRef<Path>>(path: P, passphrase: &str) -> EncryptedFileWalletStorage {
+        EncryptedFileWalletStorage {
+            path: path.as_ref().to_path_buf(),
+            passphrase: passphrase.to_owned(),
+            key: Mutex::new(None),
+        }
+    }
+
+    fn with_extension(&self, extension: &str) -> PathBuf {
+        let mut file_name = self.path.as_os_str().to_owned();
+        file_name.push(extension);
+        PathBuf::from(file_name)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl WalletStorage for EncryptedFileWalletStorage {
+    fn load(&self) -> Result<WalletContent, Box<dyn Error>> {
+        let encrypted: EncryptedData = match fs::read_to_string(&self.path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| format!("could not parse wallet file; {}", &e))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(WalletContent::default()),
+            Err(e) => return Err(Box::from(format!("could not read wallet file; {}", &e))),
+        };
+        let mut key = self
+            .key
+            .lock()
+            .map_err(|e| format!("could not lock wallet key; {}", &e))?;
+        if !key.as_ref().map_or(false, |key| key.matches(&encrypted)) {
+            *key = Some(PassphraseKey::for_encrypted(&self.passphrase, &encrypted)?);
+        }
+        let decrypted = key
+            .as_ref()
+            .ok_or("could not get wallet key")?
+            .decrypt(&encrypted)?;
+
+        Ok(serde_json::from_slice(&decrypted)?)
+    }
+
+    fn save(&self, content: &WalletContent) -> Result<(), Box<dyn Error>> {
+        let mut key = self
+            .key
+            .lock()
+            .map_err(|e| format!("could not lock wallet key; {}", &e))?;
+        if key.is_none() {
+            *key = Some(PassphraseKey::new(&self.passphrase)?);
+        }
+        let encrypted = key
+            .as_ref()
+            .ok_or("could not get wallet key")?
+            .encrypt(&serde_json::to_vec(content)?)?;
+        let temp_path = self.with_extension(".tmp");
+        fs::write(&temp_path, serde_json::to_string(&encrypted)?)
+            .map_err(|e| format!("could not write wallet file; {}", &e))?;
+        fs::rename(&temp_path, &self.path)
+            .map_err(|e| format!("could not replace wallet file; {}", &e))?;
+        Ok(())
+    }
+
+    fn update(
+        &self,
+        update: Box<dyn FnOnce(&mut WalletContent) -> Result<(), Box<dyn Error>> + '_>,
+    ) -> Result<(), Box<dyn Error>> {
+        let lock_file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .open(self.with_extension(".lock"))
+            .map_err(|e| format!("could not open wallet lock file; {}", &e))?;
+        lock_file
+            .lock_exclusive()
+            .map_err(|e| format!("could not lock wallet file; {}", &e))?;
+
+        let result = self.load().and_then(|mut content| {
+            update(&mut content)?;
+            self.save(&content)
+        });
+        lock_file
+            .unlock()
+            .map_err(|e| format!("could not unlock wallet file; {}", &e))?;
+
+        result
+    }
+}
+
+/// Wallet of a holder, keeps the private data needed to request credentials and present proofs.
+pub struct HolderWallet {
+    storage: Box<dyn WalletStorage>,
+}
+
+impl HolderWallet {
+    /// Creates a new `HolderWallet` on top of the given storage.
+    ///
+    /// # Arguments
+    /// * `storage` - Storage to keep the wallet's content in, e.g. `EncryptedFileWalletStorage`
+    pub fn new(storage: Box<dyn WalletStorage>) -> HolderWallet {
+        HolderWallet { storage }
+    }
+
+    /// Stores the holder's master secret, replacing an existing one.
+    pub fn set_master_secret(&self, master_secret: &MasterSecret) -> Result<(), Box<dyn Error>> {
+        let master_secret = serde_json::from_str(&serde_json::to_string(master_secret)?)?;
+        self.storage.update(Box::new(|content| {
+            content.master_secret = Some(master_secret);
+            Ok(())
+        }))
+    }
+
+    /// Gets the holder's master secret.
+    pub fn get_master_secret(&self) -> Result<MasterSecret, Box<dyn Error>> {
+        self.storage
+            .load()?
+            .master_secret
+            .ok_or_else(|| Box::from("no master secret stored in wallet"))
+    }
+
+    /// Stores the blinding factors created along with a credential request until the credential
+    /// has been issued.
+    ///
+    /// # Arguments
+    /// * `credential_request` - The credential request the blinding factors belong to
+    /// * `blinding_factors` - The blinding factors to store
+    pub fn set_blinding_factors(
+        &self,
+        credential_request: &CredentialRequest,
+        blinding_factors: &CredentialSecretsBlindingFactors,
+    ) -> Result<(), Box<dyn Error>> {
+        let key = get_request_key(credential_request)?;
+        let blinding_factors = serde_json::from_str(&serde_json::to_string(blinding_factors)?)?;
+        self.storage.update(Box::new(|content| {
+            content.blinding_factors.insert(key, blinding_factors);
+            Ok(())
+        }))
+    }
+
+    /// Gets the blinding factors of a credential request. They are kept in the wallet until the
+    /// credential has been stored with `store_finished_credential`, so finishing a credential
+    /// can be retried if it fails.
+    ///
+    /// # Arguments
+    /// * `credential_request` - The credential request the blinding factors belong to
+    ///
+    /// # Returns
+    /// * `CredentialSecretsBlindingFactors` - The stored blinding factors
+    pub fn get_blinding_factors(
+        &self,
+        credential_request: &CredentialRequest,
+    ) -> Result<CredentialSecretsBlindingFactors, Box<dyn Error>> {
+        self.storage
+            .load()?
+            .blinding_factors
+            .remove(&get_request_key(credential_request)?)
+            .ok_or_else(|| {
+                Box::from(format!(
+                    "no blinding factors stored in wallet for request for credential definition {}",
+                    &credential_request.credential_definition,
+                ))
+            })
+    }
+
+    /// Stores a finished credential and its revocation state and drops the blinding factors of
+    /// the credential request it has been issued for.
+    ///
+    /// # Arguments
+    /// * `credential_request` - The credential request the credential has been issued for
+    /// * `credential` - The finished credential
+    /// * `revocation_state` - The credential's current revocation state
+    pub fn store_finished_credential(
+        &self,
+        credential_request: &CredentialRequest,
+        credential: Credential,
+        revocation_state: RevocationState,
+    ) -> Result<(), Box<dyn Error>> {
+        let key = get_request_key(credential_request)?;
+        self.storage.update(Box::new(|content| {
+            content.blinding_factors.remove(&key);
+            content.credentials.insert(
+                credential.id.to_owned(),
+                HeldCredential {
+                    credential,
+                    revocation_state,
+                },
+            );
+            Ok(())
+        }))
+    }
+
+    /// Stores a credential and its revocation state, replacing a credential with the same ID.
+    ///
+    /// # Arguments
+    /// * `credential` - The finished credential
+    /// * `revocation_state` - The credential's current revocation state
+    pub fn store_credential(
+        &self,
+        credential: Credential,
+        revocation_state: RevocationState,
+    ) -> Result<(), Box<dyn Error>> {
+        self.storage.update(Box::new(|content| {
+            content.credentials.insert(
+                credential.id.to_owned(),
+                HeldCredential {
+                    credential,
+                    revocation_state,
+                },
+            );
+            Ok(())
+        }))
+    }
+
+    /// Replaces the revocation state of a held credential, e.g. after updating its witness.
+    ///
+    /// # Arguments
+    /// * `revocation_state` - The new revocation state, references its credential by ID
+    pub fn update_revocation_state(
+        &self,
+        revocation_state: RevocationState,
+    ) -> Result<(), Box<dyn Error>> {
+        let credential_id = revocation_state.credential_id.to_owned();
+        self.storage.update(Box::new(|content| {
+            content
+                .credentials
+                .get_mut(&credential_id)
+                .ok_or_else(|| {
+                    format!("no credential with id {} stored in wallet", &credential_id)
+                })?
+                .revocation_state = revocation_state;
+            Ok(())
+        }))
+    }
+
+    /// Removes a credential from the wallet.
+    ///
+    /// # Arguments
+    /// * `credential_id` - ID of the credential to remove
+    pub fn remove_credential(&self, credential_id: &str) -> Result<(), Box<dyn Error>> {
+        self.storage.update(Box::new(|content| {
+            content.credentials.remove(credential_id).ok_or_else(|| {
+                format!("no credential with id {} stored in wallet", credential_id)
+            })?;
+            Ok(())
+        }))
+    }
+
+    /// Gets a held credential by its ID.
+    pub fn get_credential(&self, credential_id: &str) -> Result<HeldCredential, Box<dyn Error>> {
+        self.storage
+            .load()?
+            .credentials
+            .remove(credential_id)
+            .ok_or_else(|| {
+                Box::from(format!(
+                    "no credential with id {} stored in wallet",
+                    credential_id
+                ))
+            })
+    }
+
+    /// Looks up held credentials by schema, credential definition and/or issuer.
+    ///
+    /// # Arguments
+    /// * `filter` - Criteria all returned credentials have to match
+    ///
+    /// # Returns
+    /// * `Vec<HeldCredential>` - Matching credentials, ordered by issuance date
+    pub fn find_credentials(
+        &self,
+        filter: &CredentialFilter,
+    ) -> Result<Vec<HeldCredential>, Box<dyn Error>> {
+        let mut found: Vec<HeldCredential> = self
+            .storage
+            .load()?
+            .credentials
+            .into_iter()
+            .map(|(_, held)| held)
+            .filter(|held| filter.matches(&held.credential))
+            .collect();
+        found.sort_by_cached_key(|held| get_timestamp_millis(&held.credential.issuance_date).ok());
+
+        Ok(found)
+    }
+
+    /// Gets all held credentials with a schema requested by a proof request.
+    ///
+    /// # Arguments
+    /// * `proof_request` - The proof request to get credentials for
+    ///
+    /// # Returns
+    /// * `Vec<HeldCredential>` - Credentials to select from for the proof request
+    pub fn find_credentials_for_proof_request(
+        &self,
+        proof_request: &ProofRequest,
+    ) -> Result<Vec<HeldCredential>, Box<dyn Error>> {
+        let mut found = Vec::new();
+        for sub_proof_request in &proof_request.sub_proof_requests {
+            found.append(&mut self.find_credentials(&CredentialFilter {
+                schema: Some(sub_proof_request.schema.to_owned()),
+                ..Default::default()
+            })?);
+        }
+
+        Ok(found)
+    }
+
+    /// Selects held credentials for a proof request with `Prover::select_credentials` and stores
+    /// the updated revocation states of the selected credentials.
+    ///
+    /// # Arguments
+    /// * `proof_request` - The proof request to answer
+    /// * `revocation_registries` - Revocation registry definitions of the held credentials, indexed by their DIDs
+    ///
+    /// # Returns
+    /// * `CredentialSelection` - Credentials and updated witnesses to present a proof with
+    pub fn select_credentials(
+        &self,
+        proof_request: &ProofRequest,
+        revocation_registries: &HashMap<String, RevocationRegistryDefinition>,
+    ) -> Result<CredentialSelection, Box<dyn Error>> {
+        let selection = Prover::select_credentials(
+            proof_request,
+            self.find_credentials_for_proof_request(proof_request)?,
+            revocation_registries,
+        )?;
+        for revocation_state in selection.revocation_states.values() {
+            self.update_revocation_state(revocation_state.clone())?;
+        }
+
+        Ok(selection)
+    }
+}
+
+fn get_request_key(credential_request: &CredentialRequest) -> Result<String, Box<dyn Error>> {
+    Ok(serde_json::to_string(&credential_request.credential_nonce)?)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate utilities;
+
+    use super::*;
+    use crate::application::{
+        datatypes::{CredentialSchema, SubProofRequest},
+        issuer::Issuer,
+        storage::InMemoryUsedNonceStore,
+        verifier::Verifier,
+    };
+    use utilities::test_data::{
+        accounts::local::{ISSUER_DID, ISSUER_PRIVATE_KEY, ISSUER_PUBLIC_KEY_DID},
+        did::EXAMPLE_DID_1,
+        vc_zkp::{EXAMPLE_CREDENTIAL_SCHEMA, EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID},
+    };
+    use vade_signer::{LocalSigner, Signer};
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn can_store_master_secret_in_encrypted_file() -> Result<(), Box<dyn Error>> {
+        let path = std::env::temp_dir().join(format!(
+            "vade-evan-cl-wallet-{}.json",
+            crate::utils::utils::generate_uuid(),
+        ));
+        let wallet = HolderWallet::new(Box::new(EncryptedFileWalletStorage::new(&path, "secret")));
+        let master_secret = Prover::create_master_secret()?;
+        wallet.set_master_secret(&master_secret)?;
+        let salt = serde_json::from_str::<EncryptedData>(&fs::read_to_string(&path)?)?.salt;
+        wallet.set_master_secret(&master_secret)?;
+        // key is derived once and reused for following writes
+        assert_eq!(
+            serde_json::from_str::<EncryptedData>(&fs::read_to_string(&path)?)?.salt,
+            salt
+        );
+
+        // wallet can only be opened with the same passphrase
+        let wallet = HolderWallet::new(Box::new(EncryptedFileWalletStorage::new(&path, "secret")));
+        assert_eq!(
+            serde_json::to_string(&wallet.get_master_secret()?)?,
+            serde_json::to_string(&master_secret)?,
+        );
+        let wallet = HolderWallet::new(Box::new(EncryptedFileWalletStorage::new(&path, "wrong")));
+        assert!(wallet.get_master_secret().is_err());
+
+        fs::remove_file(&path)?;
+
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn locks_encrypted_file_wallet_while_updating() -> Result<(), Box<dyn Error>> {
+        let path = std::env::temp_dir().join(format!(
+            "vade-evan-cl-wallet-{}.json",
+            crate::utils::utils::generate_uuid(),
+        ));
+        let storage = EncryptedFileWalletStorage::new(&path, "secret");
+        let master_secret = Prover::create_master_secret()?;
+        let mut other_update = None;
+        storage.update(Box::new(|content| {
+            // another process updating the same wallet has to wait until this update is saved
+            let other_path = path.clone();
+            other_update = Some(std::thread::spawn(move || {
+                EncryptedFileWalletStorage::new(&other_path, "secret")
+                    .update(Box::new(|content| {
+                        content
+                            .master_secret
+                            .as_ref()
+                            .map(|_| ())
+                            .ok_or_else(|| Box::from("concurrent update has been lost"))
+                    }))
+                    .map_err(|e| e.to_string())
+            }));
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            content.master_secret = Some(master_secret);
+            Ok(())
+        }))?;
+        other_update
+            .ok_or("other update has not been started")?
+            .join()
+            .map_err(|_| "other update has panicked")??;
+
+        fs::remove_file(&path)?;
+        fs::remove_file(storage.with_extension(".lock"))?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn can_find_and_select_credentials() -> Result<(), Box<dyn Error>> {
+        let schema: CredentialSchema = serde_json::from_str(&EXAMPLE_CREDENTIAL_SCHEMA)?;
+        let signer: Box<dyn Signer> = Box::new(LocalSigner::new());
+        let (definition, credential_private_key) = Issuer::create_credential_definition(
+            &EXAMPLE_DID_1,
+            &ISSUER_DID,
+            &schema,
+            &ISSUER_PUBLIC_KEY_DID,
+            &ISSUER_PRIVATE_KEY,
+            &signer,
+        )
+        .await?;
+        let (mut rev_reg_def, revocation_private_key, revocation_info) =
+            Issuer::create_revocation_registry_definition(
+                &EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID,
+                &definition,
+                &ISSUER_PUBLIC_KEY_DID,
+                &ISSUER_PRIVATE_KEY,
+                &signer,
+                42,
+            )
+            .await?;
+
+        let wallet = HolderWallet::new(Box::new(InMemoryWalletStorage::new()));
+        wallet.set_master_secret(&Prover::create_master_secret()?)?;
+        let used_nonces = InMemoryUsedNonceStore::new();
+        let offer = Issuer::offer_credential(
+            &ISSUER_DID,
+            &EXAMPLE_DID_1,
+            &schema.id,
+            &definition.id,
+            None,
+        )?;
+        let mut values = HashMap::new();
+        values.insert("test_property_string".to_owned(), "value".to_owned());
+        let (request, blinding_factors) = Prover::request_credential(
+            serde_json::from_str(&serde_json::to_string(&offer)?)?,
+            serde_json::from_str(&serde_json::to_string(&definition)?)?,
+            serde_json::from_str(&serde_json::to_string(&schema)?)?,
+            wallet.get_master_secret()?,
+            values,
+        )?;
+        wallet.set_blinding_factors(&request, &blinding_factors)?;
+        let (credential, revocation_state, _) = Issuer::issue_credential(
+            &ISSUER_DID,
+            &EXAMPLE_DID_1,
+            &offer,
+            serde_json::from_str(&serde_json::to_string(&request)?)?,
+            &used_nonces,
+            definition,
+            credential_private_key,
+            schema,
+            &mut rev_reg_def,
+            revocation_private_key,
+            &revocation_info,
+            None,
+            None,
+        )?;
+        // blinding factors are kept until the credential has been stored
+        wallet.get_blinding_factors(&request)?;
+        wallet.get_blinding_factors(&request)?;
+        let credential_id = credential.id.to_owned();
+        wallet.store_finished_credential(&request, credential, revocation_state)?;
+        assert!(wallet.get_blinding_factors(&request).is_err());
+
+        let by_issuer = wallet.find_credentials(&CredentialFilter {
+            issuer: Some(ISSUER_DID.to_owned()),
+            ..Default::default()
+        })?;
+        assert_eq!(by_issuer.len(), 1);
+        assert_eq!(by_issuer[0].credential.id, credential_id);
+        let by_definition = wallet.find_credentials(&CredentialFilter {
+            credential_definition: Some("did:evan:unknown".to_owned()),
+            ..Default::default()
+        })?;
+        assert_eq!(by_definition.len(), 0);
+
+        let schema_did = by_issuer[0].credential.credential_schema.id.to_owned();
+        let sub_proof_request = SubProofRequest {
+            schema: schema_did.to_owned(),
+            revealed_attributes: vec!["test_property_string".to_owned()],
+            restrictions: Vec::new(),
+        };
+        let proof_request = Verifier::request_proof(
+            &ISSUER_DID,
+            &EXAMPLE_DID_1,
+            vec![sub_proof_request],
+            None,
+            None,
+        )?;
+        let mut revocation_registries = HashMap::new();
+        revocation_registries.insert(rev_reg_def.id.to_owned(), rev_reg_def);
+        let selection = wallet.select_credentials(&proof_request, &revocation_registries)?;
+        assert_eq!(
+            selection
+                .credentials
+                .get(&schema_did)
+                .ok_or("credential not selected")?
+                .id,
+            credential_id,
+        );
+        assert!(selection.witnesses.contains_key(&credential_id));
+
+        let sub_proof_request = SubProofRequest {
+            schema: schema_did.to_owned(),
+            revealed_attributes: vec!["unknown_property".to_owned()],
+            restrictions: Vec::new(),
+        };
+        let proof_request = Verifier::request_proof(
+            &ISSUER_DID,
+            &EXAMPLE_DID_1,
+            vec![sub_proof_request],
+            None,
+            None,
+        )?;
+        assert!(wallet
+            .select_credentials(&proof_request, &revocation_registries)
+            .is_err());
+
+        Ok(())
+    }
+}
@@ -71,3 +71,15 @@ pub struct CryptoRevocationRegistryDefinition {
     pub revocation_public_key: RevocationKeyPublic,
     pub maximum_credential_count: u32,
 }
+
+/// Data encrypted with a key derived from a passphrase, all binary values are base64url encoded.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptedData {
+    pub kdf: String,
+    pub iterations: u32,
+    pub salt: String,
+    pub cipher: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
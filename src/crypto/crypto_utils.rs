@@ -14,14 +14,20 @@
   limitations under the License.
 */
 
-use crate::crypto::crypto_datatypes::AssertionProof;
+use crate::crypto::crypto_datatypes::{AssertionProof, EncryptedData};
 use data_encoding::BASE64URL;
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    pbkdf2,
+    rand::{SecureRandom, SystemRandom},
+};
 use secp256k1::{recover, Message, RecoveryId, Signature};
 use serde::{Deserialize, Serialize};
 use serde_json::{value::RawValue, Value};
 use sha2::{Digest, Sha256};
 use sha3::Keccak256;
-use std::{convert::TryInto, error::Error};
+use std::{collections::HashSet, convert::TryInto, error::Error, num::NonZeroU32};
+use ursa::cl::{RevocationRegistry, RevocationRegistryDelta};
 use vade_signer::Signer;
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -187,6 +193,12 @@ pub fn check_assertion_proof(
 pub fn recover_address_and_data(jwt: &str) -> Result<(String, String), Box<dyn Error>> {
     // jwt text parsing
     let split: Vec<&str> = jwt.split('.').collect();
+    if split.len() != 3 {
+        return Err(Box::from(format!(
+            "invalid jws, expected 3 segments but got {}",
+            split.len()
+        )));
+    }
     let (header, data, signature) = (split[0], split[1], split[2]);
     let header_and_data = format!("{}.{}", header, data);
 
@@ -213,6 +225,12 @@ pub fn recover_address_and_data(jwt: &str) -> Result<(String, String), Box<dyn E
     };
     debug!("signature_decoded {:?}", &signature_decoded);
     debug!("signature_decoded.len {:?}", signature_decoded.len());
+    if signature_decoded.len() < 65 {
+        return Err(Box::from(format!(
+            "invalid jws signature length {}, expected at least 65 bytes",
+            signature_decoded.len()
+        )));
+    }
 
     // create hash of data (including header)
     let mut hasher = Sha256::new();
@@ -249,6 +267,244 @@ pub fn recover_address_and_data(jwt: &str) -> Result<(String, String), Box<dyn E
     Ok((address, data_string))
 }
 
+/// Gets the indices of issued and revoked credentials recorded in a revocation registry delta.
+///
+/// # Arguments
+/// * `delta` - delta to read indices from
+///
+/// # Returns
+/// * `(HashSet<u32>, HashSet<u32>)` - (issued, revoked) tuple
+pub fn get_revocation_delta_indices(
+    delta: &RevocationRegistryDelta,
+) -> Result<(HashSet<u32>, HashSet<u32>), Box<dyn Error>> {
+    // indices are private in Ursa, therefore read them from the serialized delta
+    let serialized = serde_json::to_value(delta)?;
+    let mut indices: Vec<HashSet<u32>> = Vec::new();
+    for field in &["issued", "revoked"] {
+        indices.push(match serialized.get(field) {
+            Some(value) => serde_json::from_value(value.clone())
+                .map_err(|e| format!("could not parse {} indices of delta; {}", field, &e))?,
+            None => HashSet::new(),
+        });
+    }
+    let revoked = indices.pop().ok_or("could not get revoked indices")?;
+    let issued = indices.pop().ok_or("could not get issued indices")?;
+
+    Ok((issued, revoked))
+}
+
+/// Creates a delta containing all changes needed to get from the state described by `from` to the
+/// state described by `to`. Both deltas need to describe all changes since the creation of the registry.
+///
+/// # Arguments
+/// * `from` - delta with all changes already known
+/// * `to` - delta with all changes to catch up with
+///
+/// # Returns
+/// * `RevocationRegistryDelta` - delta that can be used to update a witness from `from` to `to`
+pub fn get_delta_between(
+    from: &RevocationRegistryDelta,
+    to: &RevocationRegistryDelta,
+) -> Result<RevocationRegistryDelta, Box<dyn Error>> {
+    let (from_issued, from_revoked) = get_revocation_delta_indices(from)?;
+    let (to_issued, to_revoked) = get_revocation_delta_indices(to)?;
+    let issued: HashSet<u32> = to_issued.difference(&from_issued).cloned().collect();
+    let revoked: HashSet<u32> = to_revoked.difference(&from_revoked).cloned().collect();
+
+    Ok(RevocationRegistryDelta::from_parts(
+        Some(&RevocationRegistry::from(from.clone())),
+        &RevocationRegistry::from(to.clone()),
+        &issued,
+        &revoked,
+    ))
+}
+
+const ENCRYPTION_KDF: &str = "pbkdf2-sha256";
+const ENCRYPTION_CIPHER: &str = "aes-256-gcm";
+const ENCRYPTION_ITERATIONS: u32 = 100_000;
+const ENCRYPTION_SALT_LEN: usize = 16;
+
+/// Key derived from a passphrase (PBKDF2-SHA256) to encrypt data with (AES-256-GCM).
+/// Deriving a key is slow on purpose, so keys should be kept and reused, e.g. for the lifetime of
+/// an unlocked wallet. All data encrypted with a key shares its salt, every encryption uses a new
+/// random nonce.
+pub struct PassphraseKey {
+    key: LessSafeKey,
+    salt: Vec<u8>,
+    iterations: u32,
+}
+
+impl PassphraseKey {
+    /// Derives a new key with a random salt.
+    ///
+    /// # Arguments
+    /// * `passphrase` - passphrase to derive the key from
+    ///
+    /// # Returns
+    /// * `PassphraseKey` - the derived key
+    pub fn new(passphrase: &str) -> Result<PassphraseKey, Box<dyn Error>> {
+        let mut salt = [0u8; ENCRYPTION_SALT_LEN];
+        SystemRandom::new()
+            .fill(&mut salt)
+            .map_err(|_| "could not generate salt for encryption")?;
+
+        PassphraseKey::derive(passphrase, &salt, ENCRYPTION_ITERATIONS)
+    }
+
+    /// Derives the key data has been encrypted with.
+    ///
+    /// # Arguments
+    /// * `passphrase` - passphrase used for encryption
+    /// * `encrypted` - encrypted data to take salt and key derivation parameters from
+    ///
+    /// # Returns
+    /// * `PassphraseKey` - the derived key
+    pub fn for_encrypted(
+        passphrase: &str,
+        encrypted: &EncryptedData,
+    ) -> Result<PassphraseKey, Box<dyn Error>> {
+        if encrypted.kdf != ENCRYPTION_KDF || encrypted.cipher != ENCRYPTION_CIPHER {
+            return Err(Box::from(format!(
+                "unsupported encryption {} with key derivation {}",
+                &encrypted.cipher, &encrypted.kdf
+            )));
+        }
+        let salt = BASE64URL
+            .decode(encrypted.salt.as_bytes())
+            .map_err(|e| format!("could not decode salt; {}", &e))?;
+
+        PassphraseKey::derive(passphrase, &salt, encrypted.iterations)
+    }
+
+    /// Checks if data has been encrypted with a key derived like this one, i.e. with the same
+    /// salt and parameters. Does not check the passphrase.
+    pub fn matches(&self, encrypted: &EncryptedData) -> bool {
+        encrypted.kdf == ENCRYPTION_KDF
+            && encrypted.cipher == ENCRYPTION_CIPHER
+            && encrypted.iterations == self.iterations
+            && encrypted.salt == BASE64URL.encode(&self.salt)
+    }
+
+    /// Encrypts data with this key.
+    ///
+    /// # Arguments
+    /// * `data` - data to encrypt
+    ///
+    /// # Returns
+    /// * `EncryptedData` - ciphertext with parameters needed to decrypt it again
+    pub fn encrypt(&self, data: &[u8]) -> Result<EncryptedData, Box<dyn Error>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| "could not generate nonce for encryption")?;
+        let mut in_out = data.to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut in_out,
+            )
+            .map_err(|_| "could not encrypt data")?;
+
+        Ok(EncryptedData {
+            kdf: ENCRYPTION_KDF.to_string(),
+            iterations: self.iterations,
+            salt: BASE64URL.encode(&self.salt),
+            cipher: ENCRYPTION_CIPHER.to_string(),
+            nonce: BASE64URL.encode(&nonce),
+            ciphertext: BASE64URL.encode(&in_out),
+        })
+    }
+
+    /// Decrypts data encrypted with this key.
+    ///
+    /// # Arguments
+    /// * `encrypted` - encrypted data
+    ///
+    /// # Returns
+    /// * `Vec<u8>` - decrypted data
+    pub fn decrypt(&self, encrypted: &EncryptedData) -> Result<Vec<u8>, Box<dyn Error>> {
+        if !self.matches(encrypted) {
+            return Err(Box::from(
+                "could not decrypt data, it has been encrypted with another key",
+            ));
+        }
+        let nonce: [u8; NONCE_LEN] = BASE64URL
+            .decode(encrypted.nonce.as_bytes())
+            .map_err(|e| format!("could not decode nonce; {}", &e))?
+            .as_slice()
+            .try_into()
+            .map_err(|_| "invalid nonce length")?;
+        let mut in_out = BASE64URL
+            .decode(encrypted.ciphertext.as_bytes())
+            .map_err(|e| format!("could not decode ciphertext; {}", &e))?;
+        let decrypted = self
+            .key
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut in_out,
+            )
+            .map_err(|_| "could not decrypt data, passphrase may be wrong")?;
+
+        Ok(decrypted.to_vec())
+    }
+
+    fn derive(
+        passphrase: &str,
+        salt: &[u8],
+        iterations: u32,
+    ) -> Result<PassphraseKey, Box<dyn Error>> {
+        let non_zero_iterations = NonZeroU32::new(iterations).ok_or("iterations must not be 0")?;
+        let mut key = [0u8; 32];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            non_zero_iterations,
+            salt,
+            passphrase.as_bytes(),
+            &mut key,
+        );
+        let unbound_key =
+            UnboundKey::new(&AES_256_GCM, &key).map_err(|_| "could not create encryption key")?;
+
+        Ok(PassphraseKey {
+            key: LessSafeKey::new(unbound_key),
+            salt: salt.to_vec(),
+            iterations,
+        })
+    }
+}
+
+/// Encrypts data with a key derived from a passphrase (PBKDF2-SHA256, AES-256-GCM).
+///
+/// # Arguments
+/// * `data` - data to encrypt
+/// * `passphrase` - passphrase to derive encryption key from
+///
+/// # Returns
+/// * `EncryptedData` - ciphertext with parameters needed to decrypt it again
+pub fn encrypt_with_passphrase(
+    data: &[u8],
+    passphrase: &str,
+) -> Result<EncryptedData, Box<dyn Error>> {
+    PassphraseKey::new(passphrase)?.encrypt(data)
+}
+
+/// Decrypts data encrypted with `encrypt_with_passphrase`.
+///
+/// # Arguments
+/// * `encrypted` - encrypted data
+/// * `passphrase` - passphrase used for encryption
+///
+/// # Returns
+/// * `Vec<u8>` - decrypted data
+pub fn decrypt_with_passphrase(
+    encrypted: &EncryptedData,
+    passphrase: &str,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    PassphraseKey::for_encrypted(passphrase, encrypted)?.decrypt(encrypted)
+}
+
 #[cfg(test)]
 mod tests {
     extern crate utilities;
@@ -377,4 +633,27 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn can_encrypt_and_decrypt_with_passphrase() -> Result<(), Box<dyn Error>> {
+        let encrypted = encrypt_with_passphrase(b"secret data", "passphrase")?;
+        assert_ne!(encrypted.ciphertext, BASE64URL.encode(b"secret data"));
+        assert_eq!(
+            decrypt_with_passphrase(&encrypted, "passphrase")?,
+            b"secret data".to_vec()
+        );
+        assert!(decrypt_with_passphrase(&encrypted, "wrong passphrase").is_err());
+
+        // keys can be reused for data sharing their salt
+        let key = PassphraseKey::for_encrypted("passphrase", &encrypted)?;
+        let reencrypted = key.encrypt(b"other data")?;
+        assert_eq!(reencrypted.salt, encrypted.salt);
+        assert_ne!(reencrypted.nonce, encrypted.nonce);
+        assert_eq!(key.decrypt(&reencrypted)?, b"other data".to_vec());
+        assert!(PassphraseKey::new("passphrase")?
+            .decrypt(&encrypted)
+            .is_err());
+
+        Ok(())
+    }
 }
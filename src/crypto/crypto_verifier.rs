@@ -24,6 +24,7 @@ pub mod verifier {
         ProofRequest,
         RevocationRegistryDefinition,
     };
+    use serde_json::json;
     use std::{collections::HashMap, error::Error};
     use ursa::cl::{
         issuer::Issuer as CryptoIssuer,
@@ -144,20 +145,16 @@ pub mod verifier {
                     .map_err(|e| format!("could not add sub proof request; {}", &e))?;
             }
 
-            // Create Ursa proof object
-            let mut sub_proofs: Vec<SubProof> = Vec::new();
-            for vc in &presented_proof.verifiable_credential {
-                sub_proofs.push(serde_json::from_str(&vc.proof.proof)?);
-            }
-            let serialized = format!(
-                r###"{{
-              "proofs": {},
-              "aggregated_proof": {}
-          }}"###,
-                serde_json::to_string(&sub_proofs)?,
-                &presented_proof.proof.aggregated_proof
-            );
-            let ursa_proof: CryptoProof = serde_json::from_str(&serialized)?;
+            // Create Ursa proof object, its aggregated proof can only be set when deserializing
+            let sub_proofs: Vec<&SubProof> = presented_proof
+                .verifiable_credential
+                .iter()
+                .map(|vc| &vc.proof.proof)
+                .collect();
+            let ursa_proof: CryptoProof = serde_json::from_value(json!({
+                "proofs": sub_proofs,
+                "aggregated_proof": &presented_proof.proof.aggregated_proof,
+            }))?;
 
             if proof_verifier
                 .verify(&ursa_proof, &presented_proof.proof.nonce)
@@ -94,4 +94,7 @@ pub(crate) mod crypto;
 pub(crate) mod utils;
 mod vade_evan_cl;
 
-pub use self::vade_evan_cl::*;
+pub use self::{
+    utils::document_cache::{DocumentCacheConfig, DocumentCacheStats},
+    vade_evan_cl::*,
+};
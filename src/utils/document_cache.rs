@@ -0,0 +1,268 @@
+/*
+  Copyright (c) 2018-present evan GmbH.
+
+  Licensed under the Apache License, Version 2.0 (the "License");
+  you may not use this file except in compliance with the License.
+  You may obtain a copy of the License at
+
+      http://www.apache.org/licenses/LICENSE-2.0
+
+  Unless required by applicable law or agreed to in writing, software
+  distributed under the License is distributed on an "AS IS" BASIS,
+  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+  See the License for the specific language governing permissions and
+  limitations under the License.
+*/
+
+use serde::{Deserialize, Serialize};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{collections::HashMap, error::Error};
+#[cfg(target_arch = "wasm32")]
+use wasm_timer::{SystemTime, UNIX_EPOCH};
+
+/// Configuration of the cache for resolved documents.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentCacheConfig {
+    /// Whether resolved documents are cached at all
+    pub enabled: bool,
+    /// Seconds to cache revocation registry definitions and DID documents for, 0 disables caching
+    /// them; schemas and credential definitions do not change and are cached until invalidated.
+    /// Revocation registry definitions used for issuance, revocation and verification are always
+    /// resolved, so they are checked against the latest accumulator
+    pub registry_ttl: u64,
+}
+
+impl Default for DocumentCacheConfig {
+    fn default() -> Self {
+        DocumentCacheConfig {
+            enabled: true,
+            registry_ttl: 0,
+        }
+    }
+}
+
+/// Hit and miss counters of the cache for resolved documents.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentCacheStats {
+    pub hits: u64,
+    /// Number of documents resolved instead of taken from the cache
+    pub misses: u64,
+    pub entries: usize,
+}
+
+/// Describes how long a resolved document stays valid.
+#[derive(Clone, Copy, PartialEq)]
+pub enum DocumentLifetime {
+    /// Document never changes (schemas, credential definitions)
+    Immutable,
+    /// Document is updated over time (revocation registry definitions, DID documents) and may be
+    /// used from the cache within the configured TTL
+    Mutable,
+    /// Document is updated over time and has to be up to date, e.g. revocation registry
+    /// definitions used for issuance, revocation and verification; is always resolved and never
+    /// taken from the cache, but refreshes cached `Mutable` documents
+    Volatile,
+}
+
+struct CacheEntry {
+    document: String,
+    expires_at: Option<u64>,
+}
+
+/// Cache for resolved documents, indexed by their DIDs.
+pub struct DocumentCache {
+    config: DocumentCacheConfig,
+    entries: HashMap<String, CacheEntry>,
+    hits: u64,
+    misses: u64,
+}
+
+impl DocumentCache {
+    pub fn new(config: DocumentCacheConfig) -> DocumentCache {
+        DocumentCache {
+            config,
+            entries: HashMap::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Replaces the cache configuration, drops all cached documents if caching is disabled.
+    pub fn set_config(&mut self, config: DocumentCacheConfig) {
+        if !config.enabled {
+            self.entries.clear();
+        }
+        self.config = config;
+    }
+
+    /// Gets a cached document if it has not expired at `now` (unix timestamp in seconds).
+    /// Every request for a document that is not served from the cache counts as a miss.
+    pub fn get(&mut self, did: &str, lifetime: DocumentLifetime, now: u64) -> Option<String> {
+        if !self.config.enabled || lifetime == DocumentLifetime::Volatile {
+            self.misses += 1;
+            return None;
+        }
+        let expired = match self.entries.get(did) {
+            Some(entry) => entry
+                .expires_at
+                .map_or(false, |expires_at| expires_at <= now),
+            None => {
+                self.misses += 1;
+                return None;
+            }
+        };
+        if expired {
+            self.entries.remove(did);
+            self.misses += 1;
+            return None;
+        }
+        self.hits += 1;
+
+        self.entries.get(did).map(|entry| entry.document.clone())
+    }
+
+    /// Caches a document resolved at `now` (unix timestamp in seconds).
+    pub fn insert(&mut self, did: &str, document: &str, lifetime: DocumentLifetime, now: u64) {
+        let expires_at = match lifetime {
+            DocumentLifetime::Immutable => None,
+            DocumentLifetime::Mutable | DocumentLifetime::Volatile
+                if self.config.registry_ttl > 0 =>
+            {
+                Some(now + self.config.registry_ttl)
+            }
+            DocumentLifetime::Mutable | DocumentLifetime::Volatile => return,
+        };
+        if self.config.enabled {
+            self.entries.insert(
+                did.to_owned(),
+                CacheEntry {
+                    document: document.to_owned(),
+                    expires_at,
+                },
+            );
+        }
+    }
+
+    /// Drops a single cached document.
+    pub fn invalidate(&mut self, did: &str) {
+        self.entries.remove(did);
+    }
+
+    /// Drops all cached documents.
+    pub fn invalidate_all(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn get_stats(&self) -> DocumentCacheStats {
+        DocumentCacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            entries: self.entries.len(),
+        }
+    }
+
+    /// Gets current unix timestamp in seconds to check and set cache expiry with.
+    pub fn now() -> Result<u64, Box<dyn Error>> {
+        Ok(SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| "Error generating unix timestamp for document cache")?
+            .as_secs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caches_immutable_documents_until_invalidated() {
+        let mut cache = DocumentCache::new(DocumentCacheConfig::default());
+        assert_eq!(
+            cache.get("did:evan:schema", DocumentLifetime::Immutable, 100),
+            None
+        );
+        cache.insert("did:evan:schema", "{}", DocumentLifetime::Immutable, 100);
+        assert_eq!(
+            cache.get("did:evan:schema", DocumentLifetime::Immutable, 1_000_000),
+            Some("{}".to_string())
+        );
+
+        cache.invalidate("did:evan:schema");
+        assert_eq!(
+            cache.get("did:evan:schema", DocumentLifetime::Immutable, 1_000_000),
+            None
+        );
+
+        let stats = cache.get_stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.entries, 0);
+    }
+
+    #[test]
+    fn caches_mutable_documents_with_ttl() {
+        let mut cache = DocumentCache::new(DocumentCacheConfig::default());
+        cache.insert("did:evan:registry", "{}", DocumentLifetime::Mutable, 100);
+        assert_eq!(
+            cache.get("did:evan:registry", DocumentLifetime::Mutable, 100),
+            None
+        );
+
+        cache.set_config(DocumentCacheConfig {
+            enabled: true,
+            registry_ttl: 60,
+        });
+        cache.insert("did:evan:registry", "{}", DocumentLifetime::Mutable, 100);
+        assert_eq!(
+            cache.get("did:evan:registry", DocumentLifetime::Mutable, 159),
+            Some("{}".to_string())
+        );
+        assert_eq!(
+            cache.get("did:evan:registry", DocumentLifetime::Mutable, 160),
+            None
+        );
+        assert_eq!(cache.get_stats().entries, 0);
+    }
+
+    #[test]
+    fn does_not_cache_if_disabled() {
+        let mut cache = DocumentCache::new(DocumentCacheConfig {
+            enabled: false,
+            registry_ttl: 60,
+        });
+        cache.insert("did:evan:schema", "{}", DocumentLifetime::Immutable, 100);
+        assert_eq!(
+            cache.get("did:evan:schema", DocumentLifetime::Immutable, 100),
+            None
+        );
+        let stats = cache.get_stats();
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.entries, 0);
+    }
+
+    #[test]
+    fn always_resolves_volatile_documents() {
+        let mut cache = DocumentCache::new(DocumentCacheConfig {
+            enabled: true,
+            registry_ttl: 60,
+        });
+        cache.insert("did:evan:registry", "{}", DocumentLifetime::Mutable, 100);
+        assert_eq!(
+            cache.get("did:evan:registry", DocumentLifetime::Volatile, 100),
+            None
+        );
+
+        // resolved volatile documents refresh the cache for mutable ones
+        cache.insert("did:evan:registry", "[]", DocumentLifetime::Volatile, 100);
+        assert_eq!(
+            cache.get("did:evan:registry", DocumentLifetime::Mutable, 100),
+            Some("[]".to_string())
+        );
+        let stats = cache.get_stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1);
+    }
+}
@@ -14,4 +14,5 @@
   limitations under the License.
 */
 
+pub mod document_cache;
 pub mod utils;
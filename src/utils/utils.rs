@@ -14,6 +14,7 @@
   limitations under the License.
 */
 
+use std::error::Error;
 use uuid::Uuid;
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -26,6 +27,61 @@ pub fn get_now_as_iso_string() -> String {
     return Utc::now().format("%Y-%m-%dT%H:%M:%S.000Z").to_string();
 }
 
+pub fn get_iso_string_in_seconds(seconds: u64) -> String {
+    #[cfg(target_arch = "wasm32")]
+    return js_sys::Date::new(&wasm_bindgen::JsValue::from_f64(
+        js_sys::Date::now() + seconds as f64 * 1000.0,
+    ))
+    .to_iso_string()
+    .to_string()
+    .into();
+    #[cfg(not(target_arch = "wasm32"))]
+    return (Utc::now() + chrono::Duration::seconds(seconds as i64))
+        .format("%Y-%m-%dT%H:%M:%S.000Z")
+        .to_string();
+}
+
+/// Parses an ISO 8601 date string to milliseconds since the unix epoch.
+pub fn get_timestamp_millis(date: &str) -> Result<i64, Box<dyn Error>> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let timestamp = js_sys::Date::parse(date);
+        if timestamp.is_nan() {
+            return Err(Box::from(format!("could not parse date {}", date)));
+        }
+        return Ok(timestamp as i64);
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    return Ok(chrono::DateTime::parse_from_rfc3339(date)
+        .map_err(|e| format!("could not parse date {}; {}", date, e))?
+        .timestamp_millis());
+}
+
+/// Checks if an ISO 8601 date string lies in the past or is the current time.
+pub fn is_date_in_past(date: &str) -> Result<bool, Box<dyn Error>> {
+    Ok(get_timestamp_millis(date)? <= get_timestamp_millis(&get_now_as_iso_string())?)
+}
+
 pub fn generate_uuid() -> String {
     return format!("{}", Uuid::new_v4());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_compare_dates_with_different_formats() -> Result<(), Box<dyn Error>> {
+        assert!(is_date_in_past("2020-01-01T00:00:00Z")?);
+        assert!(is_date_in_past("2020-01-01T01:00:00.123+01:00")?);
+        assert!(!is_date_in_past(&get_iso_string_in_seconds(60))?);
+        assert!(!is_date_in_past("9999-12-31T00:00:00+01:00")?);
+        assert!(
+            get_timestamp_millis("2020-01-01T01:00:00+01:00")?
+                == get_timestamp_millis("2020-01-01T00:00:00.000Z")?
+        );
+        assert!(is_date_in_past("01.01.2020").is_err());
+
+        Ok(())
+    }
+}
@@ -14,35 +14,72 @@
   limitations under the License.
 */
 
-use crate::application::{
-    datatypes::{
-        Credential,
-        CredentialDefinition,
-        CredentialOffer,
-        CredentialPrivateKey,
-        CredentialProposal,
-        CredentialRequest,
-        CredentialSchema,
-        CredentialSecretsBlindingFactors,
-        MasterSecret,
-        ProofPresentation,
-        ProofRequest,
-        ProofVerification,
-        RevocationIdInformation,
-        RevocationKeyPrivate,
-        RevocationRegistryDefinition,
-        RevocationState,
-        SchemaProperty,
-        SubProofRequest,
+use crate::{
+    application::{
+        datatypes::{
+            Credential,
+            CredentialDefinition,
+            CredentialOffer,
+            CredentialPrivateKey,
+            CredentialProposal,
+            CredentialRequest,
+            CredentialSchema,
+            CredentialSecretsBlindingFactors,
+            CredentialSelection,
+            HeldCredential,
+            MasterSecret,
+            MasterSecretBackup,
+            NonRevokedInterval,
+            ProofPresentation,
+            ProofRequest,
+            ProofVerification,
+            RevocationIdInformation,
+            RevocationKeyPrivate,
+            RevocationPointInTime,
+            RevocationRegistryDefinition,
+            RevocationState,
+            SchemaProperty,
+            SubProofRequest,
+        },
+        encoding::{
+            document_from_cbor,
+            document_to_cbor,
+            from_base45,
+            from_base64url,
+            to_base45,
+            to_base64url,
+        },
+        issuer::Issuer,
+        prover::Prover,
+        storage::{
+            InMemoryUsedNonceStore,
+            InMemoryVerifierSessionStore,
+            IssuerStorage,
+            UsedNonceStore,
+            VerifierSessionStore,
+        },
+        verifier::Verifier,
+        w3c::{
+            credential_from_w3c,
+            credential_to_w3c,
+            is_presentation,
+            presentation_from_w3c,
+            presentation_to_w3c,
+        },
+        wallet::{CredentialFilter, HolderWallet},
+    },
+    utils::document_cache::{
+        DocumentCache,
+        DocumentCacheConfig,
+        DocumentCacheStats,
+        DocumentLifetime,
     },
-    issuer::Issuer,
-    prover::Prover,
-    verifier::Verifier,
 };
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::{collections::HashMap, error::Error};
-use ursa::cl::{constants::LARGE_PRIME, helpers::generate_safe_prime, Witness};
+use ursa::cl::{constants::LARGE_PRIME, helpers::generate_safe_prime, Nonce, Witness};
 use vade::{Vade, VadePlugin, VadePluginResultValue};
 use vade_signer::Signer;
 
@@ -58,14 +95,30 @@ macro_rules! parse {
 }
 
 macro_rules! get_document {
-    ($vade:expr, $did:expr, $type_name:expr) => {{
-        debug!("fetching {} with did; {}", $type_name, $did);
-        let resolve_result = $vade.did_resolve($did).await?;
-        let result_str = resolve_result[0]
-            .as_ref()
-            .ok_or_else(|| format!("could not get {} did document", $type_name))?;
+    ($self:expr, $did:expr, $type_name:expr, $lifetime:expr) => {{
+        let result_str = $self.resolve_document($did, $type_name, $lifetime).await?;
         parse!(&result_str, &$type_name)
     }};
+    ($self:expr, $did:expr, $type_name:expr, $lifetime:expr, $documents:expr) => {{
+        match $documents
+            .as_ref()
+            .and_then(|documents| documents.get(AsRef::<str>::as_ref($did)))
+        {
+            Some(document) => {
+                debug!("using inline {} with did; {}", $type_name, $did);
+                let did: &str = AsRef::<str>::as_ref($did);
+                if document.get("id").and_then(|id| id.as_str()) != Some(did) {
+                    return Err(Box::from(format!(
+                        "inline {} given for {} has another id",
+                        $type_name, did,
+                    )));
+                }
+                serde_json::from_value(document.clone())
+                    .map_err(|e| format!("{} when parsing inline {} {}", &e, $type_name, did))?
+            }
+            None => get_document!($self, $did, $type_name, $lifetime),
+        }
+    }};
 }
 
 macro_rules! ignore_unrelated {
@@ -100,6 +153,22 @@ pub struct AuthenticationOptions {
     pub identity: String,
 }
 
+/// API payload to compact the delta history of a revocation registry definition
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactDeltaHistoryPayload {
+    /// DID of the issuer
+    pub issuer: String,
+    /// DID of the revocation registry definition to compact
+    pub revocation_registry_definition: String,
+    /// Number of newest delta history entries to keep uncompacted
+    pub keep_latest: usize,
+    /// DID of the issuer's public key to validate the registry's assertion proof
+    pub issuer_public_key_did: String,
+    /// Secret key to sign the registry with
+    pub issuer_proving_key: String,
+}
+
 /// API payload needed to create a credential definition needed for issuing credentials
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -162,6 +231,100 @@ pub struct CreateRevocationRegistryDefinitionResult {
     pub revocation_registry_definition: RevocationRegistryDefinition,
 }
 
+/// API payload to get the revocation status of a credential, either `credential` or
+/// `revocation_id` and `revocation_registry_definition` have to be given
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetRevocationStatusPayload {
+    /// Credential to get the revocation status for
+    #[serde(default)]
+    pub credential: Option<Credential>,
+    /// Revocation ID of the credential to get the revocation status for
+    #[serde(default)]
+    pub revocation_id: Option<u32>,
+    /// DID of the revocation registry definition the credential belongs to
+    #[serde(default)]
+    pub revocation_registry_definition: Option<String>,
+}
+
+/// API payload for exporting a master secret into an encrypted backup
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportMasterSecretPayload {
+    /// Passphrase to derive the backup's encryption key from
+    pub passphrase: String,
+    /// Master secret to export, taken from the holder wallet if omitted
+    #[serde(default)]
+    pub master_secret: Option<MasterSecret>,
+    /// Credentials to include in the backup together with their revocation states
+    #[serde(default)]
+    pub credentials: Vec<HeldCredential>,
+    /// Whether to include all credentials of the holder wallet in the backup
+    #[serde(default)]
+    pub include_wallet_credentials: bool,
+}
+
+/// API payload for encoding a presentation, proof request or credential as compact text
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodeCompactPayload {
+    /// Presentation, proof request or credential to encode
+    pub document: Value,
+    /// Text encoding of the CBOR encoded document, either "base45" (default) or "base64url"
+    #[serde(default)]
+    pub encoding: Option<String>,
+}
+
+/// API payload for decoding a document encoded with `encode_compact`
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodeCompactPayload {
+    /// Encoded document
+    pub data: String,
+    /// Text encoding of the CBOR encoded document, either "base45" (default) or "base64url"
+    #[serde(default)]
+    pub encoding: Option<String>,
+}
+
+/// API payload for importing an encrypted master secret backup
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportMasterSecretPayload {
+    /// Backup created with `export_master_secret`
+    pub backup: MasterSecretBackup,
+    /// Passphrase the backup has been exported with
+    pub passphrase: String,
+    /// Whether to store master secret and credentials of the backup in the holder wallet,
+    /// replacing its current master secret
+    #[serde(default)]
+    pub store_in_wallet: bool,
+}
+
+/// API payload for selecting credentials for a proof request
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectCredentialsPayload {
+    /// Proof request sent by a verifier
+    pub proof_request: ProofRequest,
+    /// Credentials to select from together with their revocation states, all credentials of the
+    /// holder wallet are used if omitted
+    #[serde(default)]
+    pub credentials: Option<Vec<HeldCredential>>,
+    /// Pre-resolved revocation registry definitions referenced by their DIDs, documents missing
+    /// here are resolved
+    #[serde(default)]
+    pub documents: Option<HashMap<String, Value>>,
+}
+
+/// API payload to drop documents from the document cache
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidateDocumentCachePayload {
+    /// DID of the document to drop, drops all cached documents if omitted
+    #[serde(default)]
+    pub did: Option<String>,
+}
+
 /// API payload needed to issue a new credential
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -170,18 +333,40 @@ pub struct IssueCredentialPayload {
     pub issuer: String,
     /// Date of issuance
     pub issuance_date: Option<String>,
+    /// Date of expiration, not part of the signed values
+    #[serde(default)]
+    pub expiration_date: Option<String>,
     /// DID of the credential subject
     pub subject: String,
+    /// Credential offer the credential request has been created for, as offers are not signed, it
+    /// has to be taken from the issuer's own records. If an issuer storage is set, the offer
+    /// stored by `vc_zkp_create_credential_offer` is used and this one has to be equal to it or
+    /// omitted, otherwise it is required.
+    #[serde(default)]
+    pub credential_offer: Option<CredentialOffer>,
     /// Credential request sent by the subject
     pub credential_request: CredentialRequest,
     /// DID of the associated revocation definition
     pub credential_revocation_definition: String,
-    /// Key to create the credential signature
-    pub credential_private_key: CredentialPrivateKey,
-    /// Key to make this credential revokable
-    pub revocation_private_key: RevocationKeyPrivate,
-    /// Tracker of current and next revocation IDs to use
-    pub revocation_information: RevocationIdInformation,
+    /// Key to create the credential signature, loaded from issuer storage if omitted
+    #[serde(default)]
+    pub credential_private_key: Option<CredentialPrivateKey>,
+    /// ID of the credential private key in issuer storage, defaults to the credential definition's DID
+    #[serde(default)]
+    pub credential_private_key_id: Option<String>,
+    /// Key to make this credential revokable, loaded from issuer storage if omitted
+    #[serde(default)]
+    pub revocation_private_key: Option<RevocationKeyPrivate>,
+    /// ID of the revocation private key in issuer storage, defaults to the revocation definition's DID
+    #[serde(default)]
+    pub revocation_private_key_id: Option<String>,
+    /// Tracker of current and next revocation IDs to use, revocation ID is allocated with issuer storage if omitted
+    #[serde(default)]
+    pub revocation_information: Option<RevocationIdInformation>,
+    /// Pre-resolved documents (schemas, credential definitions and revocation registry definitions)
+    /// referenced by their DIDs, documents missing here are resolved
+    #[serde(default)]
+    pub documents: Option<HashMap<String, Value>>,
 }
 
 /// API payload needed to finish a blinded credential signature by a holder/subject
@@ -194,12 +379,19 @@ pub struct FinishCredentialPayload {
     pub credential_request: CredentialRequest,
     /// DID of the revocation registry definition
     pub credential_revocation_definition: String,
-    /// Blinding factors created during credential request creation
-    pub blinding_factors: CredentialSecretsBlindingFactors,
-    /// Master secret to incorporate into the signature
-    pub master_secret: MasterSecret,
+    /// Blinding factors created during credential request creation, taken from the holder
+    /// wallet if omitted
+    #[serde(default)]
+    pub blinding_factors: Option<CredentialSecretsBlindingFactors>,
+    /// Master secret to incorporate into the signature, taken from the holder wallet if omitted
+    #[serde(default)]
+    pub master_secret: Option<MasterSecret>,
     /// Current revocation state of the credential
     pub revocation_state: RevocationState,
+    /// Pre-resolved documents (schemas, credential definitions and revocation registry definitions)
+    /// referenced by their DIDs, documents missing here are resolved
+    #[serde(default)]
+    pub documents: Option<HashMap<String, Value>>,
 }
 
 /// Result of a call to issue_credential
@@ -226,6 +418,9 @@ pub struct OfferCredentialPayload {
     pub schema: String,
     /// DID of the credential definition of the credential to be issued
     pub credential_definition: String,
+    /// Number of seconds the offer can be used for issuance, defaults to one day
+    #[serde(default)]
+    pub validity: Option<u64>,
 }
 
 /// API payload for creating proofs
@@ -234,12 +429,28 @@ pub struct OfferCredentialPayload {
 pub struct PresentProofPayload {
     /// Proof request sent by a verifier
     pub proof_request: ProofRequest,
-    /// Map of credentials referenced by their schema DIDs for all of the requested credentials
+    /// Map of credentials referenced by their schema DIDs for all of the requested credentials,
+    /// selected from the holder wallet if omitted
+    #[serde(default)]
     pub credentials: HashMap<String, Credential>,
-    /// All of the updated witnesses referenced by their associated credential's schema DID
+    /// All of the updated witnesses referenced by their associated credential's schema DID,
+    /// taken from the holder wallet if credentials are omitted
+    #[serde(default)]
     pub witnesses: HashMap<String, Witness>,
-    /// The holder's master secret
-    pub master_secret: MasterSecret,
+    /// The holder's master secret, taken from the holder wallet if omitted
+    #[serde(default)]
+    pub master_secret: Option<MasterSecret>,
+    /// DID of the prover's public key to validate the holder binding and the assertion proof with,
+    /// both are added if this and `prover_proving_key` are given
+    #[serde(default)]
+    pub prover_public_key_did: Option<String>,
+    /// Secret key to sign the holder binding and the assertion proof with
+    #[serde(default)]
+    pub prover_proving_key: Option<String>,
+    /// Pre-resolved documents (schemas, credential definitions and revocation registry definitions)
+    /// referenced by their DIDs, documents missing here are resolved
+    #[serde(default)]
+    pub documents: Option<HashMap<String, Value>>,
 }
 
 /// API payload for creating a credential proposal
@@ -262,10 +473,14 @@ pub struct RequestCredentialPayload {
     pub credential_offering: CredentialOffer,
     /// DID of the schema
     pub credential_schema: String,
-    /// The holder's master secret
-    pub master_secret: MasterSecret,
+    /// The holder's master secret, taken from the holder wallet if omitted
+    #[serde(default)]
+    pub master_secret: Option<MasterSecret>,
     /// Key-value pairs to be signed in the credential
     pub credential_values: HashMap<String, String>,
+    /// Proposal previously sent to the issuer, the offering is checked against it if given
+    #[serde(default)]
+    pub credential_proposal: Option<CredentialProposal>,
 }
 
 /// API payload for creationg proof requests as a verifier
@@ -278,6 +493,12 @@ pub struct RequestProofPayload {
     pub prover_did: String,
     /// List of subproof requests, each requiring the proof of one credential signature
     pub sub_proof_requests: Vec<SubProofRequest>,
+    /// Time range in which the proven credentials have to be non-revoked
+    #[serde(default)]
+    pub non_revoked: Option<NonRevokedInterval>,
+    /// Number of seconds the request can be answered for, does not expire if omitted
+    #[serde(default)]
+    pub validity: Option<u64>,
 }
 
 /// API payload to revoke a credential
@@ -304,11 +525,34 @@ pub struct ValidateProofPayload {
     pub presented_proof: ProofPresentation,
     /// Proof request that was sent to the holder/prover
     pub proof_request: ProofRequest,
+    /// Point in time to check non-revocation against, defaults to the latest revocation registry
+    /// version or, if the proof request has a `nonRevoked` interval, the revocation registry
+    /// version the holder/prover has used for the proof, if it has been current in that interval;
+    /// audits presentations if set, i.e. neither rejects expired proof requests nor marks
+    /// proof requests as answered
+    #[serde(default)]
+    pub revocation_point_in_time: Option<RevocationPointInTime>,
+    /// Rejects proofs without a holder binding if set, holder bindings are checked if present
+    #[serde(default)]
+    pub require_holder_binding: bool,
+    /// Rejects proofs without an assertion proof over the presentation if set,
+    /// assertion proofs are checked if present
+    #[serde(default)]
+    pub require_assertion_proof: bool,
+    /// Pre-resolved documents (schemas, credential definitions, revocation registry definitions
+    /// and the prover's DID document) referenced by their DIDs, documents missing here are resolved
+    #[serde(default)]
+    pub documents: Option<HashMap<String, Value>>,
 }
 
 pub struct VadeEvanCl {
     signer: Box<dyn Signer>,
     vade: Vade,
+    document_cache: DocumentCache,
+    issuer_storage: Option<Box<dyn IssuerStorage>>,
+    holder_wallet: Option<HolderWallet>,
+    used_nonces: Box<dyn UsedNonceStore>,
+    verifier_sessions: Box<dyn VerifierSessionStore>,
 }
 
 impl VadeEvanCl {
@@ -317,7 +561,90 @@ impl VadeEvanCl {
         match env_logger::try_init() {
             Ok(_) | Err(_) => (),
         };
-        VadeEvanCl { signer, vade }
+        VadeEvanCl {
+            signer,
+            vade,
+            document_cache: DocumentCache::new(DocumentCacheConfig::default()),
+            issuer_storage: None,
+            holder_wallet: None,
+            used_nonces: Box::new(InMemoryUsedNonceStore::new()),
+            verifier_sessions: Box::new(InMemoryVerifierSessionStore::new()),
+        }
+    }
+
+    /// Sets storage for private keys, revocation ID counters and credential offers of issuers. If
+    /// set, private keys and revocation information of new credential definitions and revocation
+    /// registry definitions and created credential offers are stored in it and
+    /// `vc_zkp_issue_credential` can omit them and reference keys by ID instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `storage` - storage to use, e.g. [`InMemoryIssuerStorage`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/application/storage/struct.InMemoryIssuerStorage.html)
+    pub fn set_issuer_storage(&mut self, storage: Box<dyn IssuerStorage>) {
+        self.issuer_storage = Some(storage);
+    }
+
+    /// Sets a wallet for master secret, blinding factors and credentials of holders. If set,
+    /// these can be omitted in holder payloads, finished credentials are stored in it and
+    /// `vc_zkp_present_proof` selects credentials and updates witnesses from it.
+    ///
+    /// # Arguments
+    ///
+    /// * `wallet` - wallet to use, e.g. a [`HolderWallet`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/application/wallet/struct.HolderWallet.html) on top of an `EncryptedFileWalletStorage`
+    pub fn set_holder_wallet(&mut self, wallet: HolderWallet) {
+        self.holder_wallet = Some(wallet);
+    }
+
+    /// Sets the store, in which nonces of credential offers are marked as used on issuance.
+    /// Defaults to an `InMemoryUsedNonceStore`, a persistent store should be used if issued offers
+    /// have to stay unusable after restarts or if multiple instances issue credentials.
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - store to use, e.g. [`InMemoryUsedNonceStore`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/application/storage/struct.InMemoryUsedNonceStore.html)
+    pub fn set_used_nonce_store(&mut self, store: Box<dyn UsedNonceStore>) {
+        self.used_nonces = store;
+    }
+
+    /// Sets the store, in which nonces of proof requests are marked as consumed after a
+    /// presentation for them has been verified successfully. Defaults to an
+    /// `InMemoryVerifierSessionStore`, a persistent store should be used if answered requests
+    /// have to stay unusable after restarts or if multiple instances verify proofs.
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - store to use, e.g. [`InMemoryVerifierSessionStore`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/application/storage/struct.InMemoryVerifierSessionStore.html)
+    pub fn set_verifier_session_store(&mut self, store: Box<dyn VerifierSessionStore>) {
+        self.verifier_sessions = store;
+    }
+
+    /// Configures caching of resolved schemas, credential definitions and revocation registry definitions.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - new cache configuration
+    pub fn set_document_cache_config(&mut self, config: DocumentCacheConfig) {
+        self.document_cache.set_config(config);
+    }
+
+    /// Drops a cached document or all cached documents.
+    ///
+    /// # Arguments
+    ///
+    /// * `did` - DID of the document to drop, drops all cached documents if omitted
+    pub fn invalidate_document_cache(&mut self, did: Option<&str>) {
+        match did {
+            Some(did) => self.document_cache.invalidate(did),
+            None => self.document_cache.invalidate_all(),
+        };
+    }
+
+    /// Gets hit and miss counters and the number of entries of the document cache.
+    ///
+    /// # Returns
+    /// * `DocumentCacheStats` - current cache statistics
+    pub fn get_document_cache_stats(&self) -> DocumentCacheStats {
+        self.document_cache.get_stats()
     }
 }
 
@@ -336,6 +663,200 @@ impl VadeEvanCl {
             .map_err(|err| Box::from(format!("could not serialize big number; {}", &err)))
     }
 
+    async fn compact_delta_history(
+        &mut self,
+        options: &str,
+        payload: &str,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        let options: AuthenticationOptions = parse!(&options, "options");
+        let payload: CompactDeltaHistoryPayload = parse!(&payload, "payload");
+        let rev_def: RevocationRegistryDefinition = get_document!(
+            self,
+            &payload.revocation_registry_definition,
+            "revocation registry definition",
+            DocumentLifetime::Volatile
+        );
+
+        let compacted_registry = Issuer::compact_delta_history(
+            &payload.issuer,
+            &rev_def,
+            payload.keep_latest,
+            &payload.issuer_public_key_did,
+            &payload.issuer_proving_key,
+            &self.signer,
+        )
+        .await?;
+
+        let serialized = serde_json::to_string(&compacted_registry)?;
+
+        self.set_did_document(
+            &rev_def.id,
+            &serialized,
+            &options.private_key,
+            &options.identity,
+        )
+        .await?;
+
+        Ok(Some(serialized))
+    }
+
+    async fn get_revocation_status(
+        &mut self,
+        payload: &str,
+    ) -> Result<Option<String>, Box<dyn Error>> {
+        let payload: GetRevocationStatusPayload = parse!(&payload, "payload");
+        let (revocation_id, rev_def_did) = match (
+            &payload.credential,
+            payload.revocation_id,
+            &payload.revocation_registry_definition,
+        ) {
+            (Some(credential), _, _) => (
+                credential.proof.revocation_id,
+                credential.proof.revocation_registry_definition.clone(),
+            ),
+            (None, Some(revocation_id), Some(rev_def_did)) => (revocation_id, rev_def_did.clone()),
+            _ => {
+                return Err(Box::from(
+                    "either credential or revocationId and revocationRegistryDefinition needed",
+                ))
+            }
+        };
+        let rev_def: RevocationRegistryDefinition = get_document!(
+            self,
+            &rev_def_did,
+            "revocation registry definition",
+            DocumentLifetime::Volatile
+        );
+
+        let status = Verifier::get_revocation_status(&rev_def, revocation_id)?;
+
+        Ok(Some(serde_json::to_string(&status)?))
+    }
+
+    /// Selects credentials for a proof request from the given credentials or from the holder
+    /// wallet, revocation states of credentials selected from the wallet are updated in it.
+    async fn select_credentials(
+        &mut self,
+        proof_request: &ProofRequest,
+        held_credentials: Option<Vec<HeldCredential>>,
+        documents: &Option<HashMap<String, Value>>,
+    ) -> Result<CredentialSelection, Box<dyn Error>> {
+        let from_wallet = held_credentials.is_none();
+        let held_credentials = match held_credentials {
+            Some(held_credentials) => held_credentials,
+            None => self
+                .get_holder_wallet()?
+                .find_credentials_for_proof_request(proof_request)?,
+        };
+
+        let mut revocation_registries: HashMap<String, RevocationRegistryDefinition> =
+            HashMap::new();
+        for held in &held_credentials {
+            let rev_def_did = &held.credential.proof.revocation_registry_definition;
+            if !revocation_registries.contains_key(rev_def_did) {
+                revocation_registries.insert(
+                    rev_def_did.to_owned(),
+                    get_document!(
+                        self,
+                        rev_def_did,
+                        "revocation definition",
+                        DocumentLifetime::Mutable,
+                        documents
+                    ),
+                );
+            }
+        }
+
+        let selection =
+            Prover::select_credentials(proof_request, held_credentials, &revocation_registries)?;
+        if from_wallet {
+            let wallet = self.get_holder_wallet()?;
+            for revocation_state in selection.revocation_states.values() {
+                wallet.update_revocation_state(revocation_state.clone())?;
+            }
+        }
+
+        Ok(selection)
+    }
+
+    fn export_master_secret(&self, payload: &str) -> Result<Option<String>, Box<dyn Error>> {
+        let payload: ExportMasterSecretPayload = parse!(&payload, "payload");
+        let master_secret = self.get_master_secret(payload.master_secret)?;
+        let mut credentials = payload.credentials;
+        if payload.include_wallet_credentials {
+            credentials.append(
+                &mut self
+                    .get_holder_wallet()?
+                    .find_credentials(&CredentialFilter::default())?,
+            );
+        }
+
+        let backup =
+            Prover::export_master_secret(&master_secret, credentials, &payload.passphrase)?;
+
+        Ok(Some(serde_json::to_string(&backup)?))
+    }
+
+    fn import_master_secret(&self, payload: &str) -> Result<Option<String>, Box<dyn Error>> {
+        let payload: ImportMasterSecretPayload = parse!(&payload, "payload");
+        let content = Prover::import_master_secret(&payload.backup, &payload.passphrase)?;
+        let result = serde_json::to_string(&content)?;
+        if payload.store_in_wallet {
+            let wallet = self.get_holder_wallet()?;
+            wallet.set_master_secret(&content.master_secret)?;
+            for held in content.credentials {
+                wallet.store_credential(held.credential, held.revocation_state)?;
+            }
+        }
+
+        Ok(Some(result))
+    }
+
+    fn encode_compact(payload: &str) -> Result<Option<String>, Box<dyn Error>> {
+        let payload: EncodeCompactPayload = parse!(&payload, "payload");
+        let cbor = document_to_cbor(&payload.document)?;
+        let encoded = match payload.encoding.as_deref() {
+            None | Some("base45") => to_base45(&cbor),
+            Some("base64url") => to_base64url(&cbor),
+            Some(encoding) => return Err(Box::from(format!("unsupported encoding {}", encoding))),
+        };
+
+        Ok(Some(serde_json::to_string(&encoded)?))
+    }
+
+    fn decode_compact(payload: &str) -> Result<Option<String>, Box<dyn Error>> {
+        let payload: DecodeCompactPayload = parse!(&payload, "payload");
+        let cbor = match payload.encoding.as_deref() {
+            None | Some("base45") => from_base45(&payload.data)?,
+            Some("base64url") => from_base64url(&payload.data)?,
+            Some(encoding) => return Err(Box::from(format!("unsupported encoding {}", encoding))),
+        };
+
+        Ok(Some(serde_json::to_string(&document_from_cbor(&cbor)?)?))
+    }
+
+    fn to_w3c(payload: &str) -> Result<Option<String>, Box<dyn Error>> {
+        let document: Value = parse!(&payload, "payload");
+        let result = if is_presentation(&document) {
+            serde_json::to_string(&presentation_to_w3c(serde_json::from_value(document)?))?
+        } else {
+            serde_json::to_string(&credential_to_w3c(serde_json::from_value(document)?))?
+        };
+
+        Ok(Some(result))
+    }
+
+    fn from_w3c(payload: &str) -> Result<Option<String>, Box<dyn Error>> {
+        let document: Value = parse!(&payload, "payload");
+        let result = if is_presentation(&document) {
+            serde_json::to_string(&presentation_from_w3c(serde_json::from_value(document)?)?)?
+        } else {
+            serde_json::to_string(&credential_from_w3c(serde_json::from_value(document)?)?)?
+        };
+
+        Ok(Some(result))
+    }
+
     async fn generate_did(
         &mut self,
         private_key: &str,
@@ -368,6 +889,75 @@ impl VadeEvanCl {
         Ok(generated_did)
     }
 
+    fn get_issuer_storage(&self) -> Result<&dyn IssuerStorage, Box<dyn Error>> {
+        self.issuer_storage
+            .as_deref()
+            .ok_or_else(|| Box::from("no issuer storage set, private state has to be passed"))
+    }
+
+    fn get_credential_offer(
+        &self,
+        nonce: &Nonce,
+        given: Option<CredentialOffer>,
+    ) -> Result<CredentialOffer, Box<dyn Error>> {
+        let storage = match &self.issuer_storage {
+            Some(storage) => storage,
+            None => {
+                return given.ok_or_else(|| {
+                    Box::from("no issuer storage set, credential offer has to be passed")
+                })
+            }
+        };
+        let stored = storage.get_credential_offer(nonce)?;
+        if let Some(given) = given {
+            if serde_json::to_value(&given)? != serde_json::to_value(&stored)? {
+                return Err(Box::from(
+                    "given credential offer does not match the offer stored for its nonce",
+                ));
+            }
+        }
+
+        Ok(stored)
+    }
+
+    fn get_holder_wallet(&self) -> Result<&HolderWallet, Box<dyn Error>> {
+        self.holder_wallet
+            .as_ref()
+            .ok_or_else(|| Box::from("no holder wallet set, private state has to be passed"))
+    }
+
+    fn get_master_secret(
+        &self,
+        master_secret: Option<MasterSecret>,
+    ) -> Result<MasterSecret, Box<dyn Error>> {
+        match master_secret {
+            Some(master_secret) => Ok(master_secret),
+            None => self.get_holder_wallet()?.get_master_secret(),
+        }
+    }
+
+    async fn resolve_document(
+        &mut self,
+        did: &str,
+        type_name: &str,
+        lifetime: DocumentLifetime,
+    ) -> Result<String, Box<dyn Error>> {
+        let now = DocumentCache::now()?;
+        if let Some(document) = self.document_cache.get(did, lifetime, now) {
+            debug!("using cached {} with did; {}", type_name, did);
+            return Ok(document);
+        }
+
+        debug!("fetching {} with did; {}", type_name, did);
+        let resolve_result = self.vade.did_resolve(did).await?;
+        let result_str = resolve_result[0]
+            .as_ref()
+            .ok_or_else(|| format!("could not get {} did document", type_name))?;
+        self.document_cache.insert(did, result_str, lifetime, now);
+
+        Ok(result_str.to_owned())
+    }
+
     async fn set_did_document(
         &mut self,
         did: &str,
@@ -385,6 +975,7 @@ impl VadeEvanCl {
             &private_key, &identity
         );
         let result = self.vade.did_update(&did, &options, &payload).await?;
+        self.document_cache.invalidate(did);
 
         if result.is_empty() {
             return Err(Box::from(
@@ -402,28 +993,104 @@ impl VadePlugin for VadeEvanCl {
     ///
     /// - `create_master_secret` to create new master secrets
     /// - `generate_safe_prime` to generate safe prime numbers for [`vc_zkp_create_credential_definition`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/struct.VadeEvanCl.html#method.vc_zkp_create_credential_definition)
+    /// - `compact_delta_history` to merge old delta history entries of a revocation registry definition into a checkpoint,
+    ///   expects serialized [`AuthenticationOptions`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/struct.AuthenticationOptions.html) as `options`
+    ///   and a serialized [`CompactDeltaHistoryPayload`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/struct.CompactDeltaHistoryPayload.html) as `payload`
+    /// - `get_revocation_status` to check if a credential is "active" or "revoked",
+    ///   expects a serialized [`GetRevocationStatusPayload`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/struct.GetRevocationStatusPayload.html) as `payload`
+    /// - `set_document_cache_config` to configure caching of resolved documents,
+    ///   expects a serialized [`DocumentCacheConfig`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/struct.DocumentCacheConfig.html) as `payload`
+    /// - `invalidate_document_cache` to drop cached documents,
+    ///   expects a serialized [`InvalidateDocumentCachePayload`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/struct.InvalidateDocumentCachePayload.html) or an empty `payload`
+    /// - `get_document_cache_stats` to get hit and miss counters of the document cache as [`DocumentCacheStats`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/struct.DocumentCacheStats.html)
+    /// - `export_master_secret` to export a master secret and optionally credentials into an encrypted backup,
+    ///   expects a serialized [`ExportMasterSecretPayload`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/struct.ExportMasterSecretPayload.html) as `payload`
+    /// - `import_master_secret` to decrypt and check a master secret backup,
+    ///   expects a serialized [`ImportMasterSecretPayload`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/struct.ImportMasterSecretPayload.html) as `payload`
+    /// - `select_credentials` to select credentials and updated witnesses for a proof request,
+    ///   expects a serialized [`SelectCredentialsPayload`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/struct.SelectCredentialsPayload.html) as `payload`
+    /// - `to_w3c` to convert a credential or proof presentation into the form of the W3C Verifiable Credentials Data Model,
+    ///   expects a serialized [`Credential`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/application/datatypes/struct.Credential.html) or [`ProofPresentation`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/application/datatypes/struct.ProofPresentation.html) as `payload`
+    /// - `from_w3c` to convert a credential or proof presentation back from the form of the W3C Verifiable Credentials Data Model,
+    ///   expects a serialized [`W3cCredential`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/application/datatypes/struct.W3cCredential.html) or [`W3cPresentation`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/application/datatypes/struct.W3cPresentation.html) as `payload`
+    /// - `encode_compact` to encode a presentation, proof request or credential as compact text, e.g. for QR codes,
+    ///   expects a serialized [`EncodeCompactPayload`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/struct.EncodeCompactPayload.html) as `payload`
+    /// - `decode_compact` to decode a document encoded with `encode_compact` into its JSON form,
+    ///   expects a serialized [`DecodeCompactPayload`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/struct.DecodeCompactPayload.html) as `payload`
     ///
     /// # Arguments
     ///
     /// * `method` - method to call a function for (e.g. "did:example")
-    /// * `function` - currently supports `generate_safe_prime` and `create_master_secret`
+    /// * `function` - function to call, see list above
     /// * `options` - serialized [`TypeOptions`](https://docs.rs/vade_evan_cl/*/vade_evan_cl/struct.TypeOptions.html)
-    /// * `_payload` - currently not used, so can be left empty
+    /// * `payload` - payload for function, not used by `generate_safe_prime` and `create_master_secret`, so can be left empty for them
     async fn run_custom_function(
         &mut self,
         method: &str,
         function: &str,
         options: &str,
-        _payload: &str,
+        payload: &str,
     ) -> Result<VadePluginResultValue<Option<String>>, Box<dyn Error>> {
         ignore_unrelated!(method, options);
         match function {
+            "compact_delta_history" => Ok(VadePluginResultValue::Success(
+                self.compact_delta_history(options, payload).await?,
+            )),
             "create_master_secret" => Ok(VadePluginResultValue::Success(Some(
                 serde_json::to_string(&Prover::create_master_secret()?)?,
             ))),
+            "decode_compact" => Ok(VadePluginResultValue::Success(VadeEvanCl::decode_compact(
+                payload,
+            )?)),
+            "encode_compact" => Ok(VadePluginResultValue::Success(VadeEvanCl::encode_compact(
+                payload,
+            )?)),
+            "export_master_secret" => Ok(VadePluginResultValue::Success(
+                self.export_master_secret(payload)?,
+            )),
+            "from_w3c" => Ok(VadePluginResultValue::Success(VadeEvanCl::from_w3c(
+                payload,
+            )?)),
             "generate_safe_prime" => Ok(VadePluginResultValue::Success(Some(
                 VadeEvanCl::generate_safe_prime()?,
             ))),
+            "get_document_cache_stats" => Ok(VadePluginResultValue::Success(Some(
+                serde_json::to_string(&self.get_document_cache_stats())?,
+            ))),
+            "get_revocation_status" => Ok(VadePluginResultValue::Success(
+                self.get_revocation_status(payload).await?,
+            )),
+            "import_master_secret" => Ok(VadePluginResultValue::Success(
+                self.import_master_secret(payload)?,
+            )),
+            "invalidate_document_cache" => {
+                let payload: InvalidateDocumentCachePayload = if payload.is_empty() {
+                    InvalidateDocumentCachePayload::default()
+                } else {
+                    parse!(&payload, "payload")
+                };
+                self.invalidate_document_cache(payload.did.as_deref());
+                Ok(VadePluginResultValue::Success(None))
+            }
+            "select_credentials" => {
+                let payload: SelectCredentialsPayload = parse!(&payload, "payload");
+                let selection = self
+                    .select_credentials(
+                        &payload.proof_request,
+                        payload.credentials,
+                        &payload.documents,
+                    )
+                    .await?;
+                Ok(VadePluginResultValue::Success(Some(serde_json::to_string(
+                    &selection,
+                )?)))
+            }
+            "set_document_cache_config" => {
+                let config: DocumentCacheConfig = parse!(&payload, "payload");
+                self.set_document_cache_config(config);
+                Ok(VadePluginResultValue::Success(None))
+            }
+            "to_w3c" => Ok(VadePluginResultValue::Success(VadeEvanCl::to_w3c(payload)?)),
             _ => Ok(VadePluginResultValue::Ignored),
         }
     }
@@ -458,7 +1125,12 @@ impl VadePlugin for VadeEvanCl {
         ignore_unrelated!(method, options);
         let options: AuthenticationOptions = parse!(&options, "options");
         let payload: CreateCredentialDefinitionPayload = parse!(&payload, "payload");
-        let schema: CredentialSchema = get_document!(&mut self.vade, &payload.schema_did, "schema");
+        let schema: CredentialSchema = get_document!(
+            self,
+            &payload.schema_did,
+            "schema",
+            DocumentLifetime::Immutable
+        );
 
         let generated_did = self
             .generate_did(&options.private_key, &options.identity)
@@ -474,6 +1146,10 @@ impl VadePlugin for VadeEvanCl {
         )
         .await?;
 
+        if let Some(storage) = &self.issuer_storage {
+            storage.set_credential_private_key(&generated_did, &pk)?;
+        }
+
         let serialized = serde_json::to_string(&(&definition, &pk))?;
         let serialized_definition = serde_json::to_string(&definition)?;
         self.set_did_document(
@@ -563,9 +1239,10 @@ impl VadePlugin for VadeEvanCl {
         let options: AuthenticationOptions = parse!(&options, "options");
         let payload: CreateRevocationRegistryDefinitionPayload = parse!(&payload, "payload");
         let definition: CredentialDefinition = get_document!(
-            &mut self.vade,
+            self,
             &payload.credential_definition,
-            "credential definition"
+            "credential definition",
+            DocumentLifetime::Immutable
         );
 
         let generated_did = self
@@ -583,6 +1260,11 @@ impl VadePlugin for VadeEvanCl {
             )
             .await?;
 
+        if let Some(storage) = &self.issuer_storage {
+            storage.set_revocation_private_key(&generated_did, &private_key)?;
+            storage.set_revocation_info(&revocation_info)?;
+        }
+
         let serialized_def = serde_json::to_string(&definition)?;
 
         self.set_did_document(
@@ -623,30 +1305,103 @@ impl VadePlugin for VadeEvanCl {
         ignore_unrelated!(method, options);
         let payload: IssueCredentialPayload = parse!(&payload, "payload");
         let definition: CredentialDefinition = get_document!(
-            &mut self.vade,
+            self,
             &payload.credential_request.credential_definition,
-            "credential definition"
+            "credential definition",
+            DocumentLifetime::Immutable,
+            payload.documents
+        );
+        let schema: CredentialSchema = get_document!(
+            self,
+            &definition.schema,
+            "schema",
+            DocumentLifetime::Immutable,
+            payload.documents
         );
-        let schema: CredentialSchema = get_document!(&mut self.vade, &definition.schema, "schema");
         let mut revocation_definition: RevocationRegistryDefinition = get_document!(
-            &mut self.vade,
+            self,
             &payload.credential_revocation_definition,
-            "revocation definition"
+            "revocation definition",
+            DocumentLifetime::Volatile,
+            payload.documents
         );
 
-        let (credential, revocation_state, revocation_info) = Issuer::issue_credential(
-            &payload.issuer,
-            &payload.subject,
-            payload.credential_request,
-            definition,
-            payload.credential_private_key,
-            schema,
-            &mut revocation_definition,
-            payload.revocation_private_key,
-            &payload.revocation_information,
-            payload.issuance_date,
+        let credential_private_key = match payload.credential_private_key {
+            Some(key) => key,
+            None => self.get_issuer_storage()?.get_credential_private_key(
+                payload
+                    .credential_private_key_id
+                    .as_deref()
+                    .unwrap_or(&definition.id),
+            )?,
+        };
+        let revocation_private_key = match payload.revocation_private_key {
+            Some(key) => key,
+            None => self.get_issuer_storage()?.get_revocation_private_key(
+                payload
+                    .revocation_private_key_id
+                    .as_deref()
+                    .unwrap_or(&revocation_definition.id),
+            )?,
+        };
+
+        let credential_offer = self.get_credential_offer(
+            &payload.credential_request.credential_nonce,
+            payload.credential_offer,
         )?;
 
+        let (credential, revocation_state, revocation_info) = match payload.revocation_information {
+            Some(revocation_information) => Issuer::issue_credential(
+                &payload.issuer,
+                &payload.subject,
+                &credential_offer,
+                payload.credential_request,
+                self.used_nonces.as_ref(),
+                definition,
+                credential_private_key,
+                schema,
+                &mut revocation_definition,
+                revocation_private_key,
+                &revocation_information,
+                payload.issuance_date,
+                payload.expiration_date,
+            )?,
+            None => {
+                let storage = self.get_issuer_storage()?;
+                Issuer::accept_credential_request(
+                    &payload.issuer,
+                    &payload.subject,
+                    &credential_offer,
+                    &payload.credential_request,
+                    self.used_nonces.as_ref(),
+                    &definition,
+                    &schema,
+                )?;
+                let revocation_id = storage.allocate_revocation_id(
+                    &revocation_definition.id,
+                    revocation_definition.maximum_credential_count,
+                )?;
+                let (credential, revocation_state) = Issuer::issue_credential_with_revocation_id(
+                    &payload.issuer,
+                    &payload.subject,
+                    payload.credential_request,
+                    definition,
+                    credential_private_key,
+                    schema,
+                    &mut revocation_definition,
+                    revocation_private_key,
+                    revocation_id,
+                    payload.issuance_date,
+                    payload.expiration_date,
+                )?;
+                let revocation_info = storage.get_revocation_info(&revocation_definition.id)?;
+                (credential, revocation_state, revocation_info)
+            }
+        };
+        if let Some(storage) = &self.issuer_storage {
+            storage.remove_credential_offer(&credential_offer.nonce)?;
+        }
+
         Ok(VadePluginResultValue::Success(Some(serde_json::to_string(
             &IssueCredentialResult {
                 credential,
@@ -684,20 +1439,39 @@ impl VadePlugin for VadeEvanCl {
             blinding_factors,
             master_secret,
             revocation_state,
+            documents,
         } = payload;
 
         let definition: CredentialDefinition = get_document!(
-            &mut self.vade,
+            self,
             &credential_request.credential_definition,
-            "credential definition"
+            "credential definition",
+            DocumentLifetime::Immutable,
+            documents
+        );
+        let schema: CredentialSchema = get_document!(
+            self,
+            &definition.schema,
+            "schema",
+            DocumentLifetime::Immutable,
+            documents
         );
-        let schema: CredentialSchema = get_document!(&mut self.vade, &definition.schema, "schema");
         let revocation_definition: RevocationRegistryDefinition = get_document!(
-            &mut self.vade,
+            self,
             &credential_revocation_definition,
-            "revocation definition"
+            "revocation definition",
+            DocumentLifetime::Mutable,
+            documents
         );
 
+        let blinding_factors = match blinding_factors {
+            Some(blinding_factors) => blinding_factors,
+            None => self
+                .get_holder_wallet()?
+                .get_blinding_factors(&credential_request)?,
+        };
+        let master_secret = self.get_master_secret(master_secret)?;
+
         Prover::post_process_credential_signature(
             &mut credential,
             &schema,
@@ -708,14 +1482,19 @@ impl VadePlugin for VadeEvanCl {
             &revocation_definition,
             &revocation_state.witness,
         )?;
-        Ok(VadePluginResultValue::Success(Some(serde_json::to_string(
-            &credential,
-        )?)))
+        let result = serde_json::to_string(&credential)?;
+        // blinding factors are dropped only after the credential has been stored
+        if let Some(wallet) = &self.holder_wallet {
+            wallet.store_finished_credential(&credential_request, credential, revocation_state)?;
+        }
+
+        Ok(VadePluginResultValue::Success(Some(result)))
     }
 
     /// Creates a `CredentialOffer` message. A `CredentialOffer` is sent by an issuer and is the response
     /// to a `CredentialProposal`. The `CredentialOffer` specifies which schema and definition the issuer
-    /// is capable and willing to use for credential issuance.
+    /// is capable and willing to use for credential issuance. If an issuer storage is set, the offer
+    /// is stored in it until it is used for issuance.
     ///
     /// # Arguments
     ///
@@ -738,7 +1517,11 @@ impl VadePlugin for VadeEvanCl {
             &payload.subject,
             &payload.schema,
             &payload.credential_definition,
+            payload.validity,
         )?;
+        if let Some(storage) = &self.issuer_storage {
+            storage.set_credential_offer(&result)?;
+        }
         Ok(VadePluginResultValue::Success(Some(serde_json::to_string(
             &result,
         )?)))
@@ -763,7 +1546,14 @@ impl VadePlugin for VadeEvanCl {
         payload: &str,
     ) -> Result<VadePluginResultValue<Option<String>>, Box<dyn Error>> {
         ignore_unrelated!(method, options);
-        let payload: PresentProofPayload = parse!(&payload, "payload");
+        let mut payload: PresentProofPayload = parse!(&payload, "payload");
+        if payload.credentials.is_empty() {
+            let selection = self
+                .select_credentials(&payload.proof_request, None, &payload.documents)
+                .await?;
+            payload.credentials = selection.credentials;
+            payload.witnesses = selection.witnesses;
+        }
 
         // Resolve all necessary credential definitions, schemas and registries
         let mut definitions: HashMap<String, CredentialDefinition> = HashMap::new();
@@ -774,7 +1564,13 @@ impl VadePlugin for VadeEvanCl {
             let schema_did = &req.schema;
             schemas.insert(
                 schema_did.clone(),
-                get_document!(&mut self.vade, &schema_did, "schema"),
+                get_document!(
+                    self,
+                    &schema_did,
+                    "schema",
+                    DocumentLifetime::Immutable,
+                    payload.documents
+                ),
             );
 
             let definition_did = payload
@@ -786,7 +1582,13 @@ impl VadePlugin for VadeEvanCl {
                 .clone();
             definitions.insert(
                 schema_did.clone(),
-                get_document!(&mut self.vade, &definition_did, "credential definition"),
+                get_document!(
+                    self,
+                    &definition_did,
+                    "credential definition",
+                    DocumentLifetime::Immutable,
+                    payload.documents
+                ),
             );
 
             // Resolve revocation definition
@@ -799,19 +1601,55 @@ impl VadePlugin for VadeEvanCl {
                 .clone();
             revocation_definitions.insert(
                 schema_did.clone(),
-                get_document!(&mut self.vade, &rev_definition_did, "revocation definition"),
+                get_document!(
+                    self,
+                    &rev_definition_did,
+                    "revocation definition",
+                    DocumentLifetime::Volatile,
+                    payload.documents
+                ),
             );
         }
 
-        let result: ProofPresentation = Prover::present_proof(
+        let master_secret = self.get_master_secret(payload.master_secret)?;
+
+        let proof_request: ProofRequest =
+            serde_json::from_str(&serde_json::to_string(&payload.proof_request)?)?;
+        let mut result: ProofPresentation = Prover::present_proof(
             payload.proof_request,
             payload.credentials,
             definitions,
             schemas,
             revocation_definitions,
             payload.witnesses,
-            &payload.master_secret,
+            &master_secret,
         )?;
+        match (payload.prover_public_key_did, payload.prover_proving_key) {
+            (Some(public_key_did), Some(proving_key)) => {
+                result = Prover::add_holder_binding(
+                    result,
+                    &proof_request,
+                    &public_key_did,
+                    &proving_key,
+                    &self.signer,
+                )
+                .await?;
+                result = Prover::sign_presentation(
+                    result,
+                    &proof_request.prover,
+                    &public_key_did,
+                    &proving_key,
+                    &self.signer,
+                )
+                .await?;
+            }
+            (None, None) => (),
+            _ => {
+                return Err(Box::from(
+                    "proverPublicKeyDid and proverProvingKey are both required for signing",
+                ))
+            }
+        };
 
         Ok(VadePluginResultValue::Success(Some(serde_json::to_string(
             &result,
@@ -867,21 +1705,37 @@ impl VadePlugin for VadeEvanCl {
         ignore_unrelated!(method, options);
         let payload: RequestCredentialPayload = serde_json::from_str(&payload)
             .map_err(|e| format!("{} when parsing payload {}", &e, &payload))?;
+        if let Some(credential_proposal) = &payload.credential_proposal {
+            Prover::check_offer_matches_proposal(
+                &payload.credential_offering,
+                credential_proposal,
+            )?;
+        }
         let definition: CredentialDefinition = get_document!(
-            &mut self.vade,
+            self,
             &payload.credential_offering.credential_definition,
-            "credential definition"
+            "credential definition",
+            DocumentLifetime::Immutable
         );
-        let schema: CredentialSchema =
-            get_document!(&mut self.vade, &payload.credential_schema, "schema");
+        let schema: CredentialSchema = get_document!(
+            self,
+            &payload.credential_schema,
+            "schema",
+            DocumentLifetime::Immutable
+        );
+
+        let master_secret = self.get_master_secret(payload.master_secret)?;
 
         let result = Prover::request_credential(
             payload.credential_offering,
             definition,
             schema,
-            payload.master_secret,
+            master_secret,
             payload.credential_values,
         )?;
+        if let Some(wallet) = &self.holder_wallet {
+            wallet.set_blinding_factors(&result.0, &result.1)?;
+        }
 
         Ok(VadePluginResultValue::Success(Some(serde_json::to_string(
             &result,
@@ -912,6 +1766,8 @@ impl VadePlugin for VadeEvanCl {
             &payload.verifier_did,
             &payload.prover_did,
             payload.sub_proof_requests,
+            payload.non_revoked,
+            payload.validity,
         )?;
 
         Ok(VadePluginResultValue::Success(Some(serde_json::to_string(
@@ -945,9 +1801,10 @@ impl VadePlugin for VadeEvanCl {
         let options: AuthenticationOptions = parse!(&options, "options");
         let payload: RevokeCredentialPayload = parse!(&payload, "payload");
         let rev_def: RevocationRegistryDefinition = get_document!(
-            &mut self.vade,
+            self,
             &payload.revocation_registry_definition,
-            "revocation registry definition"
+            "revocation registry definition",
+            DocumentLifetime::Volatile
         );
 
         let updated_registry = Issuer::revoke_credential(
@@ -1001,7 +1858,13 @@ impl VadePlugin for VadeEvanCl {
             let schema_did = &req.schema;
             schemas.insert(
                 schema_did.clone(),
-                get_document!(&mut self.vade, &schema_did, "schema"),
+                get_document!(
+                    self,
+                    &schema_did,
+                    "schema",
+                    DocumentLifetime::Immutable,
+                    payload.documents
+                ),
             );
         }
 
@@ -1009,23 +1872,84 @@ impl VadePlugin for VadeEvanCl {
             let definition_did = &credential.proof.credential_definition.clone();
             definitions.insert(
                 credential.credential_schema.id.clone(),
-                get_document!(&mut self.vade, definition_did, "credential definition"),
+                get_document!(
+                    self,
+                    definition_did,
+                    "credential definition",
+                    DocumentLifetime::Immutable,
+                    payload.documents
+                ),
             );
 
             let rev_definition_did = &credential.proof.revocation_registry_definition.clone();
             rev_definitions.insert(
                 credential.credential_schema.id.clone(),
-                get_document!(&mut self.vade, &rev_definition_did, "revocation definition"),
+                get_document!(
+                    self,
+                    &rev_definition_did,
+                    "revocation definition",
+                    DocumentLifetime::Volatile,
+                    payload.documents
+                ),
+            );
+        }
+
+        let check_holder_binding =
+            payload.require_holder_binding || payload.presented_proof.holder_binding.is_some();
+        let check_assertion_proof =
+            payload.require_assertion_proof || payload.presented_proof.assertion_proof.is_some();
+        let mut envelope_check: Result<(), Box<dyn Error>> = Ok(());
+        if check_holder_binding || check_assertion_proof {
+            let prover_did_document: Value = get_document!(
+                self,
+                &payload.proof_request.prover,
+                "DID document",
+                DocumentLifetime::Mutable,
+                payload.documents
             );
+            if check_holder_binding {
+                envelope_check = Verifier::check_holder_binding(
+                    &payload.presented_proof,
+                    &payload.proof_request,
+                    &prover_did_document,
+                );
+            }
+            if envelope_check.is_ok() && check_assertion_proof {
+                envelope_check = Verifier::check_presentation_envelope(
+                    &payload.presented_proof,
+                    &payload.proof_request,
+                    &prover_did_document,
+                );
+            }
         }
 
-        let result: ProofVerification = Verifier::verify_proof(
+        let proof_request: ProofRequest =
+            serde_json::from_str(&serde_json::to_string(&payload.proof_request)?)?;
+        let revocation_point_in_time = payload.revocation_point_in_time.clone();
+        let mut result: ProofVerification = Verifier::verify_proof(
             payload.presented_proof,
             payload.proof_request,
             definitions,
             schemas,
             rev_definitions,
+            payload.revocation_point_in_time,
         );
+        if let Err(e) = envelope_check {
+            if result.status == "verified" {
+                result.status = "rejected".to_owned();
+                result.reason = Some(e.to_string());
+            }
+        }
+        if result.status == "verified" {
+            if let Err(e) = Verifier::consume_proof_request(
+                &proof_request,
+                revocation_point_in_time.as_ref(),
+                self.verifier_sessions.as_ref(),
+            ) {
+                result.status = "rejected".to_owned();
+                result.reason = Some(e.to_string());
+            }
+        }
 
         Ok(VadePluginResultValue::Success(Some(serde_json::to_string(
             &result,
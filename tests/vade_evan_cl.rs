@@ -209,6 +209,7 @@ async fn can_issue_credentials() -> Result<(), Box<dyn Error>> {
         &mut vade,
         &definition,
         &credential_private_key,
+        &offer,
         &request,
         &rev_reg_def.private_key,
         &rev_reg_def.revocation_info,
@@ -250,6 +251,7 @@ async fn can_issue_credentials_with_issued_at() -> Result<(), Box<dyn Error>> {
         &mut vade,
         &definition,
         &credential_private_key,
+        &offer,
         &request,
         &rev_reg_def.private_key,
         &rev_reg_def.revocation_info,
@@ -285,6 +287,7 @@ async fn can_finish_credentials() -> Result<(), Box<dyn Error>> {
         &mut vade,
         &definition,
         &credential_private_key,
+        &offer,
         &request,
         &rev_reg_def.private_key,
         &rev_reg_def.revocation_info,
@@ -370,6 +373,7 @@ async fn can_present_proofs() -> Result<(), Box<dyn Error>> {
         &mut vade,
         &definition,
         &credential_private_key,
+        &offer,
         &request,
         &rev_reg_def.private_key,
         &rev_reg_def.revocation_info,
@@ -403,6 +407,10 @@ async fn can_present_proofs() -> Result<(), Box<dyn Error>> {
     assert_eq!(result.r#type.len(), 1);
     assert_eq!(result.r#type[0], "VerifiablePresentation");
     assert_eq!(result.verifiable_credential.len(), 1);
+    assert_eq!(
+        result.verifiable_credential[0].r#type,
+        vec!["VerifiableCredential".to_owned()],
+    );
 
     Ok(())
 }
@@ -428,6 +436,7 @@ async fn can_present_proofs_with_less_properties() -> Result<(), Box<dyn Error>>
         &mut vade,
         &definition,
         &credential_private_key,
+        &offer,
         &request,
         &rev_reg_def.private_key,
         &rev_reg_def.revocation_info,
@@ -487,6 +496,7 @@ async fn can_present_proofs_with_selective_revealed_attributes_and_omitted_optio
         &mut vade,
         &definition,
         &credential_private_key,
+        &offer,
         &request,
         &rev_reg_def.private_key,
         &rev_reg_def.revocation_info,
@@ -582,6 +592,7 @@ async fn can_verify_proof() -> Result<(), Box<dyn Error>> {
         &mut vade,
         &definition,
         &credential_private_key,
+        &offer,
         &request,
         &rev_reg_def.private_key,
         &rev_reg_def.revocation_info,
@@ -617,6 +628,15 @@ async fn can_verify_proof() -> Result<(), Box<dyn Error>> {
     // check results
     assert_ne!(result.status, "rejected");
 
+    // answered proof requests cannot be used again
+    let result: ProofVerification =
+        verify_proof(&mut vade, &presented_proof, &proof_request).await?;
+    assert_eq!(result.status, "rejected");
+    assert_eq!(
+        result.reason,
+        Some("proof request has already been answered".to_owned()),
+    );
+
     Ok(())
 }
 
@@ -648,6 +668,7 @@ async fn can_revoke_credential() -> Result<(), Box<dyn Error>> {
         &mut vade,
         &definition,
         &credential_private_key,
+        &offer,
         &request,
         &revocation_key_private,
         &revocation_info,
@@ -724,6 +745,7 @@ async fn can_verify_proof_after_revocation_update() -> Result<(), Box<dyn Error>
         &mut vade,
         &definition,
         &credential_private_key,
+        &offer,
         &request,
         &revocation_key_private,
         &revocation_info,
@@ -758,6 +780,7 @@ async fn can_verify_proof_after_revocation_update() -> Result<(), Box<dyn Error>
         &mut vade,
         &definition,
         &credential_private_key,
+        &other_offer,
         &other_request,
         &revocation_key_private,
         &revocation_info,
@@ -840,6 +863,7 @@ async fn can_verify_proof_after_multiple_revocation_updates() -> Result<(), Box<
         &mut vade,
         &definition,
         &credential_private_key,
+        &offer,
         &request,
         &revocation_key_private,
         &revocation_info,
@@ -877,6 +901,7 @@ async fn can_verify_proof_after_multiple_revocation_updates() -> Result<(), Box<
         &mut vade,
         &definition,
         &credential_private_key,
+        &other_offer,
         &other_request,
         &revocation_key_private,
         &revocation_info,
@@ -911,6 +936,7 @@ async fn can_verify_proof_after_multiple_revocation_updates() -> Result<(), Box<
         &mut vade,
         &definition,
         &credential_private_key,
+        &third_offer,
         &third_request,
         &revocation_key_private,
         &revocation_info,
@@ -1239,6 +1265,7 @@ async fn issue_credential(
     vade: &mut Vade,
     definition: &CredentialDefinition,
     credential_private_key: &CredentialPrivateKey,
+    offer: &CredentialOffer,
     request: &CredentialRequest,
     revocation_key_private: &RevocationKeyPrivate,
     revocation_info: &RevocationIdInformation,
@@ -1249,6 +1276,7 @@ async fn issue_credential(
         r###"{{
             "issuer": "{}",
             "subject": "{}",
+            "credentialOffer": {},
             "credentialRequest": {},
             "credentialDefinition": {},
             "credentialPrivateKey": {},
@@ -1259,6 +1287,7 @@ async fn issue_credential(
         }}"###,
         ISSUER_DID,
         SUBJECT_DID,
+        serde_json::to_string(&offer).unwrap(),
         serde_json::to_string(&request).unwrap(),
         serde_json::to_string(&definition).unwrap(),
         serde_json::to_string(&credential_private_key).unwrap(),
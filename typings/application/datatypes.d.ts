@@ -14,19 +14,23 @@
   limitations under the License.
 */
 
-import { AssertionProof } from '../crypto/crypto_datatypes';
+import { AssertionProof, EncryptedData } from '../crypto/crypto_datatypes';
 import {
+  AggregatedProof as CryptoAggregatedProof,
   BlindedCredentialSecrets,
   BlindedCredentialSecretsCorrectnessProof,
   CredentialKeyCorrectnessProof,
   CredentialPublicKey,
+  CredentialSecretsBlindingFactors,
   CryptoCredentialSignature,
+  MasterSecret,
   Nonce,
   RevocationKeyPublic,
   RevocationRegistry,
   RevocationRegistryDelta,
   RevocationTailsGenerator,
   SignatureCorrectnessProof,
+  SubProof,
   Witness,
 } from '../external';
 
@@ -81,6 +85,12 @@ export interface CredentialOffer {
   schema: string;
   credentialDefinition: string;
   nonce: Nonce;
+  createdAt: string;
+  /**
+   * Date after which the offer cannot be used for issuance anymore, offers created before
+   * this field has been added do not have it and cannot be used for issuance
+   */
+  expiresAt: string;
 }
 
 /**
@@ -122,7 +132,9 @@ export interface CredentialSubject {
 /**
  * A verifiable credential issued by an issuer upon receiving a `CredentialRequest`.
  * Specifies the signed values, the DID of the prover/subject, the `CredentialSchema`, and the `CredentialSignature`
- * including revocation info
+ * including revocation info.
+ * The optional `expirationDate` is not part of the signed values and only used by provers to
+ * skip expired credentials.
  */
 export interface Credential {
   '@context': string[];
@@ -130,6 +142,7 @@ export interface Credential {
   type: string[];
   issuer: string;
   issuanceDate: string;
+  expirationDate?: string;
   credentialSubject: CredentialSubject;
   credentialSchema: CredentialSchemaReference;
   proof: CredentialSignature;
@@ -148,7 +161,9 @@ export interface RevocationRegistryDefinition {
   updatedAt: string;
   registry: RevocationRegistry;
   registryDelta: RevocationRegistryDelta;
+  version?: number;
   deltaHistory: DeltaHistory[];
+  checkpoint?: DeltaCheckpoint;
   tails: RevocationTailsGenerator;
   revocationPublicKey: RevocationKeyPublic;
   maximumCredentialCount: number;
@@ -157,18 +172,29 @@ export interface RevocationRegistryDefinition {
 
 export interface DeltaHistory {
   created: number;
+  version?: number;
+  delta: RevocationRegistryDelta;
+}
+
+/**
+ * Merged state of all compacted `DeltaHistory` entries, created at the time of the newest compacted entry.
+ */
+export interface DeltaCheckpoint {
+  created: number;
+  version?: number;
   delta: RevocationRegistryDelta;
 }
 
 /**
  * Holds the current `Witness` for a credential. Witnesses need to be updated before creating proofs.
  * To do this, the prover needs to retrieve the `DeltaHistory` of the relevant `RevocationRegistryDefinition`
- * and update the witness with all deltas that are newer than the `updated` property of the `RevocationState`.
+ * and update the witness with all deltas with a `version` greater than the `version` of the `RevocationState`.
  */
 export interface RevocationState {
   credentialId: string;
   revocationId: number;
   updated: number;
+  version?: number;
   delta: RevocationRegistryDelta;
   witness: Witness;
 }
@@ -187,6 +213,16 @@ export interface CredentialProposal {
 export interface SubProofRequest {
   schema: string;
   revealedAttributes: string[];
+  restrictions?: CredentialRestriction[];
+}
+
+/**
+ * Restricts the credentials that can be used for a `SubProofRequest`. A credential has to match
+ * all given properties of at least one of the sub proof request's restrictions.
+ */
+export interface CredentialRestriction {
+  issuer?: string;
+  credentialDefinition?: string;
 }
 
 /**
@@ -196,20 +232,52 @@ export interface ProofRequest {
   verifier: string;
   prover: string;
   createdAt: string;
+  expiresAt?: string;
   nonce: Nonce;
   type: string;
   subProofRequests: SubProofRequest[];
+  nonRevoked?: NonRevokedInterval;
+}
+
+/**
+ * Time range (unix timestamps in seconds) in which the credentials of a proof have to be non-revoked.
+ * If `from` is omitted, the revocation registry state used for the proof has to be current at `to`.
+ */
+export interface NonRevokedInterval {
+  from?: number;
+  to: number;
+}
+
+/**
+ * Point in time to check non-revocation of a proof against, either a timestamp or a registry version.
+ */
+export interface RevocationPointInTime {
+  timestamp?: number;
+  version?: number;
 }
 
 export interface CredentialSubProof {
   credentialDefinition: string;
   revocationRegistryDefinition: string;
-  proof: string;
+  revocationRegistryVersion?: number;
+  /**
+   * Format version of `proof`, 2 for nested objects, 1 for proofs embedded as JSON string,
+   * guessed from `proof` if omitted
+   */
+  proofVersion?: number;
+  /** Ursa sub proof, older versions embedded it as JSON string, which is still accepted */
+  proof: SubProof | string;
 }
 
 export interface AggregatedProof {
   nonce: Nonce;
-  aggregatedProof: string;
+  /**
+   * Format version of `aggregatedProof`, 2 for nested objects, 1 for proofs embedded as JSON
+   * string, guessed from `aggregatedProof` if omitted
+   */
+  proofVersion?: number;
+  /** Ursa aggregated proof, older versions embedded it as JSON string, which is still accepted */
+  aggregatedProof: CryptoAggregatedProof | string;
 }
 
 /**
@@ -236,6 +304,10 @@ export interface ProofPresentation {
   type: string[];
   verifiableCredential: ProofCredential[];
   proof: AggregatedProof;
+  /** Proof that the presenter controls the prover DID of the proof request */
+  holderBinding?: AssertionProof;
+  /** Proof over the presentation, covering metadata not covered by the CL proof */
+  assertionProof?: AssertionProof;
 }
 
 export interface ProofVerification {
@@ -244,13 +316,357 @@ export interface ProofVerification {
   reason?: string;
 }
 
+/**
+ * Revocation status of a credential, either "active" or "revoked", as of the given version of its
+ * revocation registry definition.
+ */
+export interface RevocationStatus {
+  revocationRegistryDefinition: string;
+  revocationId: number;
+  version: number;
+  status: string;
+}
+
 export interface EncodedCredentialValue {
   raw: string;
   encoded: string;
 }
 
+/** A credential held by a prover together with its revocation state. */
+export interface HeldCredential {
+  credential: Credential;
+  revocationState: RevocationState;
+}
+
+/**
+ * Credentials selected for a `ProofRequest`, ready to be passed to `vc_zkp_present_proof`.
+ * `revocationStates` contains the updated states the `witnesses` were taken from, indexed by
+ * credential ID, and should be persisted by the prover.
+ */
+export interface CredentialSelection {
+  credentials: Record<string, Credential>;
+  witnesses: Record<string, Witness>;
+  revocationStates: Record<string, RevocationState>;
+}
+
+/**
+ * Versioned, encrypted backup of a prover's master secret and optionally held credentials.
+ * `encrypted` is authenticated, so altered content is rejected on import.
+ */
+export interface MasterSecretBackup {
+  type: string;
+  version: number;
+  createdAt: string;
+  encrypted: EncryptedData;
+}
+
+/** Decrypted content of a `MasterSecretBackup` */
+export interface MasterSecretBackupContent {
+  masterSecret: MasterSecret;
+  credentials?: HeldCredential[];
+}
+
 export interface RevocationIdInformation {
   definitionId: string;
   nextUnusedId: number;
   usedIds: number[];
 }
+
+/**
+ * Claims of a `W3cCredential` or `W3cProofCredential`, given as raw values next to the subject's ID.
+ * Exported claims are strings, imported claims with other values are converted to their JSON text.
+ */
+export interface W3cCredentialSubject {
+  id: string;
+  [claim: string]: any;
+}
+
+/**
+ * Status of a `W3cCredential` or `W3cProofCredential`, pointing at the revocation registry definition
+ * the credential can be revoked in. Proof credentials hide the credential's `revocationId` and
+ * reference the registry version the proof has been created with instead.
+ */
+export interface W3cCredentialStatus {
+  id: string;
+  type: string;
+  revocationRegistryDefinition: string;
+  revocationId?: number;
+  revocationRegistryVersion?: number;
+}
+
+/** CL signature of a `W3cCredential`, i.e. a `CredentialSignature` without revocation information */
+export interface W3cCredentialSignature {
+  type: string;
+  credentialDefinition: string;
+  signature: CryptoCredentialSignature;
+  signatureCorrectnessProof: SignatureCorrectnessProof;
+  issuanceNonce: Nonce;
+}
+
+/** `Credential` in the form of the W3C Verifiable Credentials Data Model 1.1 */
+export interface W3cCredential {
+  '@context': string[];
+  id: string;
+  type: string[];
+  issuer: string;
+  issuanceDate: string;
+  expirationDate?: string;
+  credentialSubject: W3cCredentialSubject;
+  credentialSchema: CredentialSchemaReference;
+  credentialStatus: W3cCredentialStatus;
+  proof: W3cCredentialSignature;
+}
+
+/** CL sub proof of a `W3cProofCredential` */
+export interface W3cSubProof {
+  type: string;
+  credentialDefinition: string;
+  proof: SubProof | string;
+}
+
+/** `ProofCredential` in the form of the W3C Verifiable Credentials Data Model 1.1 */
+export interface W3cProofCredential {
+  '@context': string[];
+  id: string;
+  type: string[];
+  issuer: string;
+  issuanceDate: string;
+  credentialSubject: W3cCredentialSubject;
+  credentialSchema: CredentialSchemaReference;
+  credentialStatus: W3cCredentialStatus;
+  proof: W3cSubProof;
+}
+
+/** Aggregated CL proof of a `W3cPresentation` */
+export interface W3cAggregatedProof {
+  type: string;
+  nonce: Nonce;
+  aggregatedProof: CryptoAggregatedProof | string;
+}
+
+/** `ProofPresentation` in the form of the W3C Verifiable Credentials Data Model 1.1 */
+export interface W3cPresentation {
+  '@context': string[];
+  id: string;
+  type: string[];
+  verifiableCredential: W3cProofCredential[];
+  proof: W3cAggregatedProof;
+  holderBinding?: AssertionProof;
+  assertionProof?: AssertionProof;
+}
+
+/** Credential schema in the AnonCreds format */
+export interface AnonCredsSchema {
+  ver: string;
+  id: string;
+  name: string;
+  version: string;
+  attrNames: string[];
+  seqNo?: number;
+}
+
+/** Credential definition in the AnonCreds format */
+export interface AnonCredsCredentialDefinition {
+  ver: string;
+  id: string;
+  schemaId: string;
+  type: string;
+  tag: string;
+  value: AnonCredsCredentialDefinitionValue;
+}
+
+/** Primary and revocation part of a `CredentialPublicKey`, as used in AnonCreds credential definitions */
+export interface AnonCredsCredentialDefinitionValue {
+  primary: any;
+  revocation?: any;
+}
+
+/** Revocation registry definition in the AnonCreds format */
+export interface AnonCredsRevocationRegistryDefinition {
+  ver: string;
+  id: string;
+  revocDefType: string;
+  tag: string;
+  credDefId: string;
+  value: AnonCredsRevocationRegistryDefinitionValue;
+}
+
+export interface AnonCredsRevocationRegistryDefinitionValue {
+  issuanceType: string;
+  maxCredNum: number;
+  publicKeys: AnonCredsRevocationRegistryPublicKeys;
+  tailsHash: string;
+  tailsLocation: string;
+}
+
+export interface AnonCredsRevocationRegistryPublicKeys {
+  accumKey: RevocationKeyPublic;
+}
+
+/** Current accumulator of a revocation registry in the AnonCreds format */
+export interface AnonCredsRevocationRegistry {
+  ver: string;
+  value: RevocationRegistry;
+}
+
+/** Credential in the AnonCreds format */
+export interface AnonCredsCredential {
+  schema_id: string;
+  cred_def_id: string;
+  rev_reg_id: string | null;
+  values: Record<string, EncodedCredentialValue>;
+  signature: CryptoCredentialSignature;
+  signature_correctness_proof: SignatureCorrectnessProof;
+  rev_reg?: RevocationRegistry | null;
+  witness?: Witness | null;
+}
+
+/** Proof request in the AnonCreds format. Predicates are not supported by this crate. */
+export interface AnonCredsProofRequest {
+  name: string;
+  version: string;
+  nonce: Nonce;
+  requested_attributes: Record<string, AnonCredsAttributeInfo>;
+  requested_predicates?: Record<string, any>;
+  non_revoked?: NonRevokedInterval;
+}
+
+/** Attribute or group of attributes (`names`) requested in an `AnonCredsProofRequest` */
+export interface AnonCredsAttributeInfo {
+  name?: string;
+  names?: string[];
+  restrictions?: AnonCredsRestriction[];
+  non_revoked?: NonRevokedInterval;
+}
+
+export interface AnonCredsRestriction {
+  schema_id?: string;
+  issuer_did?: string;
+  cred_def_id?: string;
+}
+
+/** Proof in the AnonCreds format, answering an `AnonCredsProofRequest` */
+export interface AnonCredsProof {
+  proof: AnonCredsProofData;
+  requested_proof: AnonCredsRequestedProof;
+  identifiers: AnonCredsIdentifier[];
+}
+
+export interface AnonCredsProofData {
+  proofs: SubProof[];
+  aggregated_proof: CryptoAggregatedProof;
+}
+
+export interface AnonCredsRequestedProof {
+  revealed_attrs?: Record<string, AnonCredsRevealedAttribute>;
+  revealed_attr_groups?: Record<string, AnonCredsRevealedAttributeGroup>;
+  self_attested_attrs?: Record<string, string>;
+  unrevealed_attrs?: Record<string, any>;
+  predicates?: Record<string, any>;
+}
+
+export interface AnonCredsRevealedAttribute {
+  sub_proof_index: number;
+  raw: string;
+  encoded: string;
+}
+
+export interface AnonCredsRevealedAttributeGroup {
+  sub_proof_index: number;
+  values: Record<string, EncodedCredentialValue>;
+}
+
+/** Identifies schema, credential definition and revocation registry of a sub proof of an `AnonCredsProof` */
+export interface AnonCredsIdentifier {
+  schema_id: string;
+  cred_def_id: string;
+  rev_reg_id: string | null;
+  timestamp: number | null;
+}
+
+/**
+ * DIDComm message of the Aries issue-credential and present-proof protocols. Message specific
+ * properties, like attachments, are kept next to the common ones.
+ */
+export interface DidCommMessage {
+  '@type': string;
+  '@id': string;
+  '~thread'?: DidCommThread;
+  comment?: string;
+  formats?: DidCommAttachmentFormat[];
+  [property: string]: any;
+}
+
+/** Thread decorator of a `DidCommMessage`, `thid` is the ID of the thread's first message */
+export interface DidCommThread {
+  thid: string;
+  pthid?: string;
+}
+
+/** Format of an attachment of a `DidCommMessage`, used by version 2.0 of the protocols */
+export interface DidCommAttachmentFormat {
+  attach_id: string;
+  format: string;
+}
+
+export interface DidCommAttachment {
+  '@id': string;
+  'mime-type': string;
+  data: DidCommAttachmentData;
+}
+
+/** Content of a `DidCommAttachment`, either base64url encoded or inlined as JSON */
+export interface DidCommAttachmentData {
+  base64?: string;
+  json?: any;
+}
+
+/** State of a credential issuance exchange, as seen by either issuer or holder */
+export type IssuanceState =
+  | 'proposalSent'
+  | 'proposalReceived'
+  | 'offerSent'
+  | 'offerReceived'
+  | 'requestSent'
+  | 'requestReceived'
+  | 'credentialIssued'
+  | 'done'
+  | 'abandoned';
+
+/** State of a proof presentation exchange, as seen by either verifier or holder */
+export type PresentationState =
+  | 'requestSent'
+  | 'requestReceived'
+  | 'presentationSent'
+  | 'done'
+  | 'abandoned';
+
+/** Record of a credential issuance exchange, identified by the ID of its DIDComm thread */
+export interface IssuanceExchange {
+  threadId: string;
+  protocol: string;
+  state: IssuanceState;
+  issuer: string;
+  subject: string;
+  schema: string;
+  offer?: CredentialOffer;
+  request?: CredentialRequest;
+  blindingFactors?: CredentialSecretsBlindingFactors;
+  credentialId?: string;
+  problem?: string;
+  updatedAt: string;
+}
+
+/** Record of a proof presentation exchange, identified by the ID of its DIDComm thread */
+export interface PresentationExchange {
+  threadId: string;
+  protocol: string;
+  state: PresentationState;
+  verifier: string;
+  prover: string;
+  proofRequest: ProofRequest;
+  presentationId?: string;
+  verification?: ProofVerification;
+  problem?: string;
+  updatedAt: string;
+}
@@ -21,3 +21,13 @@ export interface AssertionProof {
   verificationMethod: string;
   jws: string;
 }
+
+/** Data encrypted with a key derived from a passphrase, all binary values are base64url encoded */
+export interface EncryptedData {
+  kdf: string;
+  iterations: number;
+  salt: string;
+  cipher: string;
+  nonce: string;
+  ciphertext: string;
+}
@@ -18,6 +18,7 @@
 // there should usually be no need to work with them directly, as they can be passed
 // to vade api and returned without interaction with their inner data
 
+export interface AggregatedProof {}
 export interface BigNumber {}
 export interface BlindedCredentialSecrets {}
 export interface BlindedCredentialSecretsCorrectnessProof {}
@@ -34,4 +35,5 @@ export interface RevocationRegistry {}
 export interface RevocationRegistryDelta {}
 export interface RevocationTailsGenerator {}
 export interface SignatureCorrectnessProof {}
+export interface SubProof {}
 export interface Witness {}
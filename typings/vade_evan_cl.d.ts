@@ -18,10 +18,15 @@ import {
   Credential,
   CredentialDefinition,
   CredentialOffer,
+  CredentialProposal,
   CredentialRequest,
+  HeldCredential,
+  MasterSecretBackup,
+  NonRevokedInterval,
   ProofPresentation,
   ProofRequest,
   RevocationIdInformation,
+  RevocationPointInTime,
   RevocationRegistryDefinition,
   RevocationState,
   SchemaProperty,
@@ -52,6 +57,20 @@ export interface AuthenticationOptions {
   identity: string;
 }
 
+/** API payload to compact the delta history of a revocation registry definition */
+export interface CompactDeltaHistoryPayload {
+  /** DID of the issuer */
+  issuer: string;
+  /** DID of the revocation registry definition to compact */
+  revocationRegistryDefinition: string;
+  /** Number of newest delta history entries to keep uncompacted */
+  keepLatest: number;
+  /** DID of the issuer's public key to validate the registry's assertion proof */
+  issuerPublicKeyDid: string;
+  /** Secret key to sign the registry with */
+  issuerProvingKey: string;
+}
+
 /** API payload needed to create a credential definition needed for issuing credentials */
 export interface CreateCredentialDefinitionPayload {
   /** DID of the definition issuer/owner */
@@ -108,24 +127,139 @@ export interface CreateRevocationRegistryDefinitionResult {
   revocationRegistryDefinition: RevocationRegistryDefinition;
 }
 
+/**
+ * API payload to get the revocation status of a credential, either `credential` or
+ * `revocationId` and `revocationRegistryDefinition` have to be given
+ */
+export interface GetRevocationStatusPayload {
+  /** Credential to get the revocation status for */
+  credential?: Credential;
+  /** Revocation ID of the credential to get the revocation status for */
+  revocationId?: number;
+  /** DID of the revocation registry definition the credential belongs to */
+  revocationRegistryDefinition?: string;
+}
+
+/** API payload for exporting a master secret into an encrypted backup */
+export interface ExportMasterSecretPayload {
+  /** Passphrase to derive the backup's encryption key from */
+  passphrase: string;
+  /** Master secret to export, taken from the holder wallet if omitted */
+  masterSecret?: MasterSecret;
+  /** Credentials to include in the backup together with their revocation states */
+  credentials?: HeldCredential[];
+  /** Whether to include all credentials of the holder wallet in the backup */
+  includeWalletCredentials?: boolean;
+}
+
+/** API payload for encoding a presentation, proof request or credential as compact text */
+export interface EncodeCompactPayload {
+  /** Presentation, proof request or credential to encode */
+  document: ProofPresentation | ProofRequest | Credential;
+  /** Text encoding of the CBOR encoded document, either "base45" (default) or "base64url" */
+  encoding?: 'base45' | 'base64url';
+}
+
+/** API payload for decoding a document encoded with `encode_compact` */
+export interface DecodeCompactPayload {
+  /** Encoded document */
+  data: string;
+  /** Text encoding of the CBOR encoded document, either "base45" (default) or "base64url" */
+  encoding?: 'base45' | 'base64url';
+}
+
+/** API payload for importing an encrypted master secret backup */
+export interface ImportMasterSecretPayload {
+  /** Backup created with `export_master_secret` */
+  backup: MasterSecretBackup;
+  /** Passphrase the backup has been exported with */
+  passphrase: string;
+  /**
+   * Whether to store master secret and credentials of the backup in the holder wallet,
+   * replacing its current master secret
+   */
+  storeInWallet?: boolean;
+}
+
+/** API payload for selecting credentials for a proof request */
+export interface SelectCredentialsPayload {
+  /** Proof request sent by a verifier */
+  proofRequest: ProofRequest;
+  /**
+   * Credentials to select from together with their revocation states, all credentials of the
+   * holder wallet are used if omitted
+   */
+  credentials?: HeldCredential[];
+  /**
+   * Pre-resolved revocation registry definitions referenced by their DIDs, documents missing
+   * here are resolved
+   */
+  documents?: Record<string, any>;
+}
+
+/** API payload to drop documents from the document cache */
+export interface InvalidateDocumentCachePayload {
+  /** DID of the document to drop, drops all cached documents if omitted */
+  did?: string;
+}
+
+/** Configuration of the cache for resolved documents */
+export interface DocumentCacheConfig {
+  /** Whether resolved documents are cached at all */
+  enabled: boolean;
+  /**
+   * Seconds to cache revocation registry definitions and DID documents for, 0 disables caching
+   * them; schemas and credential definitions do not change and are cached until invalidated.
+   * Revocation registry definitions used for issuance, revocation and verification are always
+   * resolved, so they are checked against the latest accumulator
+   */
+  registryTtl: number;
+}
+
+/** Hit and miss counters of the cache for resolved documents */
+export interface DocumentCacheStats {
+  hits: number;
+  /** Number of documents resolved instead of taken from the cache */
+  misses: number;
+  entries: number;
+}
+
 /** API payload needed to issue a new credential */
 export interface IssueCredentialPayload {
   /** DID of the credential issuer */
   issuer: string;
   /** Date of issuance */
   issuanceDate?: string;
+  /** Date of expiration, not part of the signed values */
+  expirationDate?: string;
   /** DID of the credential subject */
   subject: string;
+  /**
+   * Credential offer the credential request has been created for, as offers are not signed, it
+   * has to be taken from the issuer's own records. If an issuer storage is set, the offer
+   * stored by `vc_zkp_create_credential_offer` is used and this one has to be equal to it or
+   * omitted, otherwise it is required.
+   */
+  credentialOffer?: CredentialOffer;
   /** Credential request sent by the subject */
   credentialRequest: CredentialRequest;
   /** DID of the associated revocation definition */
   credentialRevocationDefinition: string;
-  /** Key to create the credential signature */
-  credentialPrivateKey: CredentialPrivateKey;
-  /** Key to make this credential revokable */
-  revocationPrivateKey: RevocationKeyPrivate;
-  /** Tracker of current and next revocation IDs to use */
-  revocationInformation: RevocationIdInformation;
+  /** Key to create the credential signature, loaded from issuer storage if omitted */
+  credentialPrivateKey?: CredentialPrivateKey;
+  /** ID of the credential private key in issuer storage, defaults to the credential definition's DID */
+  credentialPrivateKeyId?: string;
+  /** Key to make this credential revokable, loaded from issuer storage if omitted */
+  revocationPrivateKey?: RevocationKeyPrivate;
+  /** ID of the revocation private key in issuer storage, defaults to the revocation definition's DID */
+  revocationPrivateKeyId?: string;
+  /** Tracker of current and next revocation IDs to use, revocation ID is allocated with issuer storage if omitted */
+  revocationInformation?: RevocationIdInformation;
+  /**
+   * Pre-resolved documents (schemas, credential definitions and revocation registry definitions)
+   * referenced by their DIDs, documents missing here are resolved
+   */
+  documents?: Record<string, any>;
 }
 
 /** API payload needed to finish a blinded credential signature by a holder/subject */
@@ -136,12 +270,20 @@ export interface FinishCredentialPayload {
   credentialRequest: CredentialRequest;
   /** DID of the revocation registry definition */
   credentialRevocationDefinition: string;
-  /** Blinding factors created during credential request creation */
-  blindingFactors: CredentialSecretsBlindingFactors;
-  /** Master secret to incorporate into the signature */
-  masterSecret: MasterSecret;
+  /**
+   * Blinding factors created during credential request creation, taken from the holder
+   * wallet if omitted
+   */
+  blindingFactors?: CredentialSecretsBlindingFactors;
+  /** Master secret to incorporate into the signature, taken from the holder wallet if omitted */
+  masterSecret?: MasterSecret;
   /** Current revocation state of the credential */
   revocationState: RevocationState;
+  /**
+   * Pre-resolved documents (schemas, credential definitions and revocation registry definitions)
+   * referenced by their DIDs, documents missing here are resolved
+   */
+  documents?: Record<string, any>;
 }
 
 /** Result of a call to issue_credential */
@@ -164,18 +306,38 @@ export interface OfferCredentialPayload {
   schema: string;
   /** DID of the credential definition of the credential to be issued */
   credentialDefinition: string;
+  /** Number of seconds the offer can be used for issuance, defaults to one day */
+  validity?: number;
 }
 
 /** API payload for creating proofs */
 export interface PresentProofPayload {
   /** Proof request sent by a verifier */
   proofRequest: ProofRequest;
-  /** Map of credentials referenced by their schema DIDs for all of the requested credentials */
-  credentials: Record<string, Credential>;
-  /** All of the updated witnesses referenced by their associated credential's schema DID */
-  witnesses: Record<string, Witness>;
-  /** The holder's master secret */
-  masterSecret: MasterSecret;
+  /**
+   * Map of credentials referenced by their schema DIDs for all of the requested credentials,
+   * selected from the holder wallet if omitted
+   */
+  credentials?: Record<string, Credential>;
+  /**
+   * All of the updated witnesses referenced by their associated credential's schema DID,
+   * taken from the holder wallet if credentials are omitted
+   */
+  witnesses?: Record<string, Witness>;
+  /** The holder's master secret, taken from the holder wallet if omitted */
+  masterSecret?: MasterSecret;
+  /**
+   * DID of the prover's public key to validate the holder binding and the assertion proof with,
+   * both are added if this and `proverProvingKey` are given
+   */
+  proverPublicKeyDid?: string;
+  /** Secret key to sign the holder binding and the assertion proof with */
+  proverProvingKey?: string;
+  /**
+   * Pre-resolved documents (schemas, credential definitions and revocation registry definitions)
+   * referenced by their DIDs, documents missing here are resolved
+   */
+  documents?: Record<string, any>;
 }
 
 /** API payload for creating a credential proposal */
@@ -194,10 +356,12 @@ export interface RequestCredentialPayload {
   credentialOffering: CredentialOffer;
   /** DID of the schema */
   credentialSchema: string;
-  /** The holder's master secret */
-  masterSecret: MasterSecret;
+  /** The holder's master secret, taken from the holder wallet if omitted */
+  masterSecret?: MasterSecret;
   /** Key-value pairs to be signed in the credential */
   credentialValues: Record<string, string>;
+  /** Proposal previously sent to the issuer, the offering is checked against it if given */
+  credentialProposal?: CredentialProposal;
 }
 
 export type RequestCredentialResult = [CredentialRequest, CredentialSecretsBlindingFactors];
@@ -210,6 +374,10 @@ export interface RequestProofPayload {
   proverDid: string;
   /** List of subproof requests, each requiring the proof of one credential signature */
   subProofRequests: SubProofRequest[];
+  /** Time range in which the proven credentials have to be non-revoked */
+  nonRevoked?: NonRevokedInterval;
+  /** Number of seconds the request can be answered for, does not expire if omitted */
+  validity?: number;
 }
 
 /** API payload to revoke a credential */
@@ -232,4 +400,24 @@ export interface ValidateProofPayload {
   presentedProof: ProofPresentation;
   /** Proof request that was sent to the holder/prover */
   proofRequest: ProofRequest;
+  /**
+   * Point in time to check non-revocation against, defaults to the latest revocation registry
+   * version or, if the proof request has a `nonRevoked` interval, the revocation registry
+   * version the holder/prover has used for the proof, if it has been current in that interval;
+   * audits presentations if set, i.e. neither rejects expired proof requests nor marks
+   * proof requests as answered
+   */
+  revocationPointInTime?: RevocationPointInTime;
+  /** Rejects proofs without a holder binding if set, holder bindings are checked if present */
+  requireHolderBinding?: boolean;
+  /**
+   * Rejects proofs without an assertion proof over the presentation if set,
+   * assertion proofs are checked if present
+   */
+  requireAssertionProof?: boolean;
+  /**
+   * Pre-resolved documents (schemas, credential definitions, revocation registry definitions
+   * and the prover's DID document) referenced by their DIDs, documents missing here are resolved
+   */
+  documents?: Record<string, any>;
 }
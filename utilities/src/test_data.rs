@@ -120,6 +120,12 @@ pub mod environment {
 
 #[allow(dead_code)]
 pub mod vc_zkp {
+    #[allow(dead_code)]
+    pub const EXAMPLE_AGGREGATED_PROOF: &str = r###"{
+        "c_hash": "1",
+        "c_list": [[1, 2, 3]]
+    }"###;
+
     #[allow(dead_code)]
     pub const EXAMPLE_CREDENTIAL_SCHEMA: &str = r###"
     {
@@ -151,6 +157,24 @@ pub mod vc_zkp {
     pub const EXAMPLE_REVOCATION_REGISTRY_DEFINITION_DID: &str =
         "did:evan:testcore:0x0F737D1478eA29df0856169F25cA9129035d6FD2";
 
+    #[allow(dead_code)]
+    pub const EXAMPLE_SUB_PROOF: &str = r###"{
+        "primary_proof": {
+            "eq_proof": {
+                "revealed_attrs": {
+                    "test_property_string": "1234"
+                },
+                "a_prime": "1",
+                "e": "2",
+                "v": "3",
+                "m": {},
+                "m2": "4"
+            },
+            "ge_proofs": []
+        },
+        "non_revoc_proof": null
+    }"###;
+
     #[allow(dead_code)]
     pub const SCHEMA_DESCRIPTION: &str = "Test description";
 